@@ -0,0 +1,54 @@
+//! Times `search::iterative` against `search::parallel` on a few boards
+//! too big to solve, and checks they agree.  Run it with
+//!
+//! ```text
+//! cargo bench --bench search
+//! ```
+//!
+//! Each row is the fastest of three runs.  The speedup can only approach
+//! the number of threads when there are that many cores to run them.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tic_tac_toe::mnk::{MnkBoard, Variant};
+use tic_tac_toe::search::{self, Control, Limits, Progress};
+
+const RUNS: usize = 3;
+
+/// A variant and how many plies deep to search it.
+const WORKLOADS: [(&str, u32); 3] = [("4x4", 11), ("5x5k4", 8), ("4x5k4", 9)];
+
+fn fastest(run: impl Fn() -> Progress<usize>) -> (Duration, Progress<usize>) {
+    (0..RUNS)
+        .map(|_| {
+            let started = Instant::now();
+            let progress = run();
+            (started.elapsed(), progress)
+        })
+        .min_by_key(|(elapsed, _)| *elapsed)
+        .expect("RUNS is not 0")
+}
+
+fn main() {
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    let mut counts = vec![2, 4, cores];
+    counts.sort_unstable();
+    counts.dedup();
+    println!("{} cores", cores);
+    println!("{:<8} {:>5} {:>8} {:>10} {:>12} {:>8}", "board", "depth", "threads", "nodes", "time", "speedup");
+
+    for (label, depth) in WORKLOADS {
+        let board = MnkBoard::new(Variant::from_label(label).expect("workloads are valid variants"));
+        let limits = Limits { depth: Some(depth), ..Limits::default() };
+        let (sequential, expected) = fastest(|| search::iterative(&board, limits, &Control::default()));
+        println!("{:<8} {:>5} {:>8} {:>10} {:>12.1?} {:>8}", label, depth, "-", expected.nodes, sequential, "1.00");
+
+        for &threads in &counts {
+            let (elapsed, progress) = fastest(|| search::parallel(&board, limits, &Control::default(), threads));
+            assert_eq!(progress, expected, "{} threads gave a different result on {}", threads, label);
+            let speedup = sequential.as_secs_f64() / elapsed.as_secs_f64();
+            println!("{:<8} {:>5} {:>8} {:>10} {:>12.1?} {:>8.2}", label, depth, threads, progress.nodes, elapsed, speedup);
+        }
+    }
+}
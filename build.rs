@@ -0,0 +1,31 @@
+//! With the `header` feature, generates the C header from the `extern "C"`
+//! functions and constants in `src/ffi.rs` into `OUT_DIR`.  The copy in
+//! `include/tic_tac_toe.h` is checked in; `tests/ffi.rs` fails if the two
+//! differ, so it can't drift from the source.
+
+fn main() {
+    #[cfg(feature = "header")]
+    header::generate();
+}
+
+#[cfg(feature = "header")]
+mod header {
+    use std::env;
+    use std::path::Path;
+
+    pub fn generate() {
+        let root = env::var("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR");
+        let root = Path::new(&root);
+        let out = env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+
+        let config = cbindgen::Config::from_file(root.join("cbindgen.toml")).expect("cbindgen.toml is valid");
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(root.join("src/ffi.rs"))
+            .generate()
+            .expect("src/ffi.rs parses")
+            .write_to_file(Path::new(&out).join("tic_tac_toe.h"));
+    }
+}
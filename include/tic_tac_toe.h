@@ -0,0 +1,142 @@
+/*
+ * C interface to the tic-tac-toe engine, implemented in src/ffi.rs.
+ *
+ * Link against libtic_tac_toe.so, or libtic_tac_toe.a with
+ * -lpthread -ldl -lm.  Every call but ttt_game_free and
+ * ttt_error_message returns a TTT_ code and writes its answer through an
+ * out pointer, which is left alone on error.  Squares are named A1 to C3:
+ * the letter is the row and the digit the column.
+ */
+
+#ifndef TIC_TAC_TOE_H
+#define TIC_TAC_TOE_H
+
+/* Generated from src/ffi.rs by build.rs (cargo build --features header); edit that instead. */
+
+#include <stddef.h>
+
+#define TTT_OK 0
+
+#define TTT_ERR_NULL 1
+
+#define TTT_ERR_BAD_PLAYER 2
+
+#define TTT_ERR_BAD_SQUARE 3
+
+#define TTT_ERR_SQUARE_TAKEN 4
+
+#define TTT_ERR_GAME_OVER 5
+
+#define TTT_ERR_COMPUTER_TURN 6
+
+#define TTT_ERR_HUMAN_TURN 7
+
+#define TTT_ERR_BUFFER_TOO_SMALL 8
+
+#define TTT_ERR_INTERNAL 9
+
+#define TTT_IN_PLAY 0
+
+#define TTT_TIED 1
+
+#define TTT_X_WON 2
+
+#define TTT_O_WON 3
+
+/**
+ * Bytes `ttt_game_board` writes: `X--/-O-/---` and a NUL.
+ */
+#define TTT_BOARD_SIZE 12
+
+/**
+ * The handle behind `ttt_game *`.
+ */
+typedef struct ttt_game ttt_game;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Starts a game between `x` and `o`, each a player type label such as
+ * `"human"` or `"computer:perfect"`, or null for a human.
+ *
+ * # Safety
+ *
+ * `x` and `o` must each be null or a NUL-terminated string, and `out`
+ * must be null or valid for a write.
+ */
+int ttt_game_new(const char *x, const char *o, ttt_game **out);
+
+/**
+ * Releases a game.  Null is ignored.
+ *
+ * # Safety
+ *
+ * `game` must be null or come from `ttt_game_new`, and must not be used
+ * again.
+ */
+void ttt_game_free(ttt_game *game);
+
+/**
+ * Plays `square`, such as `"B2"`, for the human whose turn it is.
+ *
+ * # Safety
+ *
+ * `game` must be null or a live handle, and `square` null or a
+ * NUL-terminated string.
+ */
+int ttt_make_move(ttt_game *game, const char *square);
+
+/**
+ * Lets the computer whose turn it is move.
+ *
+ * # Safety
+ *
+ * `game` must be null or a live handle.
+ */
+int ttt_computer_move(ttt_game *game);
+
+/**
+ * Writes `TTT_IN_PLAY`, `TTT_TIED`, `TTT_X_WON` or `TTT_O_WON`.
+ *
+ * # Safety
+ *
+ * `game` must be null or a live handle, and `status` null or valid for a
+ * write.
+ */
+int ttt_game_status(const ttt_game *game, int *status);
+
+/**
+ * Writes `'X'` or `'O'` for the side to move, or fails with
+ * `TTT_ERR_GAME_OVER`.
+ *
+ * # Safety
+ *
+ * `game` must be null or a live handle, and `mark` null or valid for a
+ * write.
+ */
+int ttt_game_turn(const ttt_game *game, char *mark);
+
+/**
+ * Writes the board as rows A, B and C of `X`, `O` and `-` joined by `/`,
+ * NUL-terminated, into `buffer` of `size` bytes.
+ *
+ * # Safety
+ *
+ * `game` must be null or a live handle, and `buffer` null or valid for
+ * `size` bytes of writes.
+ */
+int ttt_game_board(const ttt_game *game, char *buffer, size_t size);
+
+/**
+ * A short English description of a `TTT_` code, for logs.  The string
+ * is static and must not be freed.
+ */
+const char *ttt_error_message(int code);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TIC_TAC_TOE_H */
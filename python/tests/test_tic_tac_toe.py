@@ -0,0 +1,112 @@
+"""Tests for the tic_tac_toe extension module; see src/python.rs."""
+
+import unittest
+
+import tic_tac_toe as ttt
+
+
+class BoardTest(unittest.TestCase):
+    def test_parse_and_show(self):
+        board = ttt.Board("X--/-O-/---")
+        self.assertEqual(board, ttt.Board("X--\n-O-\n---"))
+        self.assertEqual(board.rows, ["X--", "-O-", "---"])
+        self.assertEqual(str(board), "X--/-O-/---")
+        self.assertEqual(repr(board), "Board('X--/-O-/---')")
+        self.assertEqual(board["a1"], "X")
+        self.assertEqual(board["B2"], "O")
+        self.assertEqual(board["C3"], "-")
+        self.assertEqual(ttt.Board().rows, ["---", "---", "---"])
+
+    def test_bad_input(self):
+        for rows in ["X--/---", "XX/---/---", "Q--/---/---"]:
+            with self.assertRaises(ValueError):
+                ttt.Board(rows)
+        with self.assertRaises(ValueError):
+            ttt.Board()["D1"]
+
+    def test_queries(self):
+        board = ttt.Board("XXX/OO-/---")
+        self.assertEqual(board.winner(), "X")
+        self.assertEqual(board.winning_line(), ["A1", "A2", "A3"])
+        self.assertEqual(board.side_to_move(), "O")
+        self.assertEqual(board.empty_squares(), ["B3", "C1", "C2", "C3"])
+        self.assertIsNone(ttt.Board().winner())
+        self.assertIsNone(ttt.Board("XX-/---/---").side_to_move())
+
+    def test_play_returns_a_new_board(self):
+        board = ttt.Board()
+        after = board.play("B2").play("A1")
+        self.assertEqual(after.rows, ["O--", "-X-", "---"])
+        self.assertEqual(board, ttt.Board())
+        with self.assertRaises(ValueError):
+            after.play("B2")
+
+
+class AnalysisTest(unittest.TestCase):
+    def test_evaluate(self):
+        self.assertEqual(ttt.evaluate(ttt.Board()), 0)
+        self.assertEqual(ttt.evaluate(ttt.Board("XX-/OO-/---")), 1)
+        with self.assertRaises(ValueError):
+            ttt.evaluate(ttt.Board("OO-/---/---"))
+
+    def test_analyse(self):
+        scores = ttt.analyse(ttt.Board("XX-/OO-/---"))
+        self.assertEqual(sorted(scores), ["A3", "B3", "C1", "C2", "C3"])
+        self.assertEqual(scores["A3"], 1)
+        self.assertEqual(scores["C1"], -1)
+        self.assertEqual(ttt.analyse(ttt.Board("XXX/OO-/---")), {})
+
+    def test_search(self):
+        stats = ttt.search(ttt.Board("XX-/OO-/---"), "3000")
+        self.assertEqual(sorted(stats), ["A3", "B3", "C1", "C2", "C3"])
+        self.assertEqual(sum(visits for visits, _ in stats.values()), 3000)
+        self.assertEqual(max(stats, key=lambda square: stats[square][0]), "A3")
+        self.assertGreater(stats["A3"][1], 0.9)
+        self.assertEqual(ttt.search(ttt.Board("XXX/OO-/---")), {})
+        with self.assertRaises(ValueError):
+            ttt.search(ttt.Board(), "soon")
+
+    def test_positions(self):
+        self.assertEqual(ttt.parse_position(" b3 "), (1, 2))
+        self.assertEqual(ttt.position_name((2, 0)), "C1")
+        with self.assertRaises(ValueError):
+            ttt.parse_position("B4")
+        with self.assertRaises(ValueError):
+            ttt.position_name((3, 0))
+
+
+class GameTest(unittest.TestCase):
+    def test_human_game(self):
+        game = ttt.Game(x="human", o="human")
+        self.assertEqual(game.players, {"x": "human", "o": "human"})
+        for square in ["A1", "B1", "A2", "B2", "A3"]:
+            game.play(square)
+        self.assertEqual(game.status, "won")
+        self.assertEqual(game.winner, "X")
+        self.assertIsNone(game.turn)
+        self.assertEqual(game.moves, ["A1", "B1", "A2", "B2", "A3"])
+        with self.assertRaises(ValueError):
+            game.play("C3")
+
+    def test_computer_game(self):
+        game = ttt.Game(x="computer:perfect", o="computer:perfect")
+        while game.status == "in_play":
+            square = game.computer_move()
+            self.assertEqual(game.moves[-1], square)
+        self.assertEqual(game.status, "tied")
+        self.assertEqual(len(game.board.empty_squares()), 0)
+
+    def test_mixed_game(self):
+        game = ttt.Game()
+        self.assertEqual(game.players, {"x": "human", "o": "computer:perfect"})
+        game.play("A1")
+        self.assertEqual(game.turn, "O")
+        self.assertEqual(game.computer_move(), "B2")
+        with self.assertRaises(ValueError):
+            game.play("B2")
+        with self.assertRaises(ValueError):
+            ttt.Game(x="engine")
+
+
+if __name__ == "__main__":
+    unittest.main()
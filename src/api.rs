@@ -0,0 +1,404 @@
+//! The HTTP JSON API behind `tic-tac-toe serve`; `web` adds the page.
+//!
+//! Games live in memory, each under a numeric ID, and are lost when the
+//! server stops.  At most [`MAX_GAMES`] are kept: a new game past that
+//! pushes out the finished game left alone longest, or failing one, the
+//! game in play left alone longest.  Every request and response body is
+//! JSON.
+//!
+//! | Request | Body | Answer |
+//! |---|---|---|
+//! | `POST /games` | `{"x": "human", "o": "computer:perfect"}`, both optional | `201` and the new game |
+//! | `GET /games` | | `200` and `{"games": [...]}` |
+//! | `GET /games/<id>` | | `200` and the game |
+//! | `POST /games/<id>/moves` | `{"square": "B2"}` | `200` and the game after a human move |
+//! | `POST /games/<id>/computer-move` | | `200` and the game after the computer's move |
+//!
+//! Player types are the labels of `PlayerType`, except `engine`, which
+//! needs a process the server doesn't have.  A game looks like:
+//!
+//! ```json
+//! {"id": 1, "status": "in_play", "turn": "O", "winner": null,
+//!  "players": {"x": "human", "o": "computer:perfect"},
+//!  "board": ["---", "-X-", "---"], "moves": ["B2"], "last_move": "B2",
+//!  "winning_line": null}
+//! ```
+//!
+//! `status` is `in_play`, `won` or `tied`; `turn` is null once the game is
+//! over and `winner` is null unless it was won.  `board` holds rows A, B
+//! and C of `X`, `O` and `-`.
+//!
+//! Errors come back with a 4xx status and
+//! `{"error": {"code": "square_taken", "message": "B2 is already taken"}}`.
+//! The codes are `bad_request`, `bad_player`, `bad_square`, `not_found`,
+//! `method_not_allowed`, `square_taken`, `game_over`, `computer_turn`,
+//! `human_turn` and `conflict`, the last when the game moved on while the
+//! computer was thinking.
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+use crate::board::{parse_position, position_name, Board};
+use crate::game::{self, Game, Player, PlayerSet, PlayerType};
+use crate::http::{Handler, Request, Response};
+use crate::json::Json;
+use crate::profile::PlayerNames;
+use crate::session::Session;
+
+struct ApiError {
+    status: u16,
+    code: &'static str,
+    message: String
+}
+
+fn error(status: u16, code: &'static str, message: &str) -> ApiError {
+    ApiError { status, code, message: message.to_string() }
+}
+
+impl ApiError {
+    fn response(&self) -> Response {
+        let body = Json::object([
+            ("error", Json::object([("code", Json::string(self.code)), ("message", Json::string(&self.message))]))
+        ]);
+        json_response(self.status, &body)
+    }
+}
+
+fn json_response(status: u16, body: &Json) -> Response {
+    Response::new(status, "application/json", body.to_string())
+}
+
+fn player_label(player: &Player) -> Json {
+    Json::string(&player.associated_square().notation().to_string())
+}
+
+fn board_rows(board: &Board) -> Json {
+    Json::Array(board.notation().lines().map(Json::string).collect())
+}
+
+fn squares(positions: &[(usize, usize)]) -> Json {
+    Json::Array(positions.iter().map(|pos| Json::string(&position_name(*pos))).collect())
+}
+
+/// The JSON shape of a game, as documented above.
+pub fn game_json(id: u64, session: &Session) -> Json {
+    let (status, turn, winner) = match &session.game {
+        Game::InPlay { turn, .. } => ("in_play", player_label(turn), Json::Null),
+        Game::Win(winner, _) => ("won", Json::Null, player_label(winner)),
+        Game::Tie(_) | Game::Uninitiated => ("tied", Json::Null, Json::Null)
+    };
+    let board = session.game.board().copied().unwrap_or_default();
+    let winning_line = game::winning_line(&board).map(|line| squares(&line)).unwrap_or(Json::Null);
+    let last_move = session.last_move().map(|pos| Json::string(&position_name(pos))).unwrap_or(Json::Null);
+
+    Json::object([
+        ("id", Json::Number(id as f64)),
+        ("status", Json::string(status)),
+        ("turn", turn),
+        ("winner", winner),
+        ("players", Json::object([
+            ("x", Json::string(&session.set.x.player_type().label())),
+            ("o", Json::string(&session.set.o.player_type().label()))
+        ])),
+        ("board", board_rows(&board)),
+        ("moves", squares(&session.history)),
+        ("last_move", last_move),
+        ("winning_line", winning_line)
+    ])
+}
+
+/// How many games the server keeps by default.
+pub const MAX_GAMES: usize = 1000;
+
+struct Stored {
+    session: Session,
+    /// When a request last found this game.
+    used: Instant
+}
+
+#[derive(Default)]
+struct Games {
+    next_id: u64,
+    sessions: BTreeMap<u64, Stored>
+}
+
+impl Games {
+    /// Makes room for one more game under `max`.
+    fn evict(&mut self, max: usize) {
+        while self.sessions.len() >= max.max(1) {
+            let oldest = self.sessions.iter()
+                .min_by_key(|(_, stored)| (matches!(stored.session.game, Game::InPlay { .. }), stored.used))
+                .map(|(id, _)| *id);
+            let Some(id) = oldest else { return; };
+            self.sessions.remove(&id);
+        }
+    }
+}
+
+/// Every game the server knows about, and the routes that play them.
+pub struct Api {
+    games: Mutex<Games>,
+    max_games: usize
+}
+
+impl Default for Api {
+    fn default() -> Self {
+        Api { games: Mutex::new(Games::default()), max_games: MAX_GAMES }
+    }
+}
+
+impl Handler for Api {
+    fn handle(&self, request: &Request) -> Response {
+        if request.method == "OPTIONS" {
+            return Response::new(204, "text/plain", String::new());
+        }
+        match self.route(request) {
+            Ok((status, body)) => json_response(status, &body),
+            Err(error) => error.response()
+        }
+    }
+}
+
+impl Api {
+    /// Keeps at most `max_games` games instead of `MAX_GAMES`.
+    pub fn with_max_games(mut self, max_games: usize) -> Api {
+        self.max_games = max_games;
+        self
+    }
+
+    fn route(&self, request: &Request) -> Result<(u16, Json), ApiError> {
+        let segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+        let method = request.method.as_str();
+        if let ("POST", ["games", id, "computer-move"]) = (method, segments.as_slice()) {
+            return self.computer_move(id);
+        }
+        let mut games = self.lock();
+
+        match (method, segments.as_slice()) {
+            ("POST", ["games"]) => {
+                let set = parse_players(&request.body)?;
+                games.evict(self.max_games);
+                games.next_id += 1;
+                let id = games.next_id;
+                let game = Game::InPlay { set, turn: set.x, board: Board::default() };
+                let session = Session::new(set, game, PlayerNames::default());
+                let body = game_json(id, &session);
+                games.sessions.insert(id, Stored { session, used: Instant::now() });
+                Ok((201, body))
+            },
+            ("GET", ["games"]) => {
+                let list = games.sessions.iter().map(|(id, stored)| game_json(*id, &stored.session)).collect();
+                Ok((200, Json::object([("games", Json::Array(list))])))
+            },
+            ("GET", ["games", id]) => {
+                let (id, session) = find(&mut games, id)?;
+                Ok((200, game_json(id, session)))
+            },
+            ("POST", ["games", id, "moves"]) => {
+                let (id, session) = find(&mut games, id)?;
+                human_move(session, &request.body)?;
+                Ok((200, game_json(id, session)))
+            },
+            (_, ["games"]) | (_, ["games", _]) | (_, ["games", _, "moves" | "computer-move"]) => {
+                Err(error(405, "method_not_allowed", &format!("{} is not allowed on {}", method, request.path)))
+            },
+            _ => Err(error(404, "not_found", &format!("nothing at {}", request.path)))
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Games> {
+        self.games.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Plays the computer's move in game `id` without holding the lock
+    /// while it thinks, so other games carry on meanwhile.
+    fn computer_move(&self, id: &str) -> Result<(u16, Json), ApiError> {
+        let (id, before) = {
+            let mut games = self.lock();
+            let (id, session) = find(&mut games, id)?;
+            (id, session.game)
+        };
+        let (set, turn, board) = computer_turn(&before)?;
+        let updated = game::computer_move(&turn, &set, &board)
+            .map_err(|e| error(500, "internal", &e.to_string()))?;
+
+        let mut games = self.lock();
+        let (id, session) = find(&mut games, &id.to_string())?;
+        if session.game != before {
+            return Err(error(409, "conflict", "the game moved on while the computer was thinking"));
+        }
+        session.advance(updated);
+        Ok((200, game_json(id, session)))
+    }
+}
+
+fn find<'a>(games: &'a mut Games, id: &str) -> Result<(u64, &'a mut Session), ApiError> {
+    let not_found = || error(404, "not_found", &format!("no game {}", id));
+    let id: u64 = id.parse().map_err(|_| not_found())?;
+    let stored = games.sessions.get_mut(&id).ok_or_else(not_found)?;
+    stored.used = Instant::now();
+    Ok((id, &mut stored.session))
+}
+
+fn parse_body(body: &str) -> Result<Json, ApiError> {
+    if body.trim().is_empty() {
+        return Ok(Json::Object(Vec::new()));
+    }
+    Json::parse(body).map_err(|e| error(400, "bad_request", &format!("invalid JSON: {}", e)))
+}
+
+fn parse_players(body: &str) -> Result<PlayerSet, ApiError> {
+    let body = parse_body(body)?;
+    let player_type = |key: &str| -> Result<PlayerType, ApiError> {
+        let Some(value) = body.get(key) else { return Ok(PlayerType::Human); };
+        let label = value.as_str().ok_or_else(|| error(400, "bad_player", &format!("{} must be a string", key)))?;
+        match PlayerType::from_label(label) {
+            Some(PlayerType::Engine) => Err(error(400, "bad_player", "engine players are not available over HTTP")),
+            Some(player_type) => Ok(player_type),
+            None => Err(error(400, "bad_player", &format!("unknown player type '{}'", label)))
+        }
+    };
+    Ok(PlayerSet { x: Player::X(player_type("x")?), o: Player::O(player_type("o")?) })
+}
+
+fn game_in_play(game: &Game) -> Result<(PlayerSet, Player, Board), ApiError> {
+    match *game {
+        Game::InPlay { set, turn, board } => Ok((set, turn, board)),
+        _ => Err(error(409, "game_over", "the game is over"))
+    }
+}
+
+fn human_move(session: &mut Session, body: &str) -> Result<(), ApiError> {
+    let (set, turn, board) = game_in_play(&session.game)?;
+    if turn.player_type() != &PlayerType::Human {
+        return Err(error(409, "computer_turn", "it is the computer's turn; POST to computer-move"));
+    }
+
+    let body = parse_body(body)?;
+    let square = body.get("square").and_then(Json::as_str)
+        .ok_or_else(|| error(400, "bad_request", "expected {\"square\": \"B2\"}"))?;
+    let pos = parse_position(square)
+        .ok_or_else(|| error(400, "bad_square", &format!("'{}' is not a square from A1 to C3", square)))?;
+
+    let updated = game::make_move(&pos, &board, &turn, &set)
+        .map_err(|_| error(409, "square_taken", &format!("{} is already taken", position_name(pos))))?;
+    session.advance(updated);
+    Ok(())
+}
+
+fn computer_turn(game: &Game) -> Result<(PlayerSet, Player, Board), ApiError> {
+    let (set, turn, board) = game_in_play(game)?;
+    if turn.player_type() == &PlayerType::Human {
+        return Err(error(409, "human_turn", "it is a human's turn; POST to moves"));
+    }
+    Ok((set, turn, board))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::{Handler, Request};
+    use crate::json::Json;
+
+    use super::Api;
+
+    fn call(api: &Api, method: &str, path: &str, body: &str) -> (u16, Json) {
+        let response = api.handle(&Request::new(method, path, body));
+        (response.status, Json::parse(&response.body).unwrap())
+    }
+
+    fn error_code(json: &Json) -> &str {
+        json.get("error").and_then(|e| e.get("code")).and_then(Json::as_str).unwrap()
+    }
+
+    #[test]
+    fn test_human_game() {
+        let api = Api::default();
+        let (status, game) = call(&api, "POST", "/games", "");
+        assert_eq!(status, 201);
+        assert_eq!(game.get("id"), Some(&Json::Number(1.0)));
+        assert_eq!(game.get("turn").and_then(Json::as_str), Some("X"));
+
+        for (square, status) in [("B2", 200), ("b2", 409), ("A1", 200), ("B1", 200), ("A2", 200), ("B3", 200)] {
+            let (got, _) = call(&api, "POST", "/games/1/moves", &format!("{{\"square\": \"{}\"}}", square));
+            assert_eq!(got, status, "{}", square);
+        }
+
+        let (status, game) = call(&api, "GET", "/games/1", "");
+        assert_eq!(status, 200);
+        assert_eq!(game.get("status").and_then(Json::as_str), Some("won"));
+        assert_eq!(game.get("winner").and_then(Json::as_str), Some("X"));
+        assert_eq!(game.get("turn"), Some(&Json::Null));
+        assert_eq!(game.get("last_move").and_then(Json::as_str), Some("B3"));
+        assert_eq!(game.get("winning_line").unwrap().to_string(), r#"["B1","B2","B3"]"#);
+        assert_eq!(game.get("board").unwrap().to_string(), r#"["OO-","XXX","---"]"#);
+
+        let (status, body) = call(&api, "POST", "/games/1/moves", r#"{"square": "C3"}"#);
+        assert_eq!((status, error_code(&body)), (409, "game_over"));
+    }
+
+    #[test]
+    fn test_computer_game() {
+        let api = Api::default();
+        let (_, game) = call(&api, "POST", "/games", r#"{"x": "computer:perfect", "o": "human"}"#);
+        assert_eq!(game.get("players").unwrap().to_string(), r#"{"x":"computer:perfect","o":"human"}"#);
+
+        let (status, body) = call(&api, "POST", "/games/1/moves", r#"{"square": "B2"}"#);
+        assert_eq!((status, error_code(&body)), (409, "computer_turn"));
+        let (status, game) = call(&api, "POST", "/games/1/computer-move", "");
+        assert_eq!(status, 200);
+        assert_eq!(game.get("moves").unwrap().to_string(), r#"["A1"]"#);
+        let (status, body) = call(&api, "POST", "/games/1/computer-move", "");
+        assert_eq!((status, error_code(&body)), (409, "human_turn"));
+    }
+
+    #[test]
+    fn test_old_games_make_way() {
+        let api = Api::default().with_max_games(3);
+        call(&api, "POST", "/games", "");
+        call(&api, "POST", "/games", "");
+        call(&api, "POST", "/games", "");
+        for square in ["B2", "A1", "B1", "A2", "B3"] {
+            call(&api, "POST", "/games/2/moves", &format!("{{\"square\": \"{}\"}}", square));
+        }
+        call(&api, "GET", "/games/1", "");
+
+        // The finished game goes first, then whichever was used least
+        // recently.
+        call(&api, "POST", "/games", "");
+        let (_, list) = call(&api, "GET", "/games", "");
+        assert!(matches!(list.get("games"), Some(Json::Array(games)) if games.len() == 3));
+        assert_eq!(call(&api, "GET", "/games/2", "").0, 404);
+        call(&api, "POST", "/games", "");
+        assert_eq!(call(&api, "GET", "/games/3", "").0, 404);
+        assert_eq!(call(&api, "GET", "/games/1", "").0, 200);
+    }
+
+    #[test]
+    fn test_errors() {
+        let api = Api::default();
+        let cases = [
+            ("POST", "/games", r#"{"x": "wizard"}"#, 400, "bad_player"),
+            ("POST", "/games", r#"{"o": "engine"}"#, 400, "bad_player"),
+            ("POST", "/games", "{", 400, "bad_request"),
+            ("GET", "/games/7", "", 404, "not_found"),
+            ("GET", "/games/seven", "", 404, "not_found"),
+            ("DELETE", "/games", "", 405, "method_not_allowed"),
+            ("GET", "/teapot", "", 404, "not_found")
+        ];
+        for (method, path, body, status, code) in cases {
+            let (got, json) = call(&api, method, path, body);
+            assert_eq!((got, error_code(&json)), (status, code), "{} {}", method, path);
+        }
+
+        call(&api, "POST", "/games", "");
+        let (status, body) = call(&api, "POST", "/games/1/moves", r#"{"square": "D4"}"#);
+        assert_eq!((status, error_code(&body)), (400, "bad_square"));
+        let (status, body) = call(&api, "POST", "/games/1/moves", r#"{"place": "B2"}"#);
+        assert_eq!((status, error_code(&body)), (400, "bad_request"));
+
+        let (status, list) = call(&api, "GET", "/games", "");
+        assert_eq!(status, 200);
+        assert!(matches!(list.get("games"), Some(Json::Array(games)) if games.len() == 1));
+    }
+}
@@ -1,3 +1,9 @@
+use std::io;
+use std::str::FromStr;
+
+use crate::game::LINES;
+use crate::render::{Renderer, Theme};
+
 #[non_exhaustive]
 pub struct XPos;
 impl XPos {
@@ -5,7 +11,7 @@ impl XPos {
     pub const B: usize = 1;
     pub const C: usize = 2;
 
-    fn letter_from(num: usize) -> &'static str { 
+    pub fn letter_from(num: usize) -> &'static str { 
         match num { 
             0 => "A",
             1 => "B",
@@ -28,7 +34,7 @@ pub enum Square {
 }
 
 impl Square { 
-    pub fn to_string(&self) -> &str { 
+    pub fn to_string(&self) -> &'static str { 
         match self { 
             Self::X => "❌",
             Self::O => "⭕️",
@@ -49,66 +55,214 @@ impl Default for Board {
 }
 
 impl Board { 
+    /// Prints the board to stdout in the emoji theme.
     pub fn pretty_print(&self) { 
-        for x in XPos::A ..= XPos::C { 
-            let letter = XPos::letter_from(x);
-            let squares = self.squares[x].iter()
-            .map(|f| f.to_string())
-            .collect::<String>();
-            println!("{} {}", letter, squares);
+        let renderer = Renderer::new(Theme::Emoji);
+        // Nothing sensible to do if stdout is gone.
+        let _ = renderer.write_board(&mut io::stdout(), self, None);
+    }
+}
+
+#[derive(Debug)]
+pub struct BoardBuildError;
+#[derive(Debug)]
+pub struct SquareBuildError;
+
+impl FromStr for Square { 
+    type Err = SquareBuildError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s { 
+            "X" => { Ok(Self::X) },
+            "O" => { Ok(Self::O) },
+            "-" => { Ok(Self::Empty) },
+            _ => { Err(SquareBuildError) }
+        }
+    }
+}
+
+impl FromStr for Board { 
+    type Err = BoardBuildError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.split("\n").count() != 3 { return Err(BoardBuildError); }
+
+        let build_string = s.split("\n");
+        let mut board = Board { squares: [[Square::Empty; 3]; 3] };
+
+        for (y, s) in build_string.into_iter().enumerate() {
+            if s.len() != 3 { return Result::Err(BoardBuildError); }
+            for (x, c) in s.chars().enumerate() {
+                let string = c.to_string(); 
+                let str = string.as_str();
+                let Ok(sq) = Square::from_str(str) else { return Err(BoardBuildError); };
+                board.squares[y][x] = sq;
+            }
         }
 
-        println!("  1️⃣ 2️⃣ 3️⃣");
+        Ok(board)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
+impl Square { 
+    /// Single character used by the text notation: `X`, `O` or `-`.
+    pub fn notation(self) -> char { 
+        match self { 
+            Self::X => 'X',
+            Self::O => 'O',
+            Self::Empty => '-'
+        }
+    }
+}
 
-    use crate::board::{Square, Board, XPos, YPos};
+impl Board { 
+    /// Three rows (A, B, C) of `X`, `O` and `-`, the same layout `from_str`
+    /// reads.
+    pub fn notation(&self) -> String { 
+        self.squares.iter()
+            .map(|row| row.iter().map(|sq| sq.notation()).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
 
-    #[derive(Debug)]
-    pub struct BoardBuildError;
-    #[derive(Debug)]
-    pub struct SquareBuildError;
+    /// The square that differs between `self` and `after`, when exactly one
+    /// move separates them.
+    pub fn changed_position(&self, after: &Board) -> Option<(usize, usize)> { 
+        let mut changed = Option::None;
+        for x in XPos::A ..= XPos::C { 
+            for y in YPos::_1 ..= YPos::_3 { 
+                if self.squares[x][y] != after.squares[x][y] { 
+                    if changed.is_some() { return Option::None; }
+                    changed = Option::Some((x, y));
+                }
+            }
+        }
+        changed
+    }
 
-    impl FromStr for Square { 
-        type Err = SquareBuildError;
+    /// Whose turn it is, since X moves first, or `None` if the counts of X
+    /// and O could not come from a real game.
+    pub fn side_to_move(&self) -> Option<Square> { 
+        let count = |square| self.squares.iter().flatten().filter(|&&s| s == square).count();
+        let (xs, os) = (count(Square::X), count(Square::O));
+        if xs == os { 
+            Some(Square::X)
+        } else if xs == os + 1 { 
+            Some(Square::O)
+        } else { 
+            None
+        }
+    }
+}
 
-        fn from_str(s: &str) -> Result<Self, Self::Err> {
-            match s { 
-                "X" => { Ok(Self::X) },
-                "O" => { Ok(Self::O) },
-                "-" => { Ok(Self::Empty) },
-                _ => { Err(SquareBuildError) }
+impl Board { 
+    /// The empty squares where `square` would complete a line at once.
+    pub fn threats(&self, square: Square) -> Vec<(usize, usize)> { 
+        let mut threats = Vec::new();
+        for line in LINES { 
+            let mine = line.iter().filter(|pos| self.squares[pos.0][pos.1] == square).count();
+            let empty: Vec<(usize, usize)> = line.into_iter().filter(|pos| self.squares[pos.0][pos.1] == Square::Empty).collect();
+            if mine == 2 && empty.len() == 1 && !threats.contains(&empty[0]) { 
+                threats.push(empty[0]);
             }
         }
+        threats.sort();
+        threats
     }
 
-    impl FromStr for Board { 
-        type Err = BoardBuildError;
+    /// The empty squares where `square` would make two threats at once, so
+    /// the other side can only block one.
+    pub fn forks(&self, square: Square) -> Vec<(usize, usize)> { 
+        self.get_empty_squares().into_iter()
+            .filter(|pos| { 
+                let mut after = *self;
+                after.squares[pos.0][pos.1] = square;
+                after.threats(square).len() >= 2
+            })
+            .collect()
+    }
+}
 
-        fn from_str(s: &str) -> Result<Self, Self::Err> {
-            if s.split("\n").count() != 3 { return Err(BoardBuildError); }
+/// How many ways the board maps onto itself: four turns, each with or
+/// without a mirror.
+pub const SYMMETRIES: usize = 8;
 
-            let build_string = s.split("\n");
-            let mut board = Board { squares: [[Square::Empty; 3]; 3] };
+/// Where `pos` lands under symmetry `symmetry`, from 0 to 7: 0 to 3 turn
+/// the board that many quarter turns clockwise, and 4 to 7 mirror it left
+/// to right first.
+pub fn transform_position(pos: (usize, usize), symmetry: usize) -> (usize, usize) { 
+    let (mut x, mut y) = if symmetry >= 4 { (pos.0, 2 - pos.1) } else { pos };
+    for _ in 0..symmetry % 4 { 
+        (x, y) = (y, 2 - x);
+    }
+    (x, y)
+}
 
-            for (y, s) in build_string.into_iter().enumerate() {
-                if s.len() != 3 { return Result::Err(BoardBuildError); }
-                for (x, c) in s.chars().enumerate() {
-                    let string = c.to_string(); 
-                    let str = string.as_str();
-                    let Ok(sq) = Square::from_str(str) else { return Err(BoardBuildError); };
-                    board.squares[y][x] = sq;
-                }
-            }
+/// The square that `transform_position` takes to `pos`.
+pub fn untransform_position(pos: (usize, usize), symmetry: usize) -> (usize, usize) { 
+    let mut from = pos;
+    for x in XPos::A ..= XPos::C { 
+        for y in YPos::_1 ..= YPos::_3 { 
+            if transform_position((x, y), symmetry) == pos { from = (x, y); }
+        }
+    }
+    from
+}
 
-            Ok(board)
+impl Board { 
+    /// This board under symmetry `symmetry`; see `transform_position`.
+    pub fn transformed(&self, symmetry: usize) -> Board { 
+        let mut after = Board::default();
+        for x in XPos::A ..= XPos::C { 
+            for y in YPos::_1 ..= YPos::_3 { 
+                let (to_x, to_y) = transform_position((x, y), symmetry);
+                after.squares[to_x][to_y] = self.squares[x][y];
+            }
         }
+        after
     }
 
+    /// The one board among this board's symmetries that every one of them
+    /// shares, the first in notation order, and the symmetry that gives it.
+    pub fn canonical(&self) -> (Board, usize) { 
+        (0..SYMMETRIES)
+            .map(|symmetry| (self.transformed(symmetry), symmetry))
+            .min_by_key(|(board, _)| board.notation())
+            .expect("there are symmetries")
+    }
+}
+
+/// Reads a square name such as `B3` (case and surrounding space ignored).
+pub fn parse_position(text: &str) -> Option<(usize, usize)> { 
+    let text = text.trim().to_ascii_uppercase();
+    let mut chars = text.chars();
+    let x = match chars.next()? { 
+        'A' => XPos::A,
+        'B' => XPos::B,
+        'C' => XPos::C,
+        _ => return None
+    };
+    let y = match chars.next()? { 
+        '1' => YPos::_1,
+        '2' => YPos::_2,
+        '3' => YPos::_3,
+        _ => return None
+    };
+    if chars.next().is_some() { return None; }
+    Some((x, y))
+}
+
+pub fn position_name(pos: (usize, usize)) -> String { 
+    format!("{}{}", XPos::letter_from(pos.0), pos.1 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::board::{Square, Board, XPos, YPos, parse_position, position_name};
+    use crate::board::{transform_position, untransform_position, SYMMETRIES};
+
     #[test]
     fn test_square_to_string() { 
         let x = Square::X;
@@ -131,6 +285,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unused_enumerate_index)]
     fn test_building_strings() { 
 
         let build_string = empty_build_string().split("\n");        
@@ -167,6 +322,33 @@ mod tests {
         assert!(board_from_str.is_ok());
     }
 
+    #[test]
+    fn test_notation_round_trip() { 
+        let board = Board::from_str(x_win_build_string()).unwrap();
+        assert_eq!(board.notation(), x_win_build_string());
+        assert_eq!(Board::from_str(&board.notation()).unwrap(), board);
+        assert!(Board::from_str("XO\n---\n---").is_err());
+    }
+
+    #[test]
+    fn test_side_to_move() {
+        assert_eq!(Board::default().side_to_move(), Some(Square::X));
+        assert_eq!(Board::from_str("X--\n---\n---").unwrap().side_to_move(), Some(Square::O));
+        assert_eq!(Board::from_str("XX-\n---\n---").unwrap().side_to_move(), None);
+        assert_eq!(Board::from_str("O--\n---\n---").unwrap().side_to_move(), None);
+    }
+
+    #[test]
+    fn test_position_names() { 
+        assert_eq!(parse_position("a1"), Some((XPos::A, YPos::_1)));
+        assert_eq!(parse_position(" C2\n"), Some((XPos::C, YPos::_2)));
+        assert_eq!(parse_position("D1"), None);
+        assert_eq!(parse_position("A4"), None);
+        assert_eq!(parse_position("A12"), None);
+        assert_eq!(parse_position(""), None);
+        assert_eq!(position_name((XPos::B, YPos::_3)), "B3");
+    }
+
     fn empty_build_string() -> &'static str { 
         "---\n---\n---"
     }
@@ -175,4 +357,36 @@ mod tests {
         "X-O\nOO-\nXXX"
     }
 
-}
\ No newline at end of file
+
+    #[test]
+    fn test_symmetries() { 
+        let board = Board::from_str("X--\n-O-\n--X").unwrap();
+        let turned = board.transformed(1);
+        assert_eq!(turned.notation(), "--X\n-O-\nX--");
+        assert_eq!(board.transformed(4).notation(), "--X\n-O-\nX--");
+        assert_eq!(transform_position((XPos::A, YPos::_2), 1), (XPos::B, YPos::_3));
+
+        let (canonical, _) = board.canonical();
+        for symmetry in 0..SYMMETRIES { 
+            assert_eq!(board.transformed(symmetry).canonical().0, canonical);
+            for pos in [(0, 0), (1, 2), (2, 1)] { 
+                assert_eq!(untransform_position(transform_position(pos, symmetry), symmetry), pos);
+            }
+        }
+        let (canonical, symmetry) = turned.canonical();
+        assert_eq!(turned.transformed(symmetry), canonical);
+    }
+
+    #[test]
+    fn test_threats_and_forks() { 
+        let board = Board::from_str("XX-\n-O-\nO--").unwrap();
+        assert_eq!(board.threats(Square::X), vec![(XPos::A, YPos::_3)]);
+        assert_eq!(board.threats(Square::O), vec![(XPos::A, YPos::_3)]);
+        assert!(Board::from_str("XXX\nOO-\n---").unwrap().threats(Square::X).is_empty());
+
+        let board = Board::from_str("X--\n-O-\n--X").unwrap();
+        assert!(board.threats(Square::O).is_empty());
+        assert_eq!(board.forks(Square::X), vec![(XPos::A, YPos::_3), (XPos::C, YPos::_1)]);
+        assert!(board.forks(Square::O).is_empty());
+    }
+}
@@ -0,0 +1,454 @@
+use std::path::PathBuf;
+
+use crate::learn::Opponent;
+use crate::lobby;
+use crate::mnk::Variant;
+use crate::net;
+use crate::render::{ColorChoice, Palette, Theme};
+use crate::simulate::Strategy;
+use crate::tournament::Participant;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    Play,
+    Stats,
+    /// Wait for a player on this port, then play them as X.
+    Host(u16),
+    /// Play O against whoever is hosting at this address.
+    Join(String),
+    /// Speak the engine protocol on stdin and stdout.
+    Engine,
+    /// Serve the browser game and the HTTP JSON API on this port.
+    Serve(u16),
+    /// Run a lobby server for many players on this port.
+    Lobby(u16),
+    /// Play games between two strategies, X first, and report on them.
+    Simulate(Strategy, Strategy),
+    /// Play a round robin between these and rate them.
+    Tournament(Vec<Participant>),
+    /// Play back one game from a tournament record.
+    Replay(PathBuf, usize),
+    /// Teach the learner by playing these in turn.
+    Train(Vec<Opponent>),
+    /// Walk the whole game tree and write the tablebase here, or to the
+    /// default place.
+    Solve(Option<PathBuf>),
+    /// Solve an m,n,k variant backwards and write its tablebase here, or
+    /// to the default place for the variant.
+    Retrograde(Variant, Option<PathBuf>)
+}
+
+/// Where `serve` listens unless given a port.
+pub const DEFAULT_HTTP_PORT: u16 = 8080;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Options {
+    pub command: Command,
+    pub profiles: Option<PathBuf>,
+    pub resume: Option<PathBuf>,
+    /// `None` means detect from the terminal.
+    pub theme: Option<Theme>,
+    pub color: ColorChoice,
+    pub palette: Palette,
+    pub tui: bool,
+    /// A command that starts an external engine to play the computer.
+    pub engine: Option<String>,
+    /// Games for `simulate`, or for each pairing and colour in a
+    /// `tournament`.  `None` means the command's default.
+    pub games: Option<usize>,
+    /// `None` means one per CPU.
+    pub threads: Option<usize>,
+    pub csv: bool,
+    /// Where `tournament` writes its game record.
+    pub record: Option<PathBuf>,
+    /// The learner's table, for `train` and the `learner` difficulty.
+    pub learner: Option<PathBuf>,
+    pub coach: bool,
+    /// The most memory `retrograde` may use, in bytes.  `None` means the
+    /// default.
+    pub memory: Option<usize>
+}
+
+/// How many games `simulate` plays unless told otherwise.
+pub const DEFAULT_SIMULATE_GAMES: usize = 1000;
+/// How many games each pairing plays with each colour in a tournament.
+pub const DEFAULT_TOURNAMENT_GAMES: usize = 20;
+/// How many games `train` plays unless told otherwise.
+pub const DEFAULT_TRAIN_GAMES: usize = 5000;
+
+pub fn usage() -> &'static str {
+    "usage: tic-tac-toe [stats | host [<port>] | join <address> | engine | serve [<port>] | lobby [<port>]
+                    | simulate <x> <o> | tournament <participant>... | replay <file> <game>
+                    | train [<opponent>...] | solve [<file>] | retrograde <variant> [<file>]]
+                   [--profiles <file>] [--resume <file>]
+                   [--theme <theme>] [--color <when>] [--palette <palette>] [--tui] [--coach] [--engine <command>]
+                   [--games <n>] [--threads <n>] [--csv] [--record <file>] [--learner <file>]
+                   [--memory <MiB>]
+
+commands:
+  (none)              play a game
+  stats               print lifetime statistics for every player profile
+  host [<port>]       host a network game on <port> (default 7878) and play X
+  join <address>      join a network game at <address>, such as host:7878, as O
+  engine              answer engine protocol commands on stdin and stdout
+  serve [<port>]      serve the browser game and its HTTP JSON API on <port>
+                      (default 8080)
+  lobby [<port>]      run a lobby server on <port> (default 7879) where many
+                      players pair up, play bots, watch and chat
+  simulate <x> <o>    play games between two strategies, each random, easy,
+                      perfect, learner, rules, mcts, mcts:<budget> (playouts
+                      such as 5000 or a time such as 100ms) or engine (the
+                      --engine command), and report results, game length,
+                      first moves and speed
+  tournament <participant>...
+                      play every pair of participants with both colours and
+                      print Elo ratings and a crosstable; a participant is a
+                      strategy as for simulate or engine:<command>
+  replay <file> <game>
+                      play back game <game> of a tournament record and check
+                      its result
+  train [<opponent>...]
+                      teach the learner difficulty by playing self (the
+                      default), random, easy, perfect, mcts or rules in turn,
+                      save what it learned and print its learning curve
+  solve [<file>]      count every game and position, solve them all and
+                      write the tablebase the perfect computer plays from to
+                      <file> (default tablebase.bin beside the profiles)
+  retrograde <variant> [<file>]
+                      solve an m,n,k variant such as 4x4k3 (k in a row on 4
+                      by 4) or 3x4 (k the shorter side) backwards from the
+                      full board, report who wins and write its tablebase
+                      to <file> (default tablebase-<variant>.bin beside the
+                      profiles)
+
+options:
+  --profiles <file>   read and write player profiles at <file>
+  --resume <file>     continue a game saved with `save <file>` or by Ctrl-C
+  --theme <theme>     board style: ascii, unicode, emoji or auto (default)
+  --color <when>      auto (default), always or never; auto honours NO_COLOR
+  --palette <palette> standard (default), or the colour-blind-safe okabe-ito
+                      and high-contrast
+  --tui               play full screen, moving with the arrow keys or mouse
+  --coach             after each of your moves, say whether it kept the
+                      result perfect play would reach, and if not, why not
+                      (not with --tui)
+  --engine <command>  run <command>, an engine protocol program, as the computer
+  --games <n>         games for simulate to play (default 1000), for each
+                      tournament pairing with each colour (default 20), or
+                      for train (default 5000)
+  --threads <n>       threads for simulate, tournament, retrograde and the
+                      engine's search (default one per CPU)
+  --csv               print simulate's report or train's curve as CSV
+  --record <file>     write every tournament game to <file> for replay
+  --learner <file>    keep the learner's table in <file> (default learner.txt
+                      beside the profiles)
+  --memory <MiB>      the most memory retrograde may use (default 1024)"
+}
+
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options { 
+        command: Command::Play,
+        profiles: Option::None,
+        resume: Option::None,
+        theme: Option::None,
+        color: ColorChoice::Auto,
+        palette: Palette::Standard,
+        tui: false,
+        engine: Option::None,
+        games: Option::None,
+        threads: Option::None,
+        csv: false,
+        record: Option::None,
+        learner: Option::None,
+        coach: false,
+        memory: Option::None
+    };
+    let mut command: Option<Command> = Option::None;
+    let mut args = args.into_iter();
+    let mut previous_takes_port = false;
+
+    while let Some(arg) = args.next() {
+        // Only the word straight after `host`, `serve` or `lobby` may be
+        // its port.
+        let port_allowed = previous_takes_port;
+        previous_takes_port = matches!(arg.as_str(), "host" | "serve" | "lobby");
+        match arg.as_str() {
+            "--profiles" => {
+                let path = args.next().ok_or("--profiles needs a file")?;
+                options.profiles = Option::Some(PathBuf::from(path));
+            },
+            "--resume" => {
+                let path = args.next().ok_or("--resume needs a file")?;
+                options.resume = Option::Some(PathBuf::from(path));
+            },
+            "--theme" => {
+                let label = args.next().ok_or("--theme needs ascii, unicode, emoji or auto")?;
+                options.theme = match label.as_str() {
+                    "auto" => Option::None,
+                    _ => Option::Some(Theme::from_label(&label).ok_or(format!("unknown theme '{}'", label))?)
+                };
+            },
+            "--color" => {
+                let when = args.next().ok_or("--color needs auto, always or never")?;
+                options.color = match when.as_str() {
+                    "auto" => ColorChoice::Auto,
+                    "always" => ColorChoice::Always,
+                    "never" => ColorChoice::Never,
+                    _ => { return Result::Err(format!("unknown --color value '{}'", when)); }
+                };
+            },
+            "--palette" => {
+                let label = args.next().ok_or("--palette needs a palette name")?;
+                options.palette = Palette::from_label(&label).ok_or(format!("unknown palette '{}'", label))?;
+            },
+            "--tui" => { options.tui = true; },
+            "--coach" => { options.coach = true; },
+            "--memory" => {
+                let memory = args.next().ok_or("--memory needs a number of MiB")?;
+                match memory.parse::<usize>().ok().and_then(|mib| mib.checked_mul(1 << 20)) {
+                    Option::None | Option::Some(0) => { return Result::Err(format!("'{}' is not a number of MiB", memory)); },
+                    Option::Some(memory) => { options.memory = Option::Some(memory); }
+                }
+            },
+            "--engine" => {
+                let command = args.next().ok_or("--engine needs a command")?;
+                options.engine = Option::Some(command);
+            },
+            "--games" => {
+                let games = args.next().ok_or("--games needs a number")?;
+                let games = games.parse().map_err(|_| format!("'{}' is not a number of games", games))?;
+                options.games = Option::Some(games);
+            },
+            "--threads" => {
+                let threads = args.next().ok_or("--threads needs a number")?;
+                match threads.parse() {
+                    Ok(0) | Err(_) => { return Result::Err(format!("'{}' is not a number of threads", threads)); },
+                    Ok(threads) => { options.threads = Option::Some(threads); }
+                }
+            },
+            "--csv" => { options.csv = true; },
+            "--record" => {
+                let path = args.next().ok_or("--record needs a file")?;
+                options.record = Option::Some(PathBuf::from(path));
+            },
+            "--learner" => {
+                let path = args.next().ok_or("--learner needs a file")?;
+                options.learner = Option::Some(PathBuf::from(path));
+            },
+            "stats" if command.is_none() => { command = Option::Some(Command::Stats); },
+            "engine" if command.is_none() => { command = Option::Some(Command::Engine); },
+            "serve" if command.is_none() => { command = Option::Some(Command::Serve(DEFAULT_HTTP_PORT)); },
+            "lobby" if command.is_none() => { command = Option::Some(Command::Lobby(lobby::DEFAULT_LOBBY_PORT)); },
+            "host" if command.is_none() => { command = Option::Some(Command::Host(net::DEFAULT_PORT)); },
+            "join" if command.is_none() => {
+                let address = args.next().ok_or("join needs an address such as localhost:7878")?;
+                command = Option::Some(Command::Join(address));
+            },
+            "simulate" if command.is_none() => {
+                let mut strategy = || -> Result<Strategy, String> {
+                    let label = args.next().ok_or("simulate needs two strategies, such as `simulate perfect random`")?;
+                    Strategy::from_label(&label).ok_or(format!("unknown strategy '{}'", label))
+                };
+                command = Option::Some(Command::Simulate(strategy()?, strategy()?));
+            },
+            "tournament" if command.is_none() => { command = Option::Some(Command::Tournament(Vec::new())); },
+            "replay" if command.is_none() => {
+                let path = args.next().ok_or("replay needs a tournament record and a game number")?;
+                let game = args.next().ok_or("replay needs a game number")?;
+                let game = game.parse().map_err(|_| format!("'{}' is not a game number", game))?;
+                command = Option::Some(Command::Replay(PathBuf::from(path), game));
+            },
+            "solve" if command.is_none() => { command = Option::Some(Command::Solve(Option::None)); },
+            path if matches!(command, Option::Some(Command::Solve(Option::None))) && !path.starts_with('-') => {
+                command = Option::Some(Command::Solve(Option::Some(PathBuf::from(path))));
+            },
+            "retrograde" if command.is_none() => {
+                let label = args.next().ok_or("retrograde needs a variant such as 4x4k3")?;
+                let variant = Variant::from_label(&label).ok_or(format!("unknown variant '{}'", label))?;
+                command = Option::Some(Command::Retrograde(variant, Option::None));
+            },
+            path if matches!(command, Option::Some(Command::Retrograde(_, Option::None))) && !path.starts_with('-') => {
+                if let Option::Some(Command::Retrograde(_, file)) = &mut command {
+                    *file = Option::Some(PathBuf::from(path));
+                }
+            },
+            "train" if command.is_none() => { command = Option::Some(Command::Train(Vec::new())); },
+            label if matches!(command, Option::Some(Command::Train(_))) && !label.starts_with('-') => {
+                let opponent = Opponent::from_label(label).ok_or(format!("unknown opponent '{}'", label))?;
+                if let Option::Some(Command::Train(opponents)) = &mut command {
+                    opponents.push(opponent);
+                }
+            },
+            label if matches!(command, Option::Some(Command::Tournament(_))) && !label.starts_with('-') => {
+                let participant = Participant::from_label(label).ok_or(format!("unknown participant '{}'", label))?;
+                if let Option::Some(Command::Tournament(participants)) = &mut command {
+                    if participants.iter().any(|p| p.name == participant.name) {
+                        return Result::Err(format!("'{}' is entered twice", label));
+                    }
+                    participants.push(participant);
+                }
+            },
+            port if port_allowed && !port.starts_with('-') => {
+                let port = port.parse().map_err(|_| format!("'{}' is not a port", port))?;
+                command = match command {
+                    Option::Some(Command::Serve(_)) => Option::Some(Command::Serve(port)),
+                    Option::Some(Command::Lobby(_)) => Option::Some(Command::Lobby(port)),
+                    _ => Option::Some(Command::Host(port))
+                };
+            },
+            _ => { return Result::Err(format!("unexpected argument '{}'", arg)); }
+        }
+    }
+
+    let strategies: Vec<&mut Strategy> = match &mut command {
+        Option::Some(Command::Simulate(x, o)) => vec![x, o],
+        Option::Some(Command::Tournament(participants)) => {
+            if participants.len() < 2 {
+                return Result::Err("a tournament needs at least two participants".to_string());
+            }
+            participants.iter_mut().map(|p| &mut p.strategy).collect()
+        },
+        _ => Vec::new()
+    };
+    for strategy in strategies {
+        if let Strategy::Engine(engine) = strategy {
+            if engine.is_empty() {
+                *engine = options.engine.clone().ok_or("the engine strategy needs --engine <command>")?;
+            }
+        }
+    }
+
+    if let Option::Some(Command::Train(opponents)) = &mut command {
+        if opponents.is_empty() {
+            opponents.push(Opponent::Itself);
+        }
+    }
+
+    if let Some(command) = command {
+        options.command = command;
+    }
+    Result::Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::game::Difficulty;
+    use crate::learn::Opponent;
+    use crate::mnk::Variant;
+    use crate::render::{ColorChoice, Palette, Theme};
+    use crate::simulate::Strategy;
+
+    use super::{parse, Command};
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        let options = parse(args(&[])).unwrap();
+        assert_eq!(options.command, Command::Play);
+
+        let options = parse(args(&["stats", "--profiles", "p.txt"])).unwrap();
+        assert_eq!(options.command, Command::Stats);
+        assert_eq!(options.profiles, Some(PathBuf::from("p.txt")));
+
+        let options = parse(args(&["--resume", "game.txt"])).unwrap();
+        assert_eq!(options.command, Command::Play);
+        assert_eq!(options.resume, Some(PathBuf::from("game.txt")));
+        assert_eq!(options.theme, None);
+
+        let options = parse(args(&["--theme", "unicode"])).unwrap();
+        assert_eq!(options.theme, Some(Theme::Unicode));
+        let options = parse(args(&["--theme", "emoji", "--theme", "auto"])).unwrap();
+        assert_eq!(options.theme, None);
+
+        let options = parse(args(&["--color", "always", "--palette", "okabe-ito"])).unwrap();
+        assert_eq!(options.color, ColorChoice::Always);
+        assert_eq!(options.palette, Palette::OkabeIto);
+        assert!(!options.tui);
+
+        let options = parse(args(&["--tui"])).unwrap();
+        assert!(options.tui);
+        assert!(!options.coach);
+        assert!(parse(args(&["--coach"])).unwrap().coach);
+
+        assert_eq!(parse(args(&["host"])).unwrap().command, Command::Host(7878));
+        assert_eq!(parse(args(&["host", "9000", "--theme", "ascii"])).unwrap().command, Command::Host(9000));
+        let options = parse(args(&["join", "example.org:9000"])).unwrap();
+        assert_eq!(options.command, Command::Join("example.org:9000".to_string()));
+
+        assert_eq!(parse(args(&["engine"])).unwrap().command, Command::Engine);
+        assert_eq!(parse(args(&["serve"])).unwrap().command, Command::Serve(8080));
+        assert_eq!(parse(args(&["serve", "3000"])).unwrap().command, Command::Serve(3000));
+        assert_eq!(parse(args(&["lobby"])).unwrap().command, Command::Lobby(7879));
+        assert_eq!(parse(args(&["lobby", "4000"])).unwrap().command, Command::Lobby(4000));
+        let options = parse(args(&["--engine", "./bot --fast"])).unwrap();
+        assert_eq!(options.command, Command::Play);
+        assert_eq!(options.engine.as_deref(), Some("./bot --fast"));
+
+        let options = parse(args(&["simulate", "perfect", "random"])).unwrap();
+        assert_eq!(options.command, Command::Simulate(Strategy::Builtin(Difficulty::Perfect), Strategy::Builtin(Difficulty::Random)));
+        assert_eq!((options.games, options.threads, options.csv), (None, None, false));
+        let options = parse(args(&["simulate", "engine", "easy", "--games", "50", "--threads", "2", "--csv", "--engine", "./bot"])).unwrap();
+        assert_eq!(options.command, Command::Simulate(Strategy::Engine("./bot".to_string()), Strategy::Builtin(Difficulty::Easy)));
+        assert_eq!((options.games, options.threads, options.csv), (Some(50), Some(2), true));
+
+        let options = parse(args(&["tournament", "perfect", "engine:./bot -q", "engine", "--engine", "./other", "--record", "t.txt"])).unwrap();
+        let Command::Tournament(participants) = options.command else { panic!("not a tournament") };
+        let names: Vec<&str> = participants.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["perfect", "engine:./bot -q", "engine"]);
+        assert_eq!(participants[1].strategy, Strategy::Engine("./bot -q".to_string()));
+        assert_eq!(participants[2].strategy, Strategy::Engine("./other".to_string()));
+        assert_eq!(options.record, Some(PathBuf::from("t.txt")));
+        assert_eq!(parse(args(&["replay", "t.txt", "7"])).unwrap().command, Command::Replay(PathBuf::from("t.txt"), 7));
+
+        assert_eq!(parse(args(&["solve"])).unwrap().command, Command::Solve(None));
+        assert_eq!(parse(args(&["solve", "t.bin"])).unwrap().command, Command::Solve(Some(PathBuf::from("t.bin"))));
+        let options = parse(args(&["retrograde", "4x4k3", "--memory", "64"])).unwrap();
+        assert_eq!(options.command, Command::Retrograde(Variant::new(4, 4, 3).unwrap(), None));
+        assert_eq!(options.memory, Some(64 << 20));
+        let options = parse(args(&["retrograde", "3x4", "t.bin"])).unwrap();
+        assert_eq!(options.command, Command::Retrograde(Variant::new(3, 4, 3).unwrap(), Some(PathBuf::from("t.bin"))));
+        assert_eq!(parse(args(&["train"])).unwrap().command, Command::Train(vec![Opponent::Itself]));
+        let options = parse(args(&["train", "random", "self", "--games", "100", "--learner", "l.txt"])).unwrap();
+        assert_eq!(options.command, Command::Train(vec![Opponent::Builtin(Difficulty::Random), Opponent::Itself]));
+        assert_eq!((options.games, options.learner), (Some(100), Some(PathBuf::from("l.txt"))));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(args(&["--profiles"])).is_err());
+        assert!(parse(args(&["--resume"])).is_err());
+        assert!(parse(args(&["--theme", "neon"])).is_err());
+        assert!(parse(args(&["--color", "sometimes"])).is_err());
+        assert!(parse(args(&["--palette", "rainbow"])).is_err());
+        assert!(parse(args(&["stats", "stats"])).is_err());
+        assert!(parse(args(&["--bogus"])).is_err());
+        assert!(parse(args(&["host", "seventy"])).is_err());
+        assert!(parse(args(&["host", "9000", "9001"])).is_err());
+        assert!(parse(args(&["join"])).is_err());
+        assert!(parse(args(&["--engine"])).is_err());
+        assert!(parse(args(&["stats", "host"])).is_err());
+        assert!(parse(args(&["simulate", "perfect"])).is_err());
+        assert!(parse(args(&["simulate", "perfect", "genius"])).is_err());
+        assert!(parse(args(&["simulate", "engine", "perfect"])).is_err());
+        assert!(parse(args(&["--games", "many"])).is_err());
+        assert!(parse(args(&["--threads", "0"])).is_err());
+        assert!(parse(args(&["tournament", "perfect"])).is_err());
+        assert!(parse(args(&["tournament", "perfect", "perfect"])).is_err());
+        assert!(parse(args(&["tournament", "perfect", "wizard"])).is_err());
+        assert!(parse(args(&["tournament", "perfect", "engine"])).is_err());
+        assert!(parse(args(&["replay", "t.txt"])).is_err());
+        assert!(parse(args(&["replay", "t.txt", "first"])).is_err());
+        assert!(parse(args(&["train", "learner"])).is_err());
+        assert!(parse(args(&["--learner"])).is_err());
+        assert!(parse(args(&["solve", "a.bin", "b.bin"])).is_err());
+        assert!(parse(args(&["retrograde"])).is_err());
+        assert!(parse(args(&["retrograde", "9x9"])).is_err());
+        assert!(parse(args(&["--memory", "0"])).is_err());
+        assert!(parse(args(&["--memory", "18446744073709551615"])).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! The coach, which reviews each human move against perfect play.
+//!
+//! A move either keeps the game-theoretic value of the position, the
+//! result both sides would reach from it with perfect play, or gives some
+//! of it away.  When it gives some away the coach names the refutation, in
+//! this order: a line the opponent can now complete, squares where they
+//! can now fork, a win the mover passed up, or else the opponent's best
+//! reply.  Ties go to the first square in notation order, so the same
+//! move always gets the same review.
+
+use crate::board::{position_name, Board, Square};
+use crate::game::{self, Player, PlayerSet, PlayerType};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Value {
+    Win, Draw, Loss
+}
+
+impl Value {
+    /// From a minimax score: 1, 0 or -1.
+    pub fn from_score(score: i32) -> Value {
+        match score {
+            1 => Value::Win,
+            0 => Value::Draw,
+            _ => Value::Loss
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Value::Win => "win",
+            Value::Draw => "draw",
+            Value::Loss => "loss"
+        }
+    }
+}
+
+/// Why a move lost value.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Refutation {
+    /// The opponent can complete a line on any of these squares.
+    Wins(Vec<(usize, usize)>),
+    /// The opponent can make two threats at once on any of these.
+    Forks(Vec<(usize, usize)>),
+    /// The mover could have completed a line on any of these instead.
+    MissedWin(Vec<(usize, usize)>),
+    /// The opponent's best reply and what it gets them.
+    Reply((usize, usize), Value)
+}
+
+/// What a move did to the mover's prospects.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Review {
+    pub pos: (usize, usize),
+    /// The mover's value before the move.
+    pub before: Value,
+    /// The mover's value after it.
+    pub after: Value,
+    /// `None` when the move kept the value.
+    pub refutation: Option<Refutation>
+}
+
+impl Review {
+    pub fn kept(&self) -> bool {
+        self.before == self.after
+    }
+
+    /// The review in a sentence, calling the opponent `opponent`, such as
+    /// `A2 turns the draw into a loss: this lets ⭕️ fork at A3 and C1.`
+    pub fn message(&self, opponent: &str) -> String {
+        let square = position_name(self.pos);
+        let Some(refutation) = &self.refutation else {
+            return match self.after {
+                Value::Loss => format!("{} is as good as any; best play still loses.", square),
+                value => format!("{} keeps the {}.", square, value.name())
+            };
+        };
+        let why = match refutation {
+            Refutation::Wins(squares) => format!("this lets {} win at {}", opponent, name_list(squares)),
+            Refutation::Forks(squares) => format!("this lets {} fork at {}", opponent, name_list(squares)),
+            Refutation::MissedWin(squares) => format!("you could have won at {}", name_list(squares)),
+            Refutation::Reply(reply, Value::Win) => format!("{} answers at {} and wins with best play", opponent, position_name(*reply)),
+            Refutation::Reply(reply, _) => format!("{} answers at {} and holds the draw", opponent, position_name(*reply))
+        };
+        format!("{} turns the {} into a {}: {}.", square, self.before.name(), self.after.name(), why)
+    }
+}
+
+/// `A3`, `A3 and C1` or `A1, A3 and C1`.
+fn name_list(squares: &[(usize, usize)]) -> String {
+    let names: Vec<String> = squares.iter().map(|&pos| position_name(pos)).collect();
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new()
+    }
+}
+
+/// Reviews the side to move playing `pos` on `board`, or `None` if the
+/// game is over or `pos` is taken.
+pub fn review(board: &Board, pos: (usize, usize)) -> Option<Review> {
+    let me = board.side_to_move()?;
+    if game::winning_line(board).is_some() || board.squares[pos.0][pos.1] != Square::Empty {
+        return None;
+    }
+    let set = PlayerSet { x: Player::X(PlayerType::Human), o: Player::O(PlayerType::Human) };
+    let mover = if me == Square::X { set.x } else { set.o };
+    let opponent = set.opposite_player(&mover);
+
+    let before = Value::from_score(game::perfect_score(&mover, &set, board));
+    let after = Value::from_score(game::move_score(&mover, &set, board, pos));
+    if before == after {
+        return Some(Review { pos, before, after, refutation: None });
+    }
+
+    let mut played = *board;
+    played.squares[pos.0][pos.1] = me;
+    let them = opponent.associated_square();
+
+    // Only squares that are best for the opponent refute the move, and
+    // while the mover threatens a line a fork anywhere but the block
+    // comes too late.
+    let best = game::perfect_score(&opponent, &set, &played);
+    let blocks = played.threats(me);
+    let refuting = |squares: Vec<(usize, usize)>| -> Vec<(usize, usize)> {
+        squares.into_iter().filter(|&square| game::move_score(&opponent, &set, &played, square) == best).collect()
+    };
+    let wins = refuting(played.threats(them));
+    let forks = refuting(played.forks(them).into_iter().filter(|square| blocks.is_empty() || blocks.contains(square)).collect());
+    let missed = board.threats(me);
+    let refutation = if !wins.is_empty() {
+        Refutation::Wins(wins)
+    } else if !forks.is_empty() {
+        Refutation::Forks(forks)
+    } else if !missed.is_empty() {
+        Refutation::MissedWin(missed)
+    } else {
+        // Best for the opponent is worst for the mover.
+        let reply = played.get_empty_squares().into_iter()
+            .max_by_key(|&reply| (game::move_score(&opponent, &set, &played, reply), std::cmp::Reverse(reply)))
+            .expect("a move that changes the value leaves the game going");
+        Refutation::Reply(reply, Value::from_score(game::move_score(&opponent, &set, &played, reply)))
+    };
+    Some(Review { pos, before, after, refutation: Some(refutation) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use std::collections::HashSet;
+
+    use crate::board::{parse_position, Board};
+    use crate::search::{self, Position};
+
+    use super::{review, Refutation, Value};
+
+    fn review_of(board: &str, square: &str) -> super::Review {
+        review(&Board::from_str(board).unwrap(), parse_position(square).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_kept_values() {
+        let centre = review_of("---\n---\n---", "B2");
+        assert!(centre.kept());
+        assert_eq!(centre.message("O"), "B2 keeps the draw.");
+        assert_eq!(review_of("XX-\nOO-\n---", "A3").message("O"), "A3 keeps the win.");
+        assert_eq!(review_of("XX-\nXO-\n--O", "A3").message("X"), "A3 is as good as any; best play still loses.");
+    }
+
+    #[test]
+    fn test_refutations() {
+        // O must block at A3.
+        let reviewed = review_of("XX-\n-O-\n---", "C3");
+        assert_eq!((reviewed.before, reviewed.after), (Value::Draw, Value::Loss));
+        assert_eq!(reviewed.message("X"), "C3 turns the draw into a loss: this lets X win at A3.");
+
+        // With X on opposite corners a corner loses to a fork.
+        let reviewed = review_of("X--\n-O-\n--X", "A3");
+        assert_eq!(reviewed.refutation, Some(Refutation::Forks(vec![parse_position("C1").unwrap()])));
+        assert_eq!(reviewed.message("❌"), "A3 turns the draw into a loss: this lets ❌ fork at C1.");
+
+        let reviewed = review_of("XX-\nOO-\n---", "C3");
+        assert_eq!(reviewed.message("O"), "C3 turns the win into a loss: this lets O win at B3.");
+
+        // A side square against X's corner opening loses with best play,
+        // though not to any immediate threat.
+        let reviewed = review_of("X--\n---\n---", "A2");
+        assert_eq!(reviewed.refutation, Some(Refutation::Reply(parse_position("B1").unwrap(), Value::Win)));
+        assert_eq!(reviewed.message("X"), "A2 turns the draw into a loss: X answers at B1 and wins with best play.");
+
+        // A fork only refutes a move when it is also the forced block.
+        let reviewed = review_of("XOX\nO--\n-X-", "B3");
+        assert_eq!(reviewed.message("X"), "B3 turns the draw into a loss: this lets X fork at B2.");
+        let reviewed = review_of("XOX\n---\nO--", "B3");
+        assert_eq!(reviewed.message("O"), "B3 turns the win into a draw: O answers at C3 and holds the draw.");
+
+        assert!(review(&Board::from_str("XXX\nOO-\n---").unwrap(), (2, 2)).is_none());
+        assert!(review(&Board::default().play((1, 1)), (1, 1)).is_none());
+    }
+
+    #[test]
+    fn test_refutations_are_best_replies() {
+        // Every square a review names must be one of the opponent's best
+        // replies, in every position a game can reach.
+        let mut seen = HashSet::new();
+        let mut stack = vec![Board::default()];
+        while let Some(board) = stack.pop() {
+            if !seen.insert(board.notation()) {
+                continue;
+            }
+            for pos in board.moves() {
+                let played = board.play(pos);
+                stack.push(played);
+                let Some(Refutation::Wins(squares) | Refutation::Forks(squares)) = review(&board, pos).unwrap().refutation else {
+                    continue;
+                };
+                let best = search::value(&played);
+                for square in squares {
+                    assert_eq!(search::move_value(&played, square), best, "{} then {:?}", board.notation(), pos);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::num::ParseIntError;
+use std::path::Path;
+
+use crate::board::{Board, XPos, YPos};
+use crate::coach;
+use crate::engine::{self, ExternalEngine};
+use crate::game::{self, Difficulty, Game, Player, PlayerSet, PlayerType};
+use crate::profile::PlayerNames;
+use crate::render::Renderer;
+use crate::rules;
+use crate::save::SavedGame;
+use crate::session::{Autosave, Session};
+
+/// Where the line-based game reads the players' typing from.
+pub trait Input {
+    /// The next line, including its newline, or `None` once input is
+    /// closed.
+    fn read_line(&mut self) -> io::Result<Option<String>>;
+}
+
+/// Reads lines from any buffered reader, such as a locked stdin.
+pub struct LineInput<R: BufRead> {
+    reader: R
+}
+
+impl<R: BufRead> LineInput<R> {
+    pub fn new(reader: R) -> Self {
+        LineInput { reader }
+    }
+}
+
+impl<R: BufRead> Input for LineInput<R> {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut in_buffer = String::new();
+        let read = self.reader.read_line(&mut in_buffer)?;
+        Ok(if read == 0 { None } else { Some(in_buffer) })
+    }
+}
+
+/// Canned input for tests: each entry is one line typed by a player.
+#[derive(Default)]
+pub struct Script {
+    lines: VecDeque<String>
+}
+
+impl Script {
+    pub fn new(lines: &[&str]) -> Self {
+        Script { lines: lines.iter().map(|line| format!("{}\n", line)).collect() }
+    }
+}
+
+impl Input for Script {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        Ok(self.lines.pop_front())
+    }
+}
+
+enum PromptResult {
+    Move(Game),
+    Load(SavedGame)
+}
+
+/// The line-based game: menus, prompts and boards written to `output`,
+/// answers read from `input`.
+///
+/// Every method fails with `io::ErrorKind::UnexpectedEof` once input runs
+/// out, since nobody is left to play.
+pub struct Console<'a> {
+    input: &'a mut dyn Input,
+    output: &'a mut dyn Write,
+    renderer: Renderer,
+    autosave: Option<Autosave>,
+    engine: Option<ExternalEngine>,
+    coach: bool
+}
+
+impl<'a> Console<'a> {
+    pub fn new(input: &'a mut dyn Input, output: &'a mut dyn Write, renderer: Renderer) -> Self {
+        Console { input, output, renderer, autosave: None, engine: None, coach: false }
+    }
+
+    /// Keeps `autosave` up to date with the game in progress, for a Ctrl-C
+    /// handler to write out.
+    pub fn with_autosave(self, autosave: Autosave) -> Self {
+        Console { autosave: Some(autosave), ..self }
+    }
+
+    /// Seats `engine` wherever the menu would have seated the computer.
+    pub fn with_engine(self, engine: ExternalEngine) -> Self {
+        Console { engine: Some(engine), ..self }
+    }
+
+    /// Reviews every human move against perfect play; see `coach`.
+    pub fn with_coach(self) -> Self {
+        Console { coach: true, ..self }
+    }
+
+    pub fn engine(&mut self) -> Option<&mut ExternalEngine> {
+        self.engine.as_mut()
+    }
+
+    fn read_input_line(&mut self) -> io::Result<String> {
+        self.input.read_line()?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "input closed"))
+    }
+
+    /// Runs a whole session: `start_session`, then the game to its end.
+    pub fn run(&mut self, resume: Option<SavedGame>) -> io::Result<Session> {
+        let mut session = self.start_session(resume)?;
+        self.play_game(&mut session)?;
+        Ok(session)
+    }
+
+    /// The menu and profile names for a new game, or the resumed game.
+    pub fn start_session(&mut self, resume: Option<SavedGame>) -> io::Result<Session> {
+        if let Some(engine) = &mut self.engine {
+            engine.new_game()?;
+        }
+
+        match resume {
+            Some(saved) => Ok(Session::resume(saved)),
+            None => {
+                let game = self.init_game()?;
+                let Game::InPlay { set, .. } = game else { unreachable!("init_game always starts a game") };
+                let names = self.player_names(&set)?;
+                Ok(Session::new(set, game, names))
+            }
+        }
+    }
+
+    pub fn play_game(&mut self, session: &mut Session) -> io::Result<()> {
+        let mut is_playing = true;
+        while is_playing {
+            if let Some(autosave) = &self.autosave {
+                if let Ok(mut latest) = autosave.lock() {
+                    *latest = session.snapshot();
+                }
+            }
+
+            match session.game {
+                Game::Uninitiated => { writeln!(self.output, "wait.. this shouldn't happen")?; },
+                Game::Tie(_) | Game::Win(..) => {
+                    self.renderer.write_game(self.output, &session.game, session.last_move())?;
+                },
+                Game::InPlay { set, turn, board } => {
+                    match turn.player_type() {
+                        PlayerType::Human => {
+                            match self.player_input(session, set, turn, board)? {
+                                PromptResult::Move(updated_game) => {
+                                    if self.coach {
+                                        self.coach_move(&board, &updated_game, &set.opposite_player(&turn))?;
+                                    }
+                                    session.advance(updated_game);
+                                },
+                                PromptResult::Load(saved) => { *session = Session::resume(saved); }
+                            }
+                        },
+                        PlayerType::Computer(_) | PlayerType::Engine => {
+                            if turn.player_type() == &PlayerType::Computer(Difficulty::Rules) {
+                                if let Some(choice) = rules::choose(&board) {
+                                    writeln!(self.output, "{} is {}", self.renderer.player(&turn), choice.explain())?;
+                                }
+                            }
+                            let updated_game = engine::play_turn(self.engine.as_mut(), &turn, &set, &board)?;
+                            session.advance(updated_game);
+                        }
+                    }
+
+                    continue;
+                }
+            }
+
+            is_playing = false;
+        }
+
+        Ok(())
+    }
+
+    fn player_input(&mut self, session: &Session, set: PlayerSet, turn: Player, board: Board) -> io::Result<PromptResult> {
+        loop {
+            self.renderer.write_board(self.output, &board, session.last_move())?;
+            writeln!(self.output, "{}, please enter move A1 thru C3 (or save <file>, load <file>):", self.renderer.player(&turn))?;
+
+            let raw_input = self.read_input_line()?;
+            let trimmed = raw_input.trim();
+
+            if let Some(path) = trimmed.strip_prefix("save ") {
+                self.save_session(session, Path::new(path.trim()))?;
+                continue;
+            }
+
+            if let Some(path) = trimmed.strip_prefix("load ") {
+                match SavedGame::load(Path::new(path.trim())) {
+                    Ok(saved) => {
+                        writeln!(self.output, "Loaded {}", path.trim())?;
+                        return Ok(PromptResult::Load(saved));
+                    },
+                    Err(error) => {
+                        writeln!(self.output, "Couldn't load {}: {}", path.trim(), error)?;
+                        continue;
+                    }
+                }
+            }
+
+            let in_buffer = raw_input.to_ascii_uppercase();
+            let letter = in_buffer.get(..1).unwrap_or("");
+            let number = in_buffer.get(1..).unwrap_or("");
+
+            let x_pos = match letter {
+                "A" => XPos::A,
+                "B" => XPos::B,
+                "C" => XPos::C,
+                _ => {
+                    writeln!(self.output, "Letter. Please enter Letter (A-C) & Number (1-3) format. ie: A1, C2, etc")?;
+                    continue;
+                }
+            };
+
+            let y_pos = match number.trim() {
+                "1" => YPos::_1,
+                "2" => YPos::_2,
+                "3" => YPos::_3,
+                _ => {
+                    writeln!(self.output, "Number. Please enter Letter (A-C) & Number (1-3) format. ie: A1, C2, etc")?;
+                    continue;
+                }
+            };
+
+            let pos = &(x_pos, y_pos);
+            let move_result = game::make_move(pos, &board, &turn, &set);
+
+            match move_result {
+                Ok(result_game) => { return Ok(PromptResult::Move(result_game)); },
+                Err(error) => {
+                    writeln!(self.output, "Can't move there. Please choose another move. {:?}", error)?;
+                    continue;
+                }
+            }
+        }
+    }
+
+    fn coach_move(&mut self, board: &Board, updated_game: &Game, opponent: &Player) -> io::Result<()> {
+        let review = updated_game.board()
+            .and_then(|after| board.changed_position(after))
+            .and_then(|pos| coach::review(board, pos));
+        match review {
+            Some(review) => writeln!(self.output, "Coach: {}", review.message(&self.renderer.player(opponent))),
+            None => Ok(())
+        }
+    }
+
+    fn save_session(&mut self, session: &Session, path: &Path) -> io::Result<()> {
+        let Some(saved) = session.snapshot() else { return Ok(()); };
+        match saved.save(path) {
+            Ok(()) => writeln!(self.output, "Game saved to {}", path.display()),
+            Err(error) => writeln!(self.output, "Couldn't save to {}: {}", path.display(), error)
+        }
+    }
+
+    pub fn init_game(&mut self) -> io::Result<Game> {
+        loop {
+            writeln!(self.output, "Tic Tac Toe.  Enter an option: (X goes first)")?;
+            writeln!(self.output, "1. X: Human, O: Computer")?;
+            writeln!(self.output, "2: X: Human, O: Human")?;
+            writeln!(self.output, "3: X: Computer, O: Human")?;
+
+            let in_buffer = self.read_input_line()?;
+
+            let option: Result<i32, ParseIntError> = in_buffer
+            .trim()
+            .parse();
+
+            let Ok(num_input) = option else {
+                writeln!(self.output, "Please enter a number")?;
+                continue;
+            };
+
+            let mut x_type = PlayerType::Human;
+            let mut o_type = PlayerType::Human;
+
+            match num_input {
+                1 => { o_type = self.computer_type()?; },
+                2 => { },
+                3 => { x_type = self.computer_type()?; }
+                _ => {
+                    writeln!(self.output, "invalid input, try again")?;
+                    continue;
+                }
+            }
+
+            let player_set = PlayerSet { x: Player::X(x_type), o: Player::O(o_type) };
+            return Ok(Game::InPlay { set: player_set, turn: player_set.x, board: Board::default() });
+        }
+    }
+
+    /// The external engine if there is one, otherwise the built-in
+    /// computer at a difficulty the player picks.
+    fn computer_type(&mut self) -> io::Result<PlayerType> {
+        if self.engine.is_some() {
+            return Ok(PlayerType::Engine);
+        }
+        Ok(PlayerType::Computer(self.choose_difficulty()?))
+    }
+
+    fn choose_difficulty(&mut self) -> io::Result<Difficulty> {
+        loop {
+            writeln!(self.output, "Computer difficulty:")?;
+            for (index, difficulty) in Difficulty::ALL.iter().enumerate() {
+                writeln!(self.output, "{}. {}", index + 1, difficulty.label())?;
+            }
+
+            let choice = self.read_input_line()?.trim().parse::<usize>();
+            match choice {
+                Ok(num) if (1..=Difficulty::ALL.len()).contains(&num) => { return Ok(Difficulty::ALL[num - 1]); },
+                _ => { writeln!(self.output, "invalid input, try again")?; }
+            }
+        }
+    }
+
+    pub fn player_names(&mut self, set: &PlayerSet) -> io::Result<PlayerNames> {
+        let mut names = PlayerNames::default();
+
+        for player in [set.x, set.o] {
+            if player.player_type() != &PlayerType::Human { continue; }
+
+            writeln!(self.output, "{}, enter your profile name (or press enter to play as a guest):", self.renderer.player(&player))?;
+            let name = self.read_input_line()?.trim().to_string();
+            if name.is_empty() { continue; }
+
+            match player {
+                Player::X(_) => { names.x = Some(name); },
+                Player::O(_) => { names.o = Some(name); }
+            }
+        }
+
+        Ok(names)
+    }
+}
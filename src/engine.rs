@@ -0,0 +1,551 @@
+//! A text protocol for tic-tac-toe engines, modelled on chess's UCI.
+//!
+//! `tic-tac-toe engine` speaks it on stdin and stdout with [`Engine`],
+//! backed by [`game::computer_move`].  [`ExternalEngine`] is the other
+//! end: it starts any program that speaks the protocol and asks it for
+//! moves, so `--engine <command>` can put another bot in the computer's
+//! seat.
+//!
+//! # Protocol
+//!
+//! One command per line.  Squares are written `A1` to `C3` and boards as
+//! the rows A, B and C of `X`, `O` and `-` joined by `/`, as in the network
+//! protocol.  X always moves first, so the side to move follows from the
+//! board.  Engines ignore commands they don't know.
+//!
+//! Controller to engine:
+//!
+//! | Command | Meaning |
+//! |---|---|
+//! | `tttp` | Handshake.  The engine names itself and lists its options, then answers `tttpok`. |
+//! | `isready` | Answered with `readyok` once earlier commands are done. |
+//! | `setoption name <name> value <value>` | Sets an option listed at the handshake. |
+//! | `newgame` | The next position belongs to a new game. |
+//! | `position startpos [moves <square>...]` | The empty board, then the moves listed. |
+//! | `position board <board> [moves <square>...]` | The given board, then the moves listed. |
+//! | `go [depth <plies>] [nodes <count>] [movetime <ms>]` | Search the current position within the limits given. |
+//! | `stop` | Cut the search in progress short; it answers `bestmove` with what it has. |
+//! | `quit` | Exit. |
+//!
+//! Engine to controller:
+//!
+//! | Reply | Meaning |
+//! |---|---|
+//! | `id name <name>` | Sent during the handshake. |
+//! | `option name <name> type combo default <value> var <value>...` | An option the engine supports, sent during the handshake. |
+//! | `option name <name> type spin default <value> min <value> max <value>` | A numeric option, likewise. |
+//! | `tttpok` | Handshake done. |
+//! | `readyok` | Reply to `isready`. |
+//! | `bestmove <square> [score <score>]` | Reply to `go`.  The score is what the move is worth to the side that plays it with perfect play afterwards: 1 wins, 0 draws, -1 loses.  A search cut short by its limits or `stop` gives no score. |
+//! | `bestmove none` | Reply to `go` when the game is already over. |
+//! | `info depth <plies> nodes <count> score <score>` | How far a deepening search got, sent before `bestmove` by engines that search that way.  The score is the best move's value seen that far ahead, where an unfinished game counts as a draw. |
+//! | `info move <square> visits <count> winrate <rate>` | Search statistics for one move, sent before `bestmove` by engines that sample rather than solve.  The rate is the share of playouts through the move that its side won. |
+//! | `info string <text>` | Free text, such as why a command was refused. |
+//!
+//! The built-in engine has two options: `difficulty`, taking the
+//! `Difficulty` labels, and `threads`, how many threads the `perfect`
+//! search splits each depth's moves across, which changes how fast it
+//! searches but never what it finds.  With `perfect` it deepens one ply at
+//! a time until the position is solved, any of `depth`, `nodes` and
+//! `movetime` runs out or `stop` arrives; tic-tac-toe is solved long
+//! before.  With `mcts`, `nodes` sets the number of playouts and
+//! `movetime` the time to spend, the latter winning if both are given;
+//! `depth` is ignored.  The other difficulties ignore limits and `stop`.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::str::FromStr;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use crate::board::{parse_position, position_name, Board, Square};
+use crate::console::Input;
+use crate::game::{self, Difficulty, Game, Player, PlayerSet, PlayerType};
+use crate::mcts::{self, Budget};
+use crate::net::{decode_board, encode_board};
+use crate::rng::Rng;
+use crate::search::{self, Control};
+
+/// Limits passed with `go`.  `None` leaves that limit off.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<u64>
+}
+
+impl Limits {
+    fn parse(words: &[&str]) -> Result<Limits, String> {
+        let mut limits = Limits::default();
+        for pair in words.chunks(2) {
+            let [name, value] = pair else { return Err(format!("'{}' needs a value", pair[0])); };
+            let bad_value = || format!("bad {} '{}'", name, value);
+            match *name {
+                "depth" => { limits.depth = Some(value.parse().map_err(|_| bad_value())?); },
+                "nodes" => { limits.nodes = Some(value.parse().map_err(|_| bad_value())?); },
+                "movetime" => { limits.movetime = Some(value.parse().map_err(|_| bad_value())?); },
+                _ => { return Err(format!("unknown limit '{}'", name)); }
+            }
+        }
+        Ok(limits)
+    }
+
+    /// The Monte Carlo budget: `movetime`, else `nodes`, else the default.
+    pub fn budget(self) -> Budget {
+        match (self.movetime, self.nodes) {
+            (Some(millis), _) if millis > 0 => Budget::Time(Duration::from_millis(millis)),
+            (_, Some(nodes)) if nodes > 0 => Budget::Iterations(u32::try_from(nodes).unwrap_or(u32::MAX)),
+            _ => Budget::default()
+        }
+    }
+
+    /// The limits for the iterative search, where 0 leaves a limit off.
+    pub fn search_limits(self) -> search::Limits {
+        search::Limits {
+            depth: self.depth.filter(|&depth| depth > 0),
+            nodes: self.nodes.filter(|&nodes| nodes > 0),
+            time: self.movetime.filter(|&millis| millis > 0).map(Duration::from_millis)
+        }
+    }
+
+    /// The limits as the words that follow `go`.
+    fn to_words(self) -> String {
+        let mut words = Vec::new();
+        if let Some(depth) = self.depth { words.push(format!("depth {}", depth)); }
+        if let Some(nodes) = self.nodes { words.push(format!("nodes {}", nodes)); }
+        if let Some(movetime) = self.movetime { words.push(format!("movetime {}", movetime)); }
+        words.join(" ")
+    }
+}
+
+fn engine_set() -> PlayerSet {
+    PlayerSet { x: Player::X(PlayerType::Human), o: Player::O(PlayerType::Human) }
+}
+
+/// The most threads the `threads` option takes.
+pub const MAX_THREADS: usize = 256;
+
+/// The engine side of the protocol.
+pub struct Engine {
+    difficulty: Difficulty,
+    threads: usize,
+    board: Board
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine { difficulty: Difficulty::Perfect, threads: 1, board: Board::default() }
+    }
+}
+
+impl Engine {
+    /// Starts the `threads` option at `threads` rather than 1.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.clamp(1, MAX_THREADS);
+        self
+    }
+
+    /// Answers commands from `input` until `quit` or the end of input.
+    ///
+    /// Input is read on a thread of its own, so `stop` reaches a search
+    /// while it runs.  That thread hands every `go` a fresh `Control` and
+    /// stops the latest one when `stop` comes.
+    pub fn run(&mut self, input: &mut (dyn Input + Send), output: &mut dyn Write) -> io::Result<()> {
+        let (sender, receiver) = mpsc::channel::<(String, Arc<Control<(usize, usize)>>)>();
+        thread::scope(|scope| {
+            let reader = scope.spawn(move || -> io::Result<()> {
+                let mut latest = Arc::new(Control::default());
+                while let Some(line) = input.read_line()? {
+                    let command = line.split_whitespace().next().unwrap_or("").to_string();
+                    match command.as_str() {
+                        "go" => { latest = Arc::new(Control::default()); },
+                        "stop" => { latest.stop(); },
+                        _ => { }
+                    }
+                    if sender.send((line, Arc::clone(&latest))).is_err() || command == "quit" {
+                        break;
+                    }
+                }
+                Ok(())
+            });
+
+            for (line, control) in receiver {
+                if !self.respond_within(&line, &control, output)? {
+                    break;
+                }
+                output.flush()?;
+            }
+            reader.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+
+    /// Handles one command.  Returns `false` after `quit`.
+    pub fn respond(&mut self, line: &str, output: &mut dyn Write) -> io::Result<bool> {
+        self.respond_within(line, &Control::default(), output)
+    }
+
+    /// `respond`, searching under `control` if the command is `go`.
+    fn respond_within(&mut self, line: &str, control: &Control<(usize, usize)>, output: &mut dyn Write) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["tttp"] => {
+                writeln!(output, "id name tic-tac-toe {}", env!("CARGO_PKG_VERSION"))?;
+                let labels: Vec<&str> = Difficulty::ALL.iter().map(|difficulty| difficulty.label()).collect();
+                writeln!(
+                    output,
+                    "option name difficulty type combo default {} var {}",
+                    self.difficulty.label(),
+                    labels.join(" var "))?;
+                writeln!(output, "option name threads type spin default {} min 1 max {}", self.threads, MAX_THREADS)?;
+                writeln!(output, "tttpok")?;
+            },
+            ["isready"] => { writeln!(output, "readyok")?; },
+            ["setoption", "name", "difficulty", "value", label] => match Difficulty::from_label(label) {
+                Some(difficulty) => { self.difficulty = difficulty; },
+                None => { writeln!(output, "info string unknown difficulty '{}'", label)?; }
+            },
+            ["setoption", "name", "threads", "value", count] => match count.parse() {
+                Ok(threads @ 1..=MAX_THREADS) => { self.threads = threads; },
+                _ => { writeln!(output, "info string bad thread count '{}'", count)?; }
+            },
+            ["setoption", ..] => { writeln!(output, "info string unknown option")?; },
+            ["newgame"] => { self.board = Board::default(); },
+            ["position", rest @ ..] => match parse_position_command(rest) {
+                Ok(board) => { self.board = board; },
+                Err(message) => { writeln!(output, "info string {}", message)?; }
+            },
+            ["go", limits @ ..] => match Limits::parse(limits) {
+                Ok(limits) => self.go(limits, control, output)?,
+                Err(message) => { writeln!(output, "info string {}", message)?; }
+            },
+            // `run` has already passed it on to the search.
+            ["stop"] => { },
+            ["quit"] => { return Ok(false); },
+            [] => { },
+            _ => { writeln!(output, "info string unknown command '{}'", line.trim())?; }
+        }
+        Ok(true)
+    }
+
+    fn go(&mut self, limits: Limits, control: &Control<(usize, usize)>, output: &mut dyn Write) -> io::Result<()> {
+        let set = PlayerSet { x: Player::X(PlayerType::Computer(self.difficulty)), o: Player::O(PlayerType::Computer(self.difficulty)) };
+        let turn = match self.board.side_to_move() {
+            Some(Square::X) => set.x,
+            _ => set.o
+        };
+        if game::winning_line(&self.board).is_some() {
+            return writeln!(output, "bestmove none");
+        }
+
+        let chosen = if self.difficulty == Difficulty::Mcts {
+            let search = mcts::search(&self.board, limits.budget(), &mut Rng::from_entropy());
+            for stats in &search.moves {
+                writeln!(output, "info move {} visits {} winrate {:.3}", position_name(stats.mv), stats.visits, stats.win_rate())?;
+            }
+            search.best.map(|pos| (pos, Some(game::move_score(&turn, &set, &self.board, pos))))
+        } else if self.difficulty == Difficulty::Perfect {
+            let progress = game::perfect_within(&turn, &self.board, limits.search_limits(), self.threads, control);
+            writeln!(output, "info depth {} nodes {} score {}", progress.depth, progress.nodes, progress.value)?;
+            progress.best.map(|pos| (pos, progress.solved.then_some(progress.value)))
+        } else {
+            match game::computer_move(&turn, &set, &self.board) {
+                Ok(updated) => updated.board().and_then(|after| self.board.changed_position(after))
+                    .map(|pos| (pos, Some(game::move_score(&turn, &set, &self.board, pos)))),
+                Err(_) => None
+            }
+        };
+        match chosen {
+            Some((pos, Some(score))) => writeln!(output, "bestmove {} score {}", position_name(pos), score),
+            Some((pos, None)) => writeln!(output, "bestmove {}", position_name(pos)),
+            None => writeln!(output, "bestmove none")
+        }
+    }
+}
+
+/// The board described by the words after `position`.
+fn parse_position_command(words: &[&str]) -> Result<Board, String> {
+    let (mut board, rest) = match words {
+        ["startpos", rest @ ..] => (Board::default(), rest),
+        ["board", board, rest @ ..] => (decode_board(board).map_err(|e| e.0)?, rest),
+        _ => return Err("position needs startpos or board".to_string())
+    };
+
+    let moves = match rest {
+        [] => &[][..],
+        ["moves", moves @ ..] => moves,
+        _ => return Err(format!("unexpected '{}'", rest.join(" ")))
+    };
+
+    let set = engine_set();
+    let mut is_over = game::winning_line(&board).is_some();
+    for name in moves {
+        if is_over {
+            return Err(format!("the game is over before {}", name));
+        }
+        let pos = parse_position(name).ok_or_else(|| format!("'{}' is not a square", name))?;
+        let turn = match board.side_to_move() {
+            Some(Square::X) => set.x,
+            Some(_) => set.o,
+            None => return Err("impossible board".to_string())
+        };
+        let after = game::make_move(&pos, &board, &turn, &set).map_err(|_| format!("{} is taken", name))?;
+        is_over = !matches!(after, Game::InPlay { .. });
+        board = *after.board().expect("make_move returns a board");
+    }
+
+    board.side_to_move().map(|_| board).ok_or_else(|| "impossible board".to_string())
+}
+
+/// A move and its score, as reported by an engine.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BestMove {
+    pub pos: (usize, usize),
+    pub score: Option<i32>
+}
+
+impl FromStr for BestMove {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (square, rest) = match words.as_slice() {
+            ["bestmove", square, rest @ ..] => (*square, rest),
+            _ => return Err(format!("expected bestmove, got '{}'", line.trim()))
+        };
+        let pos = parse_position(square).ok_or_else(|| format!("engine played '{}'", square))?;
+        let score = match rest {
+            ["score", score, ..] => Some(score.parse().map_err(|_| format!("bad score '{}'", score))?),
+            _ => None
+        };
+        Ok(BestMove { pos, score })
+    }
+}
+
+/// How long [`ExternalEngine::spawn`] waits for the handshake.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A separate engine program, driven over its stdin and stdout.
+pub struct ExternalEngine {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    /// The name it gave at the handshake.
+    pub name: String,
+    pub limits: Limits
+}
+
+impl ExternalEngine {
+    /// Starts `command`, split on whitespace into a program and its
+    /// arguments, and runs the handshake.
+    pub fn spawn(command: &str) -> io::Result<ExternalEngine> {
+        ExternalEngine::spawn_with_timeout(command, HANDSHAKE_TIMEOUT)
+    }
+
+    /// [`spawn`](Self::spawn), giving up and killing the program if the
+    /// handshake isn't done within `timeout`.
+    pub fn spawn_with_timeout(command: &str, timeout: Duration) -> io::Result<ExternalEngine> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let mut input = child.stdin.take().expect("stdin is piped");
+        let mut output = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        // Read on a thread of its own so a silent program can't hang us; it
+        // sees end of file once the program is killed.
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let name = handshake(&mut output);
+            let _ = sender.send(name.map(|name| (name, output)));
+        });
+
+        let handshake = writeln!(input, "tttp")
+            .and_then(|()| input.flush())
+            .and_then(|()| match receiver.recv_timeout(timeout) {
+                Ok(result) => result,
+                Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, format!("no handshake within {:?}", timeout)))
+            });
+        match handshake {
+            Ok((name, output)) => {
+                let name = name.unwrap_or_else(|| command.to_string());
+                Ok(ExternalEngine { child, input, output, name, limits: Limits::default() })
+            },
+            Err(error) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(error)
+            }
+        }
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.input, "{}", line)?;
+        self.input.flush()
+    }
+
+    fn read_reply(&mut self) -> io::Result<String> {
+        read_reply(&mut self.output)
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("newgame")?;
+        self.send("isready")?;
+        while self.read_reply()? != "readyok" { }
+        Ok(())
+    }
+
+    /// Asks for the best move on `board` within `self.limits`.
+    pub fn best_move(&mut self, board: &Board) -> io::Result<BestMove> {
+        self.send(&format!("position board {}", encode_board(board)))?;
+        self.send(format!("go {}", self.limits.to_words()).trim_end())?;
+        loop {
+            let line = self.read_reply()?;
+            if line.starts_with("bestmove") {
+                return line.parse().map_err(|message: String| io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        // Killed rather than waited for, in case it's stuck or ignores `quit`.
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Reads replies up to `tttpok`, returning the name given on the way if any.
+fn handshake(output: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut name = None;
+    loop {
+        let line = read_reply(output)?;
+        if let Some(given) = line.strip_prefix("id name ") {
+            name = Some(given.to_string());
+        } else if line == "tttpok" {
+            return Ok(name);
+        }
+    }
+}
+
+fn read_reply(output: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    if output.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "engine exited"));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+/// Plays a computer-controlled turn: the built-in players through
+/// `game::computer_move`, `PlayerType::Engine` through `engine`.
+pub fn play_turn(engine: Option<&mut ExternalEngine>, turn: &Player, set: &PlayerSet, board: &Board) -> io::Result<Game> {
+    if turn.player_type() != &PlayerType::Engine {
+        return game::computer_move(turn, set, board).map_err(|error| io::Error::other(error.to_string()));
+    }
+
+    let engine = engine.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "this game needs an engine: start it with --engine <command>"))?;
+    let best = engine.best_move(board)?;
+    game::make_move(&best.pos, board, turn, set)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("{} played {}, which is taken", engine.name, position_name(best.pos))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::mcts::Budget;
+    use crate::search;
+
+    use super::{BestMove, Engine, Limits};
+
+    fn respond(engine: &mut Engine, lines: &[&str]) -> String {
+        let mut output = Vec::new();
+        for line in lines {
+            engine.respond(line, &mut output).unwrap();
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_handshake() {
+        let reply = respond(&mut Engine::default(), &["tttp", "isready"]);
+        let lines: Vec<&str> = reply.lines().collect();
+        assert!(lines[0].starts_with("id name tic-tac-toe "));
+        assert_eq!(lines[1], "option name difficulty type combo default perfect var random var easy var perfect var mcts var learner var rules");
+        assert_eq!(lines[2], "option name threads type spin default 1 min 1 max 256");
+        assert_eq!(&lines[3..], ["tttpok", "readyok"]);
+    }
+
+    #[test]
+    fn test_go_finds_wins_and_blocks() {
+        let mut engine = Engine::default();
+        assert_eq!(respond(&mut engine, &["position startpos moves A1 B2 A2", "go"]), "info depth 6 nodes 206 score 0\nbestmove A3 score 0\n");
+        assert_eq!(respond(&mut engine, &["position board X-O/OO-/X-X", "go depth 9 movetime 100"]), "info depth 1 nodes 3 score 1\nbestmove C2 score 1\n");
+        // Two plies are too few to solve the opening, so there's no score.
+        assert_eq!(respond(&mut engine, &["position startpos", "go depth 2"]), "info depth 2 nodes 34 score 0\nbestmove A1\n");
+        assert_eq!(respond(&mut engine, &["position board XXX/OO-/---", "go"]), "bestmove none\n");
+        assert_eq!(respond(&mut engine, &["position startpos moves A1 A1"]), "info string A1 is taken\n");
+        assert_eq!(respond(&mut engine, &["go depth"]), "info string 'depth' needs a value\n");
+        assert_eq!(respond(&mut engine, &["setoption name difficulty value easy", "frobnicate"]), "info string unknown command 'frobnicate'\n");
+    }
+
+    #[test]
+    fn test_stopped_search_answers_without_a_score() {
+        let control = search::Control::default();
+        control.stop();
+        let mut output = Vec::new();
+        let mut engine = Engine::default();
+        engine.respond_within("go", &control, &mut output).unwrap();
+        // The search looks at the flag every so many positions, so it
+        // gets a few plies in before noticing.
+        assert_eq!(String::from_utf8(output).unwrap(), "info depth 5 nodes 1347 score 0\nbestmove A1\n");
+    }
+
+    #[test]
+    fn test_threads_keep_results() {
+        let mut engine = Engine::default().with_threads(4);
+        assert_eq!(respond(&mut engine, &["position startpos moves A1 B2 A2", "go"]), "info depth 6 nodes 206 score 0\nbestmove A3 score 0\n");
+        assert_eq!(respond(&mut engine, &["setoption name threads value 2", "position startpos", "go depth 2"]), "info depth 2 nodes 34 score 0\nbestmove A1\n");
+        assert_eq!(respond(&mut engine, &["setoption name threads value 0"]), "info string bad thread count '0'\n");
+    }
+
+    #[test]
+    fn test_mcts_reports_statistics() {
+        let mut engine = Engine::default();
+        let reply = respond(&mut engine, &["setoption name difficulty value mcts", "position startpos moves A1 B2 A2", "go nodes 3000"]);
+        let lines: Vec<&str> = reply.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[0].starts_with("info move A3 visits "), "{}", reply);
+        assert_eq!(lines[6], "bestmove A3 score 0");
+        let visits: u32 = lines[..6].iter().map(|line| line.split(' ').nth(4).unwrap().parse::<u32>().unwrap()).sum();
+        assert_eq!(visits, 3000);
+        assert_eq!(respond(&mut engine, &["position board XXX/OO-/---", "go movetime 10"]), "bestmove none\n");
+    }
+
+    #[test]
+    fn test_parse_replies() {
+        assert_eq!("bestmove B2 score -1".parse::<BestMove>().unwrap(), BestMove { pos: (1, 1), score: Some(-1) });
+        assert_eq!("bestmove C3".parse::<BestMove>().unwrap(), BestMove { pos: (2, 2), score: None });
+        assert!("bestmove none".parse::<BestMove>().is_err());
+
+        let limits = Limits { depth: Some(4), nodes: None, movetime: Some(250) };
+        assert_eq!(limits.to_words(), "depth 4 movetime 250");
+        assert_eq!(Limits::parse(&["depth", "4", "movetime", "250"]), Ok(limits));
+        assert!(Limits::parse(&["ply", "4"]).is_err());
+
+        assert_eq!(limits.budget(), Budget::Time(Duration::from_millis(250)));
+        assert_eq!(Limits { nodes: Some(500), ..Limits::default() }.budget(), Budget::Iterations(500));
+        assert_eq!(Limits::default().budget(), Budget::default());
+        assert_eq!(limits.search_limits(), search::Limits { depth: Some(4), nodes: None, time: Some(Duration::from_millis(250)) });
+        assert_eq!(Limits { depth: Some(0), nodes: Some(0), movetime: Some(0) }.search_limits(), search::Limits::default());
+    }
+}
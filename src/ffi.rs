@@ -0,0 +1,318 @@
+//! A C ABI over `game`, declared in `include/tic_tac_toe.h`, which
+//! build.rs generates from this file with cbindgen under the `header`
+//! feature.
+//!
+//! The crate also builds as `libtic_tac_toe.so` and `libtic_tac_toe.a`.
+//! A game is an opaque `ttt_game *` from `ttt_game_new`, released with
+//! `ttt_game_free`.  Every other call returns one of the `TTT_` codes
+//! below and writes its answer through an out pointer, which is left alone
+//! on error.
+//!
+//! | Code | Value | Meaning |
+//! |---|---|---|
+//! | `TTT_OK` | 0 | Success |
+//! | `TTT_ERR_NULL` | 1 | A pointer argument was null |
+//! | `TTT_ERR_BAD_PLAYER` | 2 | Not a player type label, or `engine` |
+//! | `TTT_ERR_BAD_SQUARE` | 3 | Not a square from `A1` to `C3` |
+//! | `TTT_ERR_SQUARE_TAKEN` | 4 | The square is already taken |
+//! | `TTT_ERR_GAME_OVER` | 5 | The game has been won or tied |
+//! | `TTT_ERR_COMPUTER_TURN` | 6 | A human move on the computer's turn |
+//! | `TTT_ERR_HUMAN_TURN` | 7 | A computer move on a human's turn |
+//! | `TTT_ERR_BUFFER_TOO_SMALL` | 8 | The board needs `TTT_BOARD_SIZE` bytes |
+//! | `TTT_ERR_INTERNAL` | 9 | A bug; the game is unchanged |
+//!
+//! The rules are those of the HTTP API in `api`: humans play with
+//! `ttt_make_move`, computers with `ttt_computer_move`.
+
+use std::ffi::{c_char, c_int, CStr};
+
+use crate::board::{parse_position, Board, Square};
+use crate::game::{self, Game, Player, PlayerSet, PlayerType};
+use crate::net::encode_board;
+
+pub const TTT_OK: c_int = 0;
+pub const TTT_ERR_NULL: c_int = 1;
+pub const TTT_ERR_BAD_PLAYER: c_int = 2;
+pub const TTT_ERR_BAD_SQUARE: c_int = 3;
+pub const TTT_ERR_SQUARE_TAKEN: c_int = 4;
+pub const TTT_ERR_GAME_OVER: c_int = 5;
+pub const TTT_ERR_COMPUTER_TURN: c_int = 6;
+pub const TTT_ERR_HUMAN_TURN: c_int = 7;
+pub const TTT_ERR_BUFFER_TOO_SMALL: c_int = 8;
+pub const TTT_ERR_INTERNAL: c_int = 9;
+
+pub const TTT_IN_PLAY: c_int = 0;
+pub const TTT_TIED: c_int = 1;
+pub const TTT_X_WON: c_int = 2;
+pub const TTT_O_WON: c_int = 3;
+
+/// Bytes `ttt_game_board` writes: `X--/-O-/---` and a NUL.
+pub const TTT_BOARD_SIZE: usize = 12;
+
+/// The handle behind `ttt_game *`.
+pub struct TttGame {
+    game: Game
+}
+
+/// A player type from a C string; null means human.
+unsafe fn player_type(label: *const c_char) -> Result<PlayerType, c_int> {
+    if label.is_null() {
+        return Ok(PlayerType::Human);
+    }
+    let label = CStr::from_ptr(label).to_str().map_err(|_| TTT_ERR_BAD_PLAYER)?;
+    match PlayerType::from_label(label) {
+        Some(PlayerType::Engine) | None => Err(TTT_ERR_BAD_PLAYER),
+        Some(player_type) => Ok(player_type)
+    }
+}
+
+fn in_play(game: &Game) -> Result<(PlayerSet, Player, Board), c_int> {
+    match *game {
+        Game::InPlay { set, turn, board } => Ok((set, turn, board)),
+        _ => Err(TTT_ERR_GAME_OVER)
+    }
+}
+
+fn code(result: Result<(), c_int>) -> c_int {
+    match result {
+        Ok(()) => TTT_OK,
+        Err(code) => code
+    }
+}
+
+/// Starts a game between `x` and `o`, each a player type label such as
+/// `"human"` or `"computer:perfect"`, or null for a human.
+///
+/// # Safety
+///
+/// `x` and `o` must each be null or a NUL-terminated string, and `out`
+/// must be null or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_game_new(x: *const c_char, o: *const c_char, out: *mut *mut TttGame) -> c_int {
+    if out.is_null() {
+        return TTT_ERR_NULL;
+    }
+    let (x, o) = match (player_type(x), player_type(o)) {
+        (Ok(x), Ok(o)) => (Player::X(x), Player::O(o)),
+        _ => return TTT_ERR_BAD_PLAYER
+    };
+    let game = Game::InPlay { set: PlayerSet { x, o }, turn: x, board: Board::default() };
+    *out = Box::into_raw(Box::new(TttGame { game }));
+    TTT_OK
+}
+
+/// Releases a game.  Null is ignored.
+///
+/// # Safety
+///
+/// `game` must be null or come from `ttt_game_new`, and must not be used
+/// again.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_game_free(game: *mut TttGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Plays `square`, such as `"B2"`, for the human whose turn it is.
+///
+/// # Safety
+///
+/// `game` must be null or a live handle, and `square` null or a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_make_move(game: *mut TttGame, square: *const c_char) -> c_int {
+    if game.is_null() || square.is_null() {
+        return TTT_ERR_NULL;
+    }
+    let handle = &mut *game;
+    let square = CStr::from_ptr(square);
+    code((|| {
+        let (set, turn, board) = in_play(&handle.game)?;
+        if turn.player_type() != &PlayerType::Human {
+            return Err(TTT_ERR_COMPUTER_TURN);
+        }
+        let pos = square.to_str().ok().and_then(parse_position).ok_or(TTT_ERR_BAD_SQUARE)?;
+        handle.game = game::make_move(&pos, &board, &turn, &set).map_err(|_| TTT_ERR_SQUARE_TAKEN)?;
+        Ok(())
+    })())
+}
+
+/// Lets the computer whose turn it is move.
+///
+/// # Safety
+///
+/// `game` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_computer_move(game: *mut TttGame) -> c_int {
+    if game.is_null() {
+        return TTT_ERR_NULL;
+    }
+    let handle = &mut *game;
+    code((|| {
+        let (set, turn, board) = in_play(&handle.game)?;
+        if turn.player_type() == &PlayerType::Human {
+            return Err(TTT_ERR_HUMAN_TURN);
+        }
+        handle.game = game::computer_move(&turn, &set, &board).map_err(|_| TTT_ERR_INTERNAL)?;
+        Ok(())
+    })())
+}
+
+/// Writes `TTT_IN_PLAY`, `TTT_TIED`, `TTT_X_WON` or `TTT_O_WON`.
+///
+/// # Safety
+///
+/// `game` must be null or a live handle, and `status` null or valid for a
+/// write.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_game_status(game: *const TttGame, status: *mut c_int) -> c_int {
+    if game.is_null() || status.is_null() {
+        return TTT_ERR_NULL;
+    }
+    *status = match (*game).game {
+        Game::InPlay { .. } | Game::Uninitiated => TTT_IN_PLAY,
+        Game::Tie(_) => TTT_TIED,
+        Game::Win(winner, _) if winner.associated_square() == Square::X => TTT_X_WON,
+        Game::Win(..) => TTT_O_WON
+    };
+    TTT_OK
+}
+
+/// Writes `'X'` or `'O'` for the side to move, or fails with
+/// `TTT_ERR_GAME_OVER`.
+///
+/// # Safety
+///
+/// `game` must be null or a live handle, and `mark` null or valid for a
+/// write.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_game_turn(game: *const TttGame, mark: *mut c_char) -> c_int {
+    if game.is_null() || mark.is_null() {
+        return TTT_ERR_NULL;
+    }
+    code(in_play(&(*game).game).map(|(_, turn, _)| {
+        *mark = turn.associated_square().notation() as c_char;
+    }))
+}
+
+/// Writes the board as rows A, B and C of `X`, `O` and `-` joined by `/`,
+/// NUL-terminated, into `buffer` of `size` bytes.
+///
+/// # Safety
+///
+/// `game` must be null or a live handle, and `buffer` null or valid for
+/// `size` bytes of writes.
+#[no_mangle]
+pub unsafe extern "C" fn ttt_game_board(game: *const TttGame, buffer: *mut c_char, size: usize) -> c_int {
+    if game.is_null() || buffer.is_null() {
+        return TTT_ERR_NULL;
+    }
+    if size < TTT_BOARD_SIZE {
+        return TTT_ERR_BUFFER_TOO_SMALL;
+    }
+    let board = (*game).game.board().copied().unwrap_or_default();
+    let text = encode_board(&board);
+    std::ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, buffer, text.len());
+    *buffer.add(text.len()) = 0;
+    TTT_OK
+}
+
+/// A short English description of a `TTT_` code, for logs.  The string
+/// is static and must not be freed.
+#[no_mangle]
+pub extern "C" fn ttt_error_message(code: c_int) -> *const c_char {
+    let message: &'static CStr = match code {
+        TTT_OK => c"ok",
+        TTT_ERR_NULL => c"a pointer argument was null",
+        TTT_ERR_BAD_PLAYER => c"unknown player type",
+        TTT_ERR_BAD_SQUARE => c"not a square from A1 to C3",
+        TTT_ERR_SQUARE_TAKEN => c"the square is already taken",
+        TTT_ERR_GAME_OVER => c"the game is over",
+        TTT_ERR_COMPUTER_TURN => c"it is the computer's turn",
+        TTT_ERR_HUMAN_TURN => c"it is a human's turn",
+        TTT_ERR_BUFFER_TOO_SMALL => c"the buffer is too small",
+        TTT_ERR_INTERNAL => c"internal error",
+        _ => c"unknown error code"
+    };
+    message.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{c_char, c_int, CStr};
+    use std::ptr;
+
+    use super::*;
+
+    fn board(game: *const TttGame) -> String {
+        let mut buffer = [0 as c_char; TTT_BOARD_SIZE];
+        assert_eq!(unsafe { ttt_game_board(game, buffer.as_mut_ptr(), buffer.len()) }, TTT_OK);
+        unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap().to_string()
+    }
+
+    fn status(game: *const TttGame) -> c_int {
+        let mut status = -1;
+        assert_eq!(unsafe { ttt_game_status(game, &mut status) }, TTT_OK);
+        status
+    }
+
+    #[test]
+    fn test_human_game() {
+        let mut game = ptr::null_mut();
+        unsafe {
+            assert_eq!(ttt_game_new(ptr::null(), c"human".as_ptr(), &mut game), TTT_OK);
+            for square in [c"A1", c"B1", c"A2", c"B2"] {
+                assert_eq!(ttt_make_move(game, square.as_ptr()), TTT_OK);
+            }
+            assert_eq!(ttt_make_move(game, c"B2".as_ptr()), TTT_ERR_SQUARE_TAKEN);
+            assert_eq!(ttt_make_move(game, c"D4".as_ptr()), TTT_ERR_BAD_SQUARE);
+            assert_eq!(ttt_computer_move(game), TTT_ERR_HUMAN_TURN);
+
+            let mut mark = 0;
+            assert_eq!(ttt_game_turn(game, &mut mark), TTT_OK);
+            assert_eq!(mark as u8, b'X');
+            assert_eq!(board(game), "XX-/OO-/---");
+
+            assert_eq!(ttt_make_move(game, c"a3".as_ptr()), TTT_OK);
+            assert_eq!(status(game), TTT_X_WON);
+            assert_eq!(ttt_game_turn(game, &mut mark), TTT_ERR_GAME_OVER);
+            assert_eq!(ttt_make_move(game, c"C3".as_ptr()), TTT_ERR_GAME_OVER);
+            ttt_game_free(game);
+        }
+    }
+
+    #[test]
+    fn test_computer_game() {
+        let mut game = ptr::null_mut();
+        unsafe {
+            assert_eq!(ttt_game_new(c"computer:perfect".as_ptr(), c"computer:perfect".as_ptr(), &mut game), TTT_OK);
+            assert_eq!(ttt_make_move(game, c"B2".as_ptr()), TTT_ERR_COMPUTER_TURN);
+            while status(game) == TTT_IN_PLAY {
+                assert_eq!(ttt_computer_move(game), TTT_OK);
+            }
+            assert_eq!(status(game), TTT_TIED);
+            ttt_game_free(game);
+        }
+    }
+
+    #[test]
+    fn test_bad_arguments() {
+        let mut game = ptr::null_mut();
+        unsafe {
+            assert_eq!(ttt_game_new(c"wizard".as_ptr(), ptr::null(), &mut game), TTT_ERR_BAD_PLAYER);
+            assert_eq!(ttt_game_new(c"engine".as_ptr(), ptr::null(), &mut game), TTT_ERR_BAD_PLAYER);
+            assert!(game.is_null());
+            assert_eq!(ttt_game_new(ptr::null(), ptr::null(), ptr::null_mut()), TTT_ERR_NULL);
+            assert_eq!(ttt_make_move(ptr::null_mut(), c"A1".as_ptr()), TTT_ERR_NULL);
+
+            assert_eq!(ttt_game_new(ptr::null(), ptr::null(), &mut game), TTT_OK);
+            let mut small = [0 as c_char; TTT_BOARD_SIZE - 1];
+            assert_eq!(ttt_game_board(game, small.as_mut_ptr(), small.len()), TTT_ERR_BUFFER_TOO_SMALL);
+            ttt_game_free(game);
+            ttt_game_free(ptr::null_mut());
+
+            assert_eq!(CStr::from_ptr(ttt_error_message(TTT_ERR_GAME_OVER)).to_str().unwrap(), "the game is over");
+        }
+    }
+}
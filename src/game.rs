@@ -1,3 +1,5 @@
+#![allow(clippy::legacy_numeric_constants)]
+
 use std::fmt;
 use std::usize;
 
@@ -5,10 +7,70 @@ use crate::board::Board;
 use crate::board::Square;
 use crate::board::XPos;
 use crate::board::YPos;
+use crate::learn;
+use crate::mcts;
+use crate::rng::{self, Rng};
+use crate::rules;
+use crate::search::{self, Control, Limits, Outcome, Position, Progress};
+use crate::solve;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Difficulty { 
+    Random, Easy, Perfect,
+    /// Monte Carlo tree search with the default budget; see `mcts`.
+    Mcts,
+    /// Plays from what it has learned; see `learn`.
+    Learner,
+    /// Follows Newell and Simon's ordered rules; see `rules`.
+    Rules
+}
+
+impl Difficulty { 
+    pub const ALL: [Difficulty; 6] = [
+        Difficulty::Random, Difficulty::Easy, Difficulty::Perfect, Difficulty::Mcts, Difficulty::Learner, Difficulty::Rules
+    ];
+
+    pub fn label(self) -> &'static str { 
+        match self { 
+            Self::Random => "random",
+            Self::Easy => "easy",
+            Self::Perfect => "perfect",
+            Self::Mcts => "mcts",
+            Self::Learner => "learner",
+            Self::Rules => "rules"
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Difficulty> { 
+        Self::ALL.into_iter().find(|d| d.label() == label)
+    }
+}
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum PlayerType { 
-    Human, Computer
+    Human, Computer(Difficulty),
+    /// An external program speaking the engine protocol; see `engine`.
+    Engine
+}
+
+impl PlayerType { 
+    /// `human`, `engine`, or `computer:` followed by the difficulty label.
+    pub fn label(self) -> String { 
+        match self { 
+            Self::Human => "human".to_string(),
+            Self::Computer(difficulty) => format!("computer:{}", difficulty.label()),
+            Self::Engine => "engine".to_string()
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<PlayerType> { 
+        match label.split_once(':') { 
+            None if label == "human" => Some(PlayerType::Human),
+            None if label == "engine" => Some(PlayerType::Engine),
+            Some(("computer", difficulty)) => Difficulty::from_label(difficulty).map(PlayerType::Computer),
+            _ => None
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -18,13 +80,6 @@ pub enum Player {
 }
 
 impl Player { 
-    pub fn to_string(&self) -> &str { 
-        match self { 
-            Self::X(_t) => "❌",
-            Self::O(_t) => "⭕️"
-        }
-    }
-
     pub fn associated_square(&self) -> Square { 
         match self { 
             Self::X(_t) => Square::X,
@@ -55,7 +110,7 @@ impl PlayerSet {
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Game { 
     Uninitiated,
     InPlay {set: PlayerSet, turn: Player, board: Board},
@@ -63,6 +118,15 @@ pub enum Game {
     Win(Player, Board)
 }
 
+impl Game { 
+    pub fn board(&self) -> Option<&Board> { 
+        match self { 
+            Game::Uninitiated => None,
+            Game::InPlay { board, .. } | Game::Tie(board) | Game::Win(_, board) => Some(board)
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MoveError;
 impl std::fmt::Display for MoveError { 
@@ -79,16 +143,7 @@ impl std::fmt::Display for PlacementError {
     }
 }
 
-#[allow(dead_code)]
-pub fn pretty_print(game: &Game) { 
-    match game { 
-        Game::Uninitiated => { println!("unitiated"); },
-        Game::Tie(board) => { board.pretty_print(); },
-        Game::Win(_, board) => { board.pretty_print(); },
-        Game::InPlay { board, .. } => { board.pretty_print(); }
-    }
-}
-
+#[allow(clippy::clone_on_copy, clippy::needless_borrow, clippy::needless_return)]
 pub fn make_move(
     pos: &(usize, usize), 
     board: &Board, 
@@ -144,6 +199,7 @@ impl Board {
     }
 }
 
+#[allow(clippy::len_zero)]
 fn is_tie(board: &Board) -> bool { 
     let empty = board.get_empty_squares();
     if empty.len() != 0 { return false; }
@@ -152,6 +208,7 @@ fn is_tie(board: &Board) -> bool {
     !(x_win || o_win)
 }
 
+#[allow(clippy::needless_range_loop)]
 fn is_win(player: &Player, board: &Board) -> bool { 
     let mut row_ctr_ary: [u8; 3] = [0; 3];
     let mut col_ctr_ary: [u8; 3] = [0; 3];
@@ -178,112 +235,156 @@ fn is_win(player: &Player, board: &Board) -> bool {
     r_diag_ctr == 3
 }
 
+/// The eight lines that win the game: rows, columns, then diagonals.
+pub const LINES: [[(usize, usize); 3]; 8] = [
+    [(0, 0), (0, 1), (0, 2)],
+    [(1, 0), (1, 1), (1, 2)],
+    [(2, 0), (2, 1), (2, 2)],
+    [(0, 0), (1, 0), (2, 0)],
+    [(0, 1), (1, 1), (2, 1)],
+    [(0, 2), (1, 2), (2, 2)],
+    [(0, 0), (1, 1), (2, 2)],
+    [(0, 2), (1, 1), (2, 0)]
+];
+
+/// The completed line on a won board, if there is one.
+pub fn winning_line(board: &Board) -> Option<[(usize, usize); 3]> { 
+    LINES.into_iter().find(|line| { 
+        let first = board.squares[line[0].0][line[0].1];
+        first != Square::Empty && line.iter().all(|pos| board.squares[pos.0][pos.1] == first)
+    })
+}
+
 pub fn computer_move(
     turn: &Player, 
     set: &PlayerSet, 
     board: &Board) -> Result<Game, MoveError> {
 
-        let mut chosen_pos: Option<(usize, usize)> = Option::None;
-        let mut high_score = -2;
-        let open_pos = board.get_empty_squares();
-
-        if open_pos.len() == 9 { 
-            // empty board, move to corner.
-            let mut copy_board = board.clone();
-            copy_board.squares[XPos::A][YPos::_1] = turn.associated_square();
-            let game = Game::InPlay { set: *set, turn: set.opposite_player(turn), board: copy_board };
-            return Result::Ok(game);
-        }
+        let difficulty = match turn.player_type() { 
+            PlayerType::Computer(difficulty) => *difficulty,
+            PlayerType::Human | PlayerType::Engine => Difficulty::Perfect
+        };
 
-        for pos in open_pos { 
-            let mut copy_board = board.clone();
-            copy_board.squares[pos.0][pos.1] = turn.associated_square();
-            let score = minimax(turn, turn, set, &copy_board, &0);
-            if score == 1 {
-                chosen_pos = Option::Some(pos); 
-                break;
-            } else if score > high_score {
-                high_score = score;
-                chosen_pos = Option::Some(pos); 
-            }
-        }
+        let chosen_pos = match difficulty { 
+            Difficulty::Random => random_position(board),
+            Difficulty::Easy => easy_position(turn, set, board),
+            Difficulty::Perfect => perfect_position(turn, set, board),
+            Difficulty::Mcts => mcts::search(board, mcts::Budget::default(), &mut Rng::from_entropy()).best,
+            Difficulty::Learner => learn::learner_move(board),
+            Difficulty::Rules => rules::choose(board).map(|choice| choice.pos)
+        };
 
         match chosen_pos {
             None => { Result::Err(MoveError) },
-            Some(pos) => {
-                let mut copy_board = board.clone();
-                copy_board.squares[pos.0][pos.1] = turn.associated_square();
-
-                if is_tie(&copy_board) { 
-                    return Result::Ok(Game::Tie(copy_board));
-                } else if is_win(turn, &copy_board) { 
-                    return Result::Ok(Game::Win(*turn, copy_board));
-                }
+            Some(pos) => { make_move(&pos, board, turn, set).map_err(|_| MoveError) }
+        }
+}
 
-                let next_turn = set.opposite_player(turn);
-                let game = Game::InPlay { set: *set, turn: next_turn, board: copy_board };
-                Result::Ok(game)
-            }
+fn random_position(board: &Board) -> Option<(usize, usize)> { 
+    let open_pos = board.get_empty_squares();
+    if open_pos.is_empty() { 
+        return Option::None;
+    }
+    Option::Some(open_pos[rng::below(open_pos.len())])
+}
+
+/// Takes a win when one is on the board, blocks the opponent's win when
+/// one is threatened, and otherwise plays a random open square.
+fn easy_position(turn: &Player, set: &PlayerSet, board: &Board) -> Option<(usize, usize)> { 
+    let opponent = set.opposite_player(turn);
+    winning_position(turn, board)
+        .or_else(|| winning_position(&opponent, board))
+        .or_else(|| random_position(board))
+}
+
+fn winning_position(player: &Player, board: &Board) -> Option<(usize, usize)> { 
+    board.get_empty_squares().into_iter().find(|pos| { 
+        let mut copy_board = *board;
+        copy_board.squares[pos.0][pos.1] = player.associated_square();
+        is_win(player, &copy_board)
+    })
+}
+
+/// The first move in notation order that keeps the position's value: from
+/// the tablebase when the board is one a game reaches with `turn` to move,
+/// otherwise by searching.
+fn perfect_position(turn: &Player, _set: &PlayerSet, board: &Board) -> Option<(usize, usize)> { 
+    if board.side_to_move() == Some(turn.associated_square()) { 
+        if let Some(&pos) = solve::tablebase().best_moves(board).unwrap_or_default().first() { 
+            return Some(pos);
         }
+    }
+    perfect_within(turn, board, Limits::default(), 1, &Control::default()).best
 }
 
-fn minimax(
+/// Searches for `turn`'s move on `board` one ply deeper at a time, for
+/// callers that can't wait for the whole tree or want to stop it early.
+/// More than one thread splits the search without changing its result.
+pub fn perfect_within(
     turn: &Player,
-    maximizing_player: &Player, 
-    set: &PlayerSet, 
     board: &Board,
-    depth: &usize) -> i32 { 
+    limits: Limits,
+    threads: usize,
+    control: &Control<(usize, usize)>) -> Progress<(usize, usize)> { 
 
-        if is_tie(board) { 
-            return 0; 
-        }
-        if is_win(turn, board) { 
-            let score: i32 = if turn == maximizing_player { 1 } else { -1 };
-            return score; 
-        }
+        search::parallel(&Turn { board: *board, mover: turn.associated_square() }, limits, control, threads)
+}
 
-        let remaining_positions = board.get_empty_squares();
-        let next_turn = set.opposite_player(turn);
+/// How the game ends for `turn`, to move on `board`, when both sides play
+/// perfectly: 1 for a win, 0 for a draw and -1 for a loss.
+pub fn perfect_score(turn: &Player, _set: &PlayerSet, board: &Board) -> i32 { 
+    search::value(&Turn { board: *board, mover: turn.associated_square() })
+}
 
-        let mut scores: Vec<i32> = Vec::new();
+/// What `mover` playing `pos` on `board` is worth to them, assuming
+/// perfect play afterwards.
+pub fn move_score(mover: &Player, set: &PlayerSet, board: &Board, pos: (usize, usize)) -> i32 { 
+    match make_move(&pos, board, mover, set) { 
+        Ok(Game::Win(..)) => 1,
+        Ok(Game::InPlay { turn, board, .. }) => -perfect_score(&turn, set, &board),
+        _ => 0
+    }
+}
 
-        for pos in remaining_positions {
-            let mut copy_board = board.clone();
-            copy_board.squares[pos.0][pos.1] = next_turn.associated_square();
-            let next_depth = *depth + 1;
-            let result = minimax(&next_turn, maximizing_player, set, &copy_board, &next_depth);
+/// A classic board with the side to move given outright, rather than
+/// worked out from the counts as `Board` does, for boards set up by hand.
+#[derive(Clone, Copy)]
+struct Turn { 
+    board: Board,
+    mover: Square
+}
 
-            scores.insert(0, result);
+impl Position for Turn { 
+    type Move = (usize, usize);
 
-            if &next_turn == maximizing_player { 
-                if result == 1 {
-                    return 1; 
-                }
-            } else { 
-                if result == -1 { 
-                    return -1; 
-                }
-            }
-        }
+    fn moves(&self) -> Vec<(usize, usize)> { 
+        if winning_line(&self.board).is_some() { Vec::new() } else { self.board.get_empty_squares() }
+    }
 
-        if &next_turn == maximizing_player { 
-            scores.sort();
-            let result = scores.last().unwrap_or(&0);
-            return *result;
-        } else { 
-            scores.sort();
-            let result = scores.first().unwrap_or(&0);
-            return *result;
-        }
+    fn play(&self, pos: (usize, usize)) -> Turn { 
+        let mut board = self.board;
+        board.squares[pos.0][pos.1] = self.mover;
+        Turn { board, mover: if self.mover == Square::X { Square::O } else { Square::X } }
+    }
+
+    fn side_to_move(&self) -> usize { 
+        usize::from(self.mover == Square::O)
+    }
+
+    fn outcome(&self) -> Option<Outcome> { 
+        Position::outcome(&self.board)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
-    use crate::{board::{Board, XPos, YPos}, game::{Player, PlayerType}};
+    use crate::{board::{Board, XPos, YPos}, game::{Difficulty, Player, PlayerType}};
 
-    use super::{is_win, make_move, PlayerSet, Game, is_tie, minimax, computer_move};
+    use crate::search;
+
+    use super::{is_win, make_move, PlayerSet, Game, is_tie, computer_move, perfect_score, winning_line, Turn};
 
     fn player_x() -> &'static Player { 
         &Player::X(PlayerType::Human)
@@ -390,6 +491,18 @@ mod tests {
         assert!(!x_d_win);
     }
 
+    #[test]
+    fn test_winning_line() { 
+        let board = Board::from_str(l_diag_win_string()).unwrap();
+        assert_eq!(winning_line(&board), Some([(0, 0), (1, 1), (2, 2)]));
+
+        let board = Board::from_str(x_win_build_string()).unwrap();
+        assert_eq!(winning_line(&board), Some([(2, 0), (2, 1), (2, 2)]));
+
+        let board = Board::from_str(tie_build_string()).unwrap();
+        assert_eq!(winning_line(&board), None);
+    }
+
     #[test]
     fn test_is_tie() { 
         let board = Board::from_str(tie_build_string()).unwrap();
@@ -408,20 +521,17 @@ mod tests {
     #[test]
     fn test_min_max() { 
         let set = &PlayerSet { 
-            x: Player::X(PlayerType::Computer), 
-            o: Player::O(PlayerType::Computer) };
+            x: Player::X(PlayerType::Computer(Difficulty::Perfect)), 
+            o: Player::O(PlayerType::Computer(Difficulty::Perfect)) };
         let board = Board::from_str(x_minmax_setup_str()).unwrap();
 
         // FROM HERE
         let mut winning_pos: Option<(usize, usize)> = Option::None;
         let open_pos = board.get_empty_squares();
+        let turn = Turn { board, mover: set.x.associated_square() };
 
         for pos in open_pos { 
-            println!("**** search for pos: {:?}", pos);
-            let mut copy_board = board.clone();
-            copy_board.squares[pos.0][pos.1] = set.x.associated_square();
-            let score = minimax(&set.x, &set.x, set, &copy_board, &0);
-            println!("**** score {} for pos: {:?}", score, pos);
+            let score = search::move_value(&turn, pos);
             if score == 1 { 
                 winning_pos = Option::Some(pos); 
                 break;
@@ -436,8 +546,8 @@ mod tests {
     #[test]
     fn test_computer_move_defend() {
         let set = &PlayerSet { 
-            x: Player::X(PlayerType::Computer), 
-            o: Player::O(PlayerType::Computer) };
+            x: Player::X(PlayerType::Computer(Difficulty::Perfect)), 
+            o: Player::O(PlayerType::Computer(Difficulty::Perfect)) };
         let board = Board::from_str(o_defend_setup_str()).unwrap();
 
         let result = computer_move(&set.o, set, &board);
@@ -453,8 +563,8 @@ mod tests {
     #[test]
     fn test_computer_move_win() {
         let set = &PlayerSet { 
-            x: Player::X(PlayerType::Computer), 
-            o: Player::O(PlayerType::Computer) };
+            x: Player::X(PlayerType::Computer(Difficulty::Perfect)), 
+            o: Player::O(PlayerType::Computer(Difficulty::Perfect)) };
         let board = Board::from_str(x_win_setup_str()).unwrap();
         let result = computer_move(&set.x, set, &board);
         assert!(result.is_ok());
@@ -466,6 +576,65 @@ mod tests {
         assert_eq!(res_game, exp_game)
     }
 
+    #[test]
+    fn test_easy_computer_blocks_and_wins() {
+        let set = &PlayerSet { 
+            x: Player::X(PlayerType::Computer(Difficulty::Easy)), 
+            o: Player::O(PlayerType::Computer(Difficulty::Easy)) };
+
+        let board = Board::from_str(o_defend_setup_str()).unwrap();
+        let result = computer_move(&set.o, set, &board).unwrap();
+        let exp_board = Board::from_str(o_defend_exp_str()).unwrap();
+        assert_eq!(result, Game::InPlay { set: *set, turn: set.x, board: exp_board });
+
+        let board = Board::from_str(x_win_setup_str()).unwrap();
+        let result = computer_move(&set.x, set, &board).unwrap();
+        let exp_board = Board::from_str(x_win_exp_str()).unwrap();
+        assert_eq!(result, Game::Win(set.x, exp_board));
+    }
+
+    #[test]
+    fn test_random_computer_fills_open_square() {
+        let set = &PlayerSet { 
+            x: Player::X(PlayerType::Computer(Difficulty::Random)), 
+            o: Player::O(PlayerType::Human) };
+        let board = Board::from_str(almost_tie_build_string()).unwrap();
+        let result = computer_move(&set.x, set, &board).unwrap();
+        let exp_board = Board::from_str(tie_build_string()).unwrap();
+        assert_eq!(result, Game::Tie(exp_board));
+    }
+
+    #[test]
+    fn test_difficulty_labels() {
+        for difficulty in Difficulty::ALL { 
+            assert_eq!(Difficulty::from_label(difficulty.label()), Some(difficulty));
+        }
+        assert_eq!(Difficulty::from_label("impossible"), None);
+
+        let computer = PlayerType::Computer(Difficulty::Easy);
+        assert_eq!(computer.label(), "computer:easy");
+        assert_eq!(PlayerType::from_label(&computer.label()), Some(computer));
+        assert_eq!(PlayerType::from_label("human"), Some(PlayerType::Human));
+        assert_eq!(PlayerType::from_label("engine"), Some(PlayerType::Engine));
+        assert_eq!(PlayerType::from_label("computer"), None);
+    }
+
+    #[test]
+    fn test_perfect_score() {
+        let empty = Board::from_str(empty_build_string()).unwrap();
+        assert_eq!(perfect_score(player_x(), player_set(), &empty), 0);
+
+        // X to move can complete row C.
+        let almost_win = Board::from_str(x_almost_win_build_string()).unwrap();
+        assert_eq!(perfect_score(player_x(), player_set(), &almost_win), 1);
+
+        // X to move forks with C1, threatening B1 and A3.
+        let forked = Board::from_str("XO-\n-X-\n--O").unwrap();
+        assert_eq!(perfect_score(player_x(), player_set(), &forked), 1);
+        let tied = Board::from_str(tie_build_string()).unwrap();
+        assert_eq!(perfect_score(player_o(), player_set(), &tied), 0);
+    }
+
     fn empty_build_string() -> &'static str { 
         "---\n---\n---"
     }
@@ -0,0 +1,232 @@
+//! A small HTTP/1.1 server: one thread and one request per connection.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Bodies past this size are refused rather than read.
+const MAX_BODY: usize = 64 * 1024;
+/// Likewise the request line and headers, together.
+const MAX_HEAD: usize = 16 * 1024;
+/// How long a client has to send its whole request, however it paces it.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long each write of the response may block.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Request {
+    pub method: String,
+    /// The path without its query string.
+    pub path: String,
+    pub body: String
+}
+
+impl Request {
+    pub fn new(method: &str, path: &str, body: &str) -> Request {
+        Request { method: method.to_string(), path: path.to_string(), body: body.to_string() }
+    }
+
+    /// Reads one request, or `None` if the client closed the connection
+    /// without sending one.
+    pub fn read(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut head_left = MAX_HEAD;
+        let request_line = read_head_line(reader, &mut head_left)?;
+        if request_line.is_empty() {
+            return Ok(None);
+        }
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(invalid("bad request line"));
+        };
+        let path = target.split('?').next().unwrap_or(target);
+
+        let mut content_length = 0;
+        loop {
+            let header = read_head_line(reader, &mut head_left)?;
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().map_err(|_| invalid("bad Content-Length"))?;
+                }
+            }
+        }
+        if content_length > MAX_BODY {
+            return Err(invalid("body too large"));
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        let body = String::from_utf8(body).map_err(|_| invalid("body is not UTF-8"))?;
+        Ok(Some(Request::new(method, path, &body)))
+    }
+}
+
+/// One line of the request line and headers, counted against `left`.
+fn read_head_line(reader: &mut impl BufRead, left: &mut usize) -> io::Result<String> {
+    let mut line = String::new();
+    let read = reader.take(*left as u64 + 1).read_line(&mut line)?;
+    if read > *left {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "headers too large"));
+    }
+    *left -= read;
+    Ok(line)
+}
+
+/// A connection whose reads give up once `deadline` passes, so a client
+/// can't hold its thread by sending a byte at a time.
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "request took too long"));
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &'static str, body: String) -> Response {
+        Response { status, content_type, body }
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+             Access-Control-Allow-Origin: *\r\n\
+             Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+             Access-Control-Allow-Headers: Content-Type\r\n\
+             Connection: close\r\n\r\n{}",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len(),
+            self.body)?;
+        out.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        _ => "Internal Server Error"
+    }
+}
+
+/// Anything that can answer requests.
+pub trait Handler: Send + Sync {
+    fn handle(&self, request: &Request) -> Response;
+}
+
+/// Answers connections on `listener` with `handler` until the listener
+/// fails.
+pub fn serve(listener: TcpListener, handler: Arc<dyn Handler>) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let handler = Arc::clone(&handler);
+        thread::spawn(move || {
+            // A client that hangs up mid-request has nothing left to tell.
+            let _ = answer(stream, handler.as_ref());
+        });
+    }
+    Ok(())
+}
+
+fn answer(stream: TcpStream, handler: &dyn Handler) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut reader = BufReader::new(Deadline { stream, deadline: Instant::now() + READ_TIMEOUT });
+    let response = match Request::read(&mut reader) {
+        Ok(Some(request)) => handler.handle(&request),
+        Ok(None) => return Ok(()),
+        Err(error) if matches!(error.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => {
+            Response::new(408, "text/plain", "request took too long\n".to_string())
+        },
+        Err(error) => Response::new(400, "text/plain", format!("{}\n", error))
+    };
+    response.write(&mut writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::time::{Duration, Instant};
+
+    use super::{Deadline, Request, Response, MAX_HEAD};
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /games/3/moves?x=1 HTTP/1.1\r\nHost: localhost\r\ncontent-length: 17\r\n\r\n{\"square\": \"B2\"}\n";
+        let request = Request::read(&mut Cursor::new(raw)).unwrap().unwrap();
+        assert_eq!(request, Request::new("POST", "/games/3/moves", "{\"square\": \"B2\"}\n"));
+
+        assert_eq!(Request::read(&mut Cursor::new("")).unwrap(), None);
+        assert!(Request::read(&mut Cursor::new("GET\r\n\r\n")).is_err());
+        assert!(Request::read(&mut Cursor::new("POST / HTTP/1.1\r\nContent-Length: 999999\r\n\r\n")).is_err());
+
+        let long_header = format!("GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(MAX_HEAD));
+        assert!(Request::read(&mut Cursor::new(long_header)).is_err());
+        let many_headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X-A: b\r\n".repeat(MAX_HEAD / 8));
+        assert!(Request::read(&mut Cursor::new(many_headers)).is_err());
+        let endless_line = "G".repeat(MAX_HEAD * 4);
+        assert!(Request::read(&mut Cursor::new(endless_line)).is_err());
+    }
+
+    #[test]
+    fn test_deadline_covers_the_whole_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let started = Instant::now();
+        let mut reader = Deadline { stream, deadline: started + Duration::from_millis(200) };
+
+        // A byte now and then doesn't keep the deadline away.
+        let mut buf = [0; 1];
+        let mut bytes = 0;
+        client.write_all(b"G").unwrap();
+        while reader.read(&mut buf).is_ok() {
+            bytes += 1;
+            std::thread::sleep(Duration::from_millis(20));
+            client.write_all(b"G").unwrap();
+        }
+        assert!(bytes > 1);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut out = Vec::new();
+        Response::new(404, "application/json", "{}".to_string()).write(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: 2\r\n"));
+        assert!(text.ends_with("\r\n\r\n{}"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Just enough JSON for the HTTP API: a value type, a parser and a writer.
+
+use std::fmt;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys stay in the order they were written.
+    Object(Vec<(String, Json)>)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pub offset: usize,
+    pub message: &'static str
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl Json {
+    /// An object from `(key, value)` pairs.
+    pub fn object<const N: usize>(pairs: [(&str, Json); N]) -> Json {
+        Json::Object(pairs.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    pub fn string(text: &str) -> Json {
+        Json::String(text.to_string())
+    }
+
+    /// The value under `key`, if this is an object that has one.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser { text, bytes: text.as_bytes(), offset: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.offset != parser.bytes.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(text) => write_string(f, text),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 { write!(f, ",")?; }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(pairs) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in pairs.iter().enumerate() {
+                    if index > 0 { write!(f, ",")?; }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?
+        }
+    }
+    write!(f, "\"")
+}
+
+/// How deeply arrays and objects may nest, so hostile input can't
+/// exhaust the stack.
+pub const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    text: &'a str,
+    /// `text`'s bytes, for looking ahead at ASCII.
+    bytes: &'a [u8],
+    offset: usize,
+    /// Arrays and objects open around the current value.
+    depth: usize
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> JsonError {
+        JsonError { offset: self.offset, message }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.offset) {
+            self.offset += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str, value: Json) -> Result<Json, JsonError> {
+        if self.bytes[self.offset..].starts_with(literal.as_bytes()) {
+            self.offset += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("unknown literal"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.bytes.get(self.offset) {
+            None => Err(self.error("unexpected end")),
+            Some(b'n') => self.expect("null", Json::Null),
+            Some(b't') => self.expect("true", Json::Bool(true)),
+            Some(b'f') => self.expect("false", Json::Bool(false)),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'[') => self.nested(Parser::array),
+            Some(b'{') => self.nested(Parser::object),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character"))
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json, JsonError>) -> Result<Json, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.offset;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.bytes.get(self.offset) {
+            self.offset += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.offset]).ok()
+            .and_then(|text| text.parse().ok())
+            .map(Json::Number)
+            .ok_or(JsonError { offset: start, message: "bad number" })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        // Skip the opening quote.
+        self.offset += 1;
+        let input = self.text;
+        let mut chars = input[self.offset..].chars();
+        let mut text = String::new();
+        loop {
+            let c = chars.next().ok_or_else(|| self.error("unterminated string"))?;
+            self.offset += c.len_utf8();
+            match c {
+                '"' => return Ok(text),
+                '\\' => {
+                    let escape = chars.next().ok_or_else(|| self.error("unterminated string"))?;
+                    self.offset += escape.len_utf8();
+                    match escape {
+                        '"' | '\\' | '/' => text.push(escape),
+                        'n' => text.push('\n'),
+                        'r' => text.push('\r'),
+                        't' => text.push('\t'),
+                        'b' => text.push('\u{8}'),
+                        'f' => text.push('\u{c}'),
+                        'u' => {
+                            let hex = input.get(self.offset..self.offset + 4).ok_or_else(|| self.error("bad escape"))?;
+                            let code = u32::from_str_radix(hex, 16).map_err(|_| self.error("bad escape"))?;
+                            // Surrogate pairs are not needed by anything we accept.
+                            text.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                            // The four digits are ASCII, one char each.
+                            chars.nth(3);
+                            self.offset += 4;
+                        },
+                        _ => return Err(self.error("bad escape"))
+                    }
+                },
+                c => text.push(c)
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.offset += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.offset) == Some(&b']') {
+            self.offset += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.bytes.get(self.offset) {
+                Some(b',') => { self.offset += 1; },
+                Some(b']') => {
+                    self.offset += 1;
+                    return Ok(Json::Array(items));
+                },
+                _ => return Err(self.error("expected , or ]"))
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.offset += 1;
+        let mut pairs = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.offset) == Some(&b'}') {
+            self.offset += 1;
+            return Ok(Json::Object(pairs));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.bytes.get(self.offset) != Some(&b'"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.bytes.get(self.offset) != Some(&b':') {
+                return Err(self.error("expected :"));
+            }
+            self.offset += 1;
+            pairs.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.bytes.get(self.offset) {
+                Some(b',') => { self.offset += 1; },
+                Some(b'}') => {
+                    self.offset += 1;
+                    return Ok(Json::Object(pairs));
+                },
+                _ => return Err(self.error("expected , or }"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Json, MAX_DEPTH};
+
+    #[test]
+    fn test_round_trip() {
+        let value = Json::object([
+            ("name", Json::string("a \"quoted\"\nline")),
+            ("moves", Json::Array(vec![Json::string("B2"), Json::Null])),
+            ("over", Json::Bool(false)),
+            ("id", Json::Number(42.0))
+        ]);
+        let text = value.to_string();
+        assert_eq!(text, r#"{"name":"a \"quoted\"\nline","moves":["B2",null],"over":false,"id":42}"#);
+        assert_eq!(Json::parse(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn test_parse() {
+        let value = Json::parse(" { \"x\" : \"human\", \"o\": \"caf\\u00e9 ☕\", \"n\": [-1.5e2, {}] } ").unwrap();
+        assert_eq!(value.get("x").and_then(Json::as_str), Some("human"));
+        assert_eq!(value.get("o").and_then(Json::as_str), Some("café ☕"));
+        assert_eq!(value.get("n"), Some(&Json::Array(vec![Json::Number(-150.0), Json::Object(vec![])])));
+        assert_eq!(value.get("missing"), None);
+
+        for bad in ["", "{", "[1,]", "{\"a\" 1}", "nul", "\"open", "{} x", "{1: 2}", "\"\\u00\"", "\"\\u00é\""] {
+            assert!(Json::parse(bad).is_err(), "{}", bad);
+        }
+
+        // Long strings are read in one pass.
+        let long = "é\\u0041".repeat(50_000);
+        let value = Json::parse(&format!("\"{}\"", long)).unwrap();
+        assert_eq!(value.as_str(), Some("éA".repeat(50_000).as_str()));
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        let error = Json::parse(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!((error.offset, error.message), (MAX_DEPTH, "nested too deeply"));
+        assert!(Json::parse(&"[{\"a\":".repeat(60_000)).is_err());
+    }
+}
@@ -0,0 +1,502 @@
+//! A computer player that learns by playing, the `learner` difficulty, and
+//! the `tic-tac-toe train` command that teaches it.
+//!
+//! It is tabular Q-learning.  For every position it has met, turned and
+//! mirrored into its canonical form (`Board::canonical`), it keeps a value
+//! for each empty square: its guess at what playing there is worth to the
+//! side to move, from 1 for a win to -1 for a loss.  It plays the square
+//! with the highest value and picks at random among equals, so a new table
+//! plays like `random`.  After a game each of its moves is moved a step
+//! towards the result, if the move ended the game, or towards the best
+//! value it could see at its next turn:
+//!
+//! ```text
+//! Q(s, a) += rate × (result or discount × max Q(s', a') − Q(s, a))
+//! ```
+//!
+//! `train` plays it against itself or built-in opponents, sometimes
+//! exploring a random square, and prints a learning curve: its results
+//! over each tenth of the games.  Games against it through the console
+//! teach it too.  The table is plain text:
+//!
+//! ```text
+//! tic-tac-toe-learner 1
+//! games 5000
+//! ---/---/--- A1 0.0712 A2 -0.1250 B2 0.1933
+//! ---/-X-/--- A1 -0.0400 A2 -0.3100
+//! ```
+//!
+//! `games` counts the games it has learned from.  Every other line is a
+//! canonical board, as in the network protocol, and the values of the
+//! squares it has tried there; squares missing from a line are worth 0.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::board::{parse_position, position_name, transform_position, Board};
+use crate::game::{self, Difficulty, Player, PlayerSet, PlayerType};
+use crate::search::{Outcome, Position};
+use crate::net::{decode_board, encode_board};
+use crate::profile;
+use crate::rng::Rng;
+
+const FILE_HEADER: &str = "tic-tac-toe-learner 1";
+
+/// Rows in a learning curve.
+const CURVE_POINTS: u64 = 10;
+
+/// How fast values move and how much the learner explores.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Settings {
+    /// The step towards each new estimate, from 0 to 1.
+    pub rate: f64,
+    /// What a value seen a turn later is worth now, so quick wins beat
+    /// slow ones.
+    pub discount: f64,
+    /// The chance of a random square instead of the best one in training.
+    pub exploration: f64
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { rate: 0.3, discount: 0.95, exploration: 0.1 }
+    }
+}
+
+/// Square values for each canonical board, in the canonical orientation.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct QTable {
+    values: BTreeMap<String, [f64; 9]>,
+    /// Games it has learned from.
+    pub games: u64
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TableError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl QTable {
+    /// Positions with at least one value.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The value of playing `pos` on `board`.
+    pub fn value(&self, board: &Board, pos: (usize, usize)) -> f64 {
+        let (canonical, symmetry) = board.canonical();
+        let (x, y) = transform_position(pos, symmetry);
+        self.values.get(&encode_board(&canonical)).map_or(0.0, |values| values[x * 3 + y])
+    }
+
+    /// Each legal move on `board` and its value.
+    pub fn values(&self, board: &Board) -> Vec<((usize, usize), f64)> {
+        board.moves().into_iter().map(|pos| (pos, self.value(board, pos))).collect()
+    }
+
+    /// The highest valued move, at random among equals, or `None` once the
+    /// game is over.
+    pub fn best_move(&self, board: &Board, rng: &mut Rng) -> Option<(usize, usize)> {
+        let values = self.values(board);
+        let best = values.iter().map(|&(_, value)| value).fold(f64::NEG_INFINITY, f64::max);
+        let ties: Vec<(usize, usize)> = values.into_iter().filter(|&(_, value)| value == best).map(|(pos, _)| pos).collect();
+        (!ties.is_empty()).then(|| ties[rng.below(ties.len())])
+    }
+
+    fn best_value(&self, board: &Board) -> f64 {
+        self.values(board).into_iter().map(|(_, value)| value).fold(f64::NEG_INFINITY, f64::max).max(-1.0)
+    }
+
+    fn update(&mut self, board: &Board, pos: (usize, usize), target: f64, rate: f64) {
+        let (canonical, symmetry) = board.canonical();
+        let (x, y) = transform_position(pos, symmetry);
+        let value = &mut self.values.entry(encode_board(&canonical)).or_insert([0.0; 9])[x * 3 + y];
+        *value += rate * (target - *value);
+    }
+
+    /// Learns from a finished game, given as its moves from the empty
+    /// board, for each side that `learners` marks, X first.
+    pub fn learn(&mut self, moves: &[(usize, usize)], learners: [bool; 2], settings: &Settings) {
+        let mut board = Board::default();
+        let mut turns: Vec<(Board, (usize, usize))> = Vec::new();
+        for &pos in moves {
+            turns.push((board, pos));
+            board = board.play(pos);
+        }
+        let Some(outcome) = board.outcome() else { return; };
+
+        for (index, &(before, pos)) in turns.iter().enumerate() {
+            let side = index % 2;
+            if !learners[side] {
+                continue;
+            }
+            let target = match turns.get(index + 2) {
+                Some((next, _)) => settings.discount * self.best_value(next),
+                None => match outcome {
+                    Outcome::Win(winner) if winner == side => 1.0,
+                    Outcome::Win(_) => -1.0,
+                    Outcome::Draw => 0.0
+                }
+            };
+            self.update(&before, pos, target, settings.rate);
+        }
+        self.games += 1;
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.serialize())
+    }
+
+    /// The table at `path`, or an empty one if there is no file yet.
+    pub fn load(path: &Path) -> io::Result<QTable> {
+        match fs::read_to_string(path) {
+            Ok(text) => QTable::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(QTable::default()),
+            Err(error) => Err(error)
+        }
+    }
+
+    /// The file described in the module docs.
+    pub fn serialize(&self) -> String {
+        let mut out = format!("{}\ngames {}\n", FILE_HEADER, self.games);
+        for (board, values) in &self.values {
+            out.push_str(board);
+            for (index, value) in values.iter().enumerate() {
+                if *value != 0.0 {
+                    let _ = write!(out, " {} {:.4}", position_name((index / 3, index % 3)), value);
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> Result<QTable, TableError> {
+        let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line.trim())).filter(|(_, line)| !line.is_empty());
+        let error = |line: usize, message: String| TableError { line, message };
+
+        if lines.next().map(|(_, line)| line) != Some(FILE_HEADER) {
+            return Err(error(1, "unknown file format or version".to_string()));
+        }
+        let mut table = QTable::default();
+        let (number, line) = lines.next().ok_or_else(|| error(2, "expected a games line".to_string()))?;
+        table.games = line.strip_prefix("games ").and_then(|games| games.trim().parse().ok())
+            .ok_or_else(|| error(number, "expected a games line".to_string()))?;
+
+        for (number, line) in lines {
+            let mut words = line.split_whitespace();
+            let board = words.next().map(decode_board).and_then(Result::ok)
+                .filter(|board| board.canonical().0 == *board)
+                .ok_or_else(|| error(number, "expected a canonical board".to_string()))?;
+            let mut values = [0.0; 9];
+            while let Some(name) = words.next() {
+                let (x, y) = parse_position(name)
+                    .filter(|&pos| board.moves().contains(&pos))
+                    .ok_or_else(|| error(number, format!("'{}' is not a move on this board", name)))?;
+                values[x * 3 + y] = words.next().and_then(|value| value.parse().ok()).filter(|value: &f64| value.is_finite())
+                    .ok_or_else(|| error(number, format!("{} needs a value", name)))?;
+            }
+            if table.values.insert(encode_board(&board), values).is_some() {
+                return Err(error(number, "the board is listed twice".to_string()));
+            }
+        }
+        Ok(table)
+    }
+}
+
+/// Who the learner trains against.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Opponent {
+    /// Itself, learning with both colours.
+    Itself,
+    Builtin(Difficulty)
+}
+
+impl Opponent {
+    pub fn label(self) -> &'static str {
+        match self {
+            Opponent::Itself => "self",
+            Opponent::Builtin(difficulty) => difficulty.label()
+        }
+    }
+
+    /// `self` or any difficulty but `learner`, which is what `self` means.
+    pub fn from_label(label: &str) -> Option<Opponent> {
+        match label {
+            "self" => Some(Opponent::Itself),
+            _ => Difficulty::from_label(label).filter(|&difficulty| difficulty != Difficulty::Learner).map(Opponent::Builtin)
+        }
+    }
+}
+
+/// Results over one stretch of training, from the learner's side.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct CurvePoint {
+    /// Games trained so far, this stretch included.
+    pub games: u64,
+    pub wins: u64,
+    pub draws: u64,
+    pub losses: u64,
+    /// The size of the table at the end of the stretch.
+    pub states: usize
+}
+
+impl CurvePoint {
+    fn rate(&self, count: u64) -> f64 {
+        let played = self.wins + self.draws + self.losses;
+        if played == 0 { 0.0 } else { 100.0 * count as f64 / played as f64 }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Curve {
+    pub opponents: Vec<Opponent>,
+    pub points: Vec<CurvePoint>
+}
+
+impl Curve {
+    pub fn format_table(&self) -> String {
+        let labels: Vec<&str> = self.opponents.iter().map(|opponent| opponent.label()).collect();
+        let games = self.points.last().map_or(0, |point| point.games);
+        let mut out = format!("learner vs {}, {} games\n", labels.join(", "), games);
+        let _ = writeln!(out, "   {:>8} {:>7} {:>7} {:>7} {:>7}", "games", "wins", "draws", "losses", "states");
+        for point in &self.points {
+            let _ = writeln!(
+                out, "   {:>8} {:>6.1}% {:>6.1}% {:>6.1}% {:>7}",
+                point.games, point.rate(point.wins), point.rate(point.draws), point.rate(point.losses), point.states);
+        }
+        out
+    }
+
+    /// A header line and a row per point, with rates as fractions.
+    pub fn format_csv(&self) -> String {
+        let mut out = "games,wins,draws,losses,win_rate,draw_rate,loss_rate,states\n".to_string();
+        for point in &self.points {
+            let _ = writeln!(
+                out, "{},{},{},{},{:.4},{:.4},{:.4},{}",
+                point.games, point.wins, point.draws, point.losses,
+                point.rate(point.wins) / 100.0, point.rate(point.draws) / 100.0, point.rate(point.losses) / 100.0, point.states);
+        }
+        out
+    }
+}
+
+/// Plays `games` training games, taking turns through `opponents` and
+/// changing colour every game.
+pub fn train(table: &mut QTable, opponents: &[Opponent], games: u64, settings: &Settings, rng: &mut Rng) -> Curve {
+    let stretch = games.div_ceil(CURVE_POINTS).max(1);
+    let mut points = Vec::new();
+    let mut point = CurvePoint::default();
+
+    for number in 0..games {
+        let side = (number % 2) as usize;
+        let opponent = opponents[(number / 2) as usize % opponents.len()];
+        let learners = [side == 0 || opponent == Opponent::Itself, side == 1 || opponent == Opponent::Itself];
+
+        let mut board = Board::default();
+        let mut moves = Vec::new();
+        while board.outcome().is_none() {
+            let pos = match opponent {
+                Opponent::Builtin(difficulty) if Position::side_to_move(&board) != side => builtin_move(difficulty, &board),
+                _ if rng.below(1000) < (settings.exploration * 1000.0) as usize => {
+                    let moves = board.moves();
+                    moves[rng.below(moves.len())]
+                },
+                _ => table.best_move(&board, rng).expect("the game is not over")
+            };
+            moves.push(pos);
+            board = board.play(pos);
+        }
+        table.learn(&moves, learners, settings);
+
+        match board.outcome() {
+            Some(Outcome::Win(winner)) if winner == side => { point.wins += 1; },
+            Some(Outcome::Win(_)) => { point.losses += 1; },
+            _ => { point.draws += 1; }
+        }
+        if (number + 1) % stretch == 0 || number + 1 == games {
+            point.games = number + 1;
+            point.states = table.len();
+            points.push(point);
+            point = CurvePoint::default();
+        }
+    }
+    Curve { opponents: opponents.to_vec(), points }
+}
+
+fn builtin_move(difficulty: Difficulty, board: &Board) -> (usize, usize) {
+    let computer = PlayerType::Computer(difficulty);
+    let set = PlayerSet { x: Player::X(computer), o: Player::O(computer) };
+    let turn = if Position::side_to_move(board) == 0 { set.x } else { set.o };
+    game::computer_move(&turn, &set, board).ok()
+        .and_then(|after| after.board().and_then(|after| board.changed_position(after)))
+        .expect("the game is not over")
+}
+
+/// `learner.txt` next to the profiles.
+pub fn default_path() -> PathBuf {
+    profile::default_path().with_file_name("learner.txt")
+}
+
+struct Shared {
+    path: Option<PathBuf>,
+    /// Loaded on first use; an error if the file was there but unreadable.
+    table: Option<Result<QTable, String>>
+}
+
+/// The table behind the `learner` difficulty, shared by every game.
+static SHARED: Mutex<Shared> = Mutex::new(Shared { path: None, table: None });
+
+fn with_shared<R>(f: impl FnOnce(&Path, &mut Result<QTable, String>) -> R) -> R {
+    let mut shared = SHARED.lock().unwrap_or_else(|p| p.into_inner());
+    let path = shared.path.clone().unwrap_or_else(default_path);
+    let table = shared.table.get_or_insert_with(|| QTable::load(&path).map_err(|e| e.to_string()));
+    f(&path, table)
+}
+
+/// Makes the `learner` difficulty play from the table at `path` instead of
+/// `default_path()`.
+pub fn use_table(path: PathBuf) {
+    let mut shared = SHARED.lock().unwrap_or_else(|p| p.into_inner());
+    *shared = Shared { path: Some(path), table: None };
+}
+
+/// The `learner` difficulty's move.  An unreadable table plays like a new
+/// one.
+pub fn learner_move(board: &Board) -> Option<(usize, usize)> {
+    with_shared(|_, table| {
+        let empty = QTable::default();
+        table.as_ref().unwrap_or(&empty).best_move(board, &mut Rng::from_entropy())
+    })
+}
+
+/// Teaches the shared table a finished game played by `set` and saves it.
+/// Does nothing unless one side is the `learner`.
+pub fn learn_from_game(set: &PlayerSet, moves: &[(usize, usize)]) -> io::Result<()> {
+    let learners = [set.x, set.o].map(|player| *player.player_type() == PlayerType::Computer(Difficulty::Learner));
+    if learners == [false, false] {
+        return Ok(());
+    }
+    with_shared(|path, table| {
+        let table = table.as_mut().map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message.clone()))?;
+        table.learn(moves, learners, &Settings::default());
+        table.save(path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::board::{parse_position, Board};
+    use crate::game::Difficulty;
+    use crate::search::{Outcome, Position};
+    use crate::rng::Rng;
+
+    use super::{train, Opponent, QTable, Settings};
+
+    fn squares(names: &[&str]) -> Vec<(usize, usize)> {
+        names.iter().map(|name| parse_position(name).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_learn_from_one_game() {
+        let mut table = QTable::default();
+        let settings = Settings { rate: 0.5, ..Settings::default() };
+        // X wins on the top row; only X learns.
+        table.learn(&squares(&["A1", "B1", "A2", "B2", "A3"]), [true, false], &settings);
+        assert_eq!(table.games, 1);
+        let before_win = Board::from_str("XX-\nOO-\n---").unwrap();
+        assert_eq!(table.value(&before_win, parse_position("A3").unwrap()), 0.5);
+        // The same position mirrored shares the value.
+        let mirrored = Board::from_str("-XX\n-OO\n---").unwrap();
+        assert_eq!(table.value(&mirrored, parse_position("A1").unwrap()), 0.5);
+        assert_eq!(table.best_move(&mirrored, &mut Rng::new(1)), parse_position("A1"));
+        assert_eq!(table.len(), 3);
+
+        // An unfinished game teaches nothing.
+        table.learn(&squares(&["B2"]), [true, true], &settings);
+        assert_eq!((table.games, table.len()), (1, 3));
+    }
+
+    #[test]
+    fn test_save_and_parse() {
+        let mut table = QTable::default();
+        table.learn(&squares(&["A1", "B1", "A2", "B2", "A3"]), [true, true], &Settings::default());
+        let text = table.serialize();
+        assert!(text.starts_with("tic-tac-toe-learner 1\ngames 1\n"));
+        let parsed = QTable::parse(&text).unwrap();
+        assert_eq!(parsed.serialize(), text);
+
+        assert_eq!(QTable::parse("tic-tac-toe-learner 2\ngames 1\n").unwrap_err().line, 1);
+        assert_eq!(QTable::parse("tic-tac-toe-learner 1\ngames 1\n--X/---/--- A1 0.5\n").unwrap_err().line, 3);
+        assert!(QTable::parse("tic-tac-toe-learner 1\ngames 1\nX--/---/--- A1 0.5\n").is_err());
+        assert!(QTable::parse("tic-tac-toe-learner 1\ngames 1\n---/---/--- A1\n").is_err());
+    }
+
+    /// Games out of 200 that `table`, played greedily, loses to random
+    /// moves.
+    fn losses_to_random(table: &QTable, rng: &mut Rng) -> usize {
+        let mut losses = 0;
+        for game in 0..200 {
+            let side = game % 2;
+            let mut board = Board::default();
+            while board.outcome().is_none() {
+                let pos = if Position::side_to_move(&board) == side {
+                    table.best_move(&board, rng).unwrap()
+                } else {
+                    let moves = board.moves();
+                    moves[rng.below(moves.len())]
+                };
+                board = board.play(pos);
+            }
+            losses += usize::from(board.outcome() == Some(Outcome::Win(1 - side)));
+        }
+        losses
+    }
+
+    #[test]
+    fn test_training_beats_random() {
+        let mut table = QTable::default();
+        let mut rng = Rng::new(4);
+        let untrained = losses_to_random(&table, &mut rng);
+        let curve = train(&mut table, &[Opponent::Builtin(Difficulty::Random), Opponent::Itself], 6000, &Settings::default(), &mut rng);
+        assert_eq!(curve.points.len(), 10);
+        assert_eq!(curve.points.last().unwrap().games, 6000);
+        assert_eq!(table.games, 6000);
+        assert!(curve.format_csv().starts_with("games,wins,draws,losses,win_rate,draw_rate,loss_rate,states\n600,"));
+        let (first, last) = (curve.points[0], curve.points[9]);
+        assert!(last.losses < first.losses, "{:?} then {:?}", first, last);
+
+        // Played greedily it should now hardly ever lose to random moves.
+        let trained = losses_to_random(&table, &mut rng);
+        assert!(trained * 10 <= untrained, "{} losses untrained, {} trained", untrained, trained);
+        assert!(trained <= 5, "{} losses in 200 games", trained);
+    }
+
+    #[test]
+    fn test_opponent_labels() {
+        assert_eq!(Opponent::from_label("self"), Some(Opponent::Itself));
+        assert_eq!(Opponent::from_label("perfect"), Some(Opponent::Builtin(Difficulty::Perfect)));
+        assert_eq!(Opponent::from_label("learner"), None);
+        assert_eq!(Opponent::from_label("engine"), None);
+    }
+}
@@ -0,0 +1,33 @@
+pub mod api;
+pub mod board;
+pub mod cli;
+pub mod coach;
+pub mod console;
+pub mod engine;
+pub mod ffi;
+pub mod game;
+pub mod http;
+pub mod json;
+pub mod learn;
+pub mod lobby;
+pub mod mcts;
+pub mod mnk;
+pub mod net;
+pub mod nim;
+pub mod profile;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod render;
+pub mod retrograde;
+pub mod rng;
+pub mod rules;
+pub mod save;
+pub mod search;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod session;
+pub mod simulate;
+pub mod solve;
+pub mod tournament;
+pub mod tui;
+pub mod web;
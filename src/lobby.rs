@@ -0,0 +1,555 @@
+//! A shared server where many people play, watch and chat at once.
+//!
+//! Where `net` hosts one game between two seats, the lobby keeps any
+//! number of tables.  Each table runs a `Game`, and every move, human or
+//! bot, goes through [`game::make_move`] or [`game::computer_move`] on
+//! the server.
+//!
+//! # Protocol
+//!
+//! Plain text lines, easy to type into `nc localhost 7879`.  On connecting
+//! the server sends `LOBBY tic-tac-toe <version>`, with the version
+//! [`LOBBY_VERSION`].  Squares, boards and game states are written as in
+//! the `net` protocol.  A line over 1024 bytes closes the connection.
+//!
+//! Client to server:
+//!
+//! | Command | Meaning |
+//! |---|---|
+//! | `NAME <name>` | Required first.  Names are 1 to 20 letters, digits, `_` or `-`, and unique while connected. |
+//! | `LIST` | One `GAME` line per open or running table, then `END`. |
+//! | `CREATE` | Open a table as X and wait for someone to `JOIN`. |
+//! | `JOIN <id>` | Take O at an open table. |
+//! | `PLAY` | Matchmaking: join the oldest open table, or open one. |
+//! | `PLAY bot [<difficulty>]` | Play X against a bot at once, `perfect` unless given. |
+//! | `WATCH <id>` | Spectate a table. |
+//! | `MOVE <square>` | Move at your table. |
+//! | `SAY <text>` | Chat to everyone at your table. |
+//! | `LEAVE` | Leave your table.  Leaving a running game abandons it. |
+//! | `QUIT` | Disconnect. |
+//!
+//! Joining, watching or playing somewhere else leaves your current table
+//! first.
+//!
+//! Server to client:
+//!
+//! | Message | Meaning |
+//! |---|---|
+//! | `WELCOME <name>` | `NAME` accepted. |
+//! | `GAME <id> <open\|playing> <x> <o> <spectators>` | A `LIST` entry.  Seats show a name, `-` when empty or `bot:<difficulty>`. |
+//! | `END` | End of a `LIST`. |
+//! | `WAITING <id>` | You opened table `<id>` and wait for an opponent. |
+//! | `START <id> <X\|O> <opponent>` | Your game at table `<id>` has begun. |
+//! | `WATCHING <id> <x> <o>` | You are spectating table `<id>`. |
+//! | `STATE <id> <state>` | The game at table `<id>` after a move, as in `net`: `TURN X <board>`, `WIN O <board>` or `TIE <board>`. |
+//! | `CHAT <id> <name> <text>` | Chat at table `<id>`. |
+//! | `OVER <id> abandoned <name>` | `<name>` left the running game at table `<id>`. |
+//! | `OK` | A `LEAVE` went through. |
+//! | `ERROR <message>` | The last command was refused. |
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::board::{parse_position, Board, Square};
+use crate::game::{self, Difficulty, Game, Player, PlayerSet, PlayerType};
+use crate::net::encode_game;
+
+pub const LOBBY_VERSION: u32 = 1;
+pub const DEFAULT_LOBBY_PORT: u16 = 7879;
+
+/// Longer command lines close the connection.
+const MAX_LINE: usize = 1024;
+/// How long a write to one user may block the lobby.  A user who stops
+/// reading for longer is sent nothing more.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type UserId = u64;
+type TableId = u64;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Seat {
+    Open,
+    User(UserId),
+    Bot(Difficulty)
+}
+
+struct User {
+    name: Option<String>,
+    out: Box<dyn Write + Send>,
+    table: Option<TableId>
+}
+
+struct Table {
+    x: Seat,
+    o: Seat,
+    game: Game,
+    spectators: Vec<UserId>,
+    abandoned: bool
+}
+
+impl Table {
+    fn new(x: Seat, o: Seat) -> Table {
+        let set = PlayerSet { x: Player::X(player_type(x)), o: Player::O(player_type(o)) };
+        Table { x, o, game: Game::InPlay { set, turn: set.x, board: Board::default() }, spectators: Vec::new(), abandoned: false }
+    }
+
+    fn is_running(&self) -> bool {
+        self.o != Seat::Open && !self.abandoned && matches!(self.game, Game::InPlay { .. })
+    }
+
+    fn seat_of(&self, user: UserId) -> Option<Square> {
+        if self.x == Seat::User(user) {
+            Some(Square::X)
+        } else if self.o == Seat::User(user) {
+            Some(Square::O)
+        } else {
+            None
+        }
+    }
+}
+
+fn player_type(seat: Seat) -> PlayerType {
+    match seat {
+        Seat::Bot(difficulty) => PlayerType::Computer(difficulty),
+        Seat::Open | Seat::User(_) => PlayerType::Human
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    (1..=20).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Every connected user and every table.  Connections call `connect`,
+/// `handle` and `disconnect`; replies and events go to each user's writer.
+#[derive(Default)]
+pub struct Lobby {
+    users: BTreeMap<UserId, User>,
+    tables: BTreeMap<TableId, Table>,
+    next_user: UserId,
+    next_table: TableId
+}
+
+impl Lobby {
+    pub fn connect(&mut self, out: Box<dyn Write + Send>) -> UserId {
+        self.next_user += 1;
+        let id = self.next_user;
+        self.users.insert(id, User { name: None, out, table: None });
+        self.send(id, &format!("LOBBY tic-tac-toe {}", LOBBY_VERSION));
+        id
+    }
+
+    pub fn disconnect(&mut self, user: UserId) {
+        self.leave(user);
+        self.users.remove(&user);
+    }
+
+    /// Carries out one command line.  Returns `false` once the user quits.
+    pub fn handle(&mut self, user: UserId, line: &str) -> bool {
+        let line = line.trim();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let named = self.users.get(&user).is_some_and(|u| u.name.is_some());
+
+        let result = match words.as_slice() {
+            ["QUIT"] => return false,
+            ["NAME", name] => self.name(user, name),
+            _ if !named => Err("say NAME <name> first".to_string()),
+            ["LIST"] => {
+                self.list(user);
+                Ok(())
+            },
+            ["CREATE"] => {
+                self.create(user);
+                Ok(())
+            },
+            ["JOIN", table] => table.parse().map_err(|_| format!("no table {}", table)).and_then(|table| self.join(user, table)),
+            ["PLAY"] => {
+                self.matchmake(user);
+                Ok(())
+            },
+            ["PLAY", "bot"] => {
+                self.play_bot(user, Difficulty::Perfect);
+                Ok(())
+            },
+            ["PLAY", "bot", label] => match Difficulty::from_label(label) {
+                Some(difficulty) => {
+                    self.play_bot(user, difficulty);
+                    Ok(())
+                },
+                None => Err(format!("unknown difficulty '{}'", label))
+            },
+            ["WATCH", table] => table.parse().map_err(|_| format!("no table {}", table)).and_then(|table| self.watch(user, table)),
+            ["MOVE", square] => match parse_position(square) {
+                Some(pos) => self.play_move(user, pos),
+                None => Err(format!("'{}' is not a square", square))
+            },
+            ["SAY", ..] => self.say(user, line["SAY".len()..].trim()),
+            ["LEAVE"] => {
+                self.leave(user);
+                self.send(user, "OK");
+                Ok(())
+            },
+            [] => Ok(()),
+            _ => Err(format!("unknown command '{}'", line))
+        };
+
+        if let Err(message) = result {
+            self.send(user, &format!("ERROR {}", message));
+        }
+        true
+    }
+
+    fn send(&mut self, user: UserId, line: &str) {
+        if let Some(user) = self.users.get_mut(&user) {
+            // A dead or stalled connection is cleaned up by its own reader
+            // thread; until then it gets nothing, so it can't hold up
+            // everyone else again.
+            if writeln!(user.out, "{}", line).and_then(|_| user.out.flush()).is_err() {
+                user.out = Box::new(io::sink());
+            }
+        }
+    }
+
+    /// Sends `line` to both players and every spectator at `table`.
+    fn broadcast(&mut self, table: TableId, line: &str) {
+        let audience: Vec<UserId> = self.users.iter()
+            .filter(|(_, user)| user.table == Some(table))
+            .map(|(id, _)| *id)
+            .collect();
+        for user in audience {
+            self.send(user, line);
+        }
+    }
+
+    fn name_of(&self, user: UserId) -> String {
+        self.users.get(&user).and_then(|u| u.name.clone()).unwrap_or_default()
+    }
+
+    fn seat_label(&self, seat: Seat) -> String {
+        match seat {
+            Seat::Open => "-".to_string(),
+            Seat::User(user) => self.name_of(user),
+            Seat::Bot(difficulty) => format!("bot:{}", difficulty.label())
+        }
+    }
+
+    fn name(&mut self, user: UserId, name: &str) -> Result<(), String> {
+        if !valid_name(name) {
+            return Err("names are 1 to 20 letters, digits, _ or -".to_string());
+        }
+        let taken = self.users.iter().any(|(id, u)| *id != user && u.name.as_deref() == Some(name));
+        if taken {
+            return Err(format!("{} is taken", name));
+        }
+        if let Some(u) = self.users.get_mut(&user) {
+            u.name = Some(name.to_string());
+        }
+        self.send(user, &format!("WELCOME {}", name));
+        Ok(())
+    }
+
+    fn list(&mut self, user: UserId) {
+        let lines: Vec<String> = self.tables.iter()
+            .filter(|(_, table)| table.o == Seat::Open || table.is_running())
+            .map(|(id, table)| {
+                let status = if table.o == Seat::Open { "open" } else { "playing" };
+                format!("GAME {} {} {} {} {}", id, status, self.seat_label(table.x), self.seat_label(table.o), table.spectators.len())
+            })
+            .collect();
+        for line in lines {
+            self.send(user, &line);
+        }
+        self.send(user, "END");
+    }
+
+    fn open_table(&mut self, x: Seat, o: Seat) -> TableId {
+        self.next_table += 1;
+        let id = self.next_table;
+        self.tables.insert(id, Table::new(x, o));
+        id
+    }
+
+    fn sit(&mut self, user: UserId, table: TableId) {
+        if let Some(u) = self.users.get_mut(&user) {
+            u.table = Some(table);
+        }
+    }
+
+    fn create(&mut self, user: UserId) {
+        self.leave(user);
+        let table = self.open_table(Seat::User(user), Seat::Open);
+        self.sit(user, table);
+        self.send(user, &format!("WAITING {}", table));
+    }
+
+    fn join(&mut self, user: UserId, id: TableId) -> Result<(), String> {
+        match self.tables.get(&id) {
+            Some(table) if table.o == Seat::Open && table.x != Seat::User(user) => { },
+            Some(_) => return Err(format!("table {} is not open", id)),
+            None => return Err(format!("no table {}", id))
+        }
+        self.leave(user);
+        if let Some(table) = self.tables.get_mut(&id) {
+            table.o = Seat::User(user);
+        }
+        self.sit(user, id);
+        self.start(id);
+        Ok(())
+    }
+
+    fn matchmake(&mut self, user: UserId) {
+        let waiting = self.tables.iter()
+            .find(|(_, table)| table.o == Seat::Open && table.x != Seat::User(user))
+            .map(|(id, _)| *id);
+        match waiting {
+            Some(table) => { let _ = self.join(user, table); },
+            None => self.create(user)
+        }
+    }
+
+    fn play_bot(&mut self, user: UserId, difficulty: Difficulty) {
+        self.leave(user);
+        let table = self.open_table(Seat::User(user), Seat::Bot(difficulty));
+        self.sit(user, table);
+        self.start(table);
+    }
+
+    /// Tells both players their game has begun, and everyone the board.
+    fn start(&mut self, id: TableId) {
+        let Some(table) = self.tables.get(&id) else { return; };
+        let (x, o) = (table.x, table.o);
+        let (x_label, o_label) = (self.seat_label(x), self.seat_label(o));
+        if let Seat::User(user) = x {
+            self.send(user, &format!("START {} X {}", id, o_label));
+        }
+        if let Seat::User(user) = o {
+            self.send(user, &format!("START {} O {}", id, x_label));
+        }
+        self.announce(id);
+    }
+
+    fn announce(&mut self, id: TableId) {
+        if let Some(table) = self.tables.get(&id) {
+            let line = format!("STATE {} {}", id, encode_game(&table.game));
+            self.broadcast(id, &line);
+        }
+    }
+
+    fn watch(&mut self, user: UserId, id: TableId) -> Result<(), String> {
+        let Some(table) = self.tables.get(&id) else { return Err(format!("no table {}", id)); };
+        let line = format!("WATCHING {} {} {}", id, self.seat_label(table.x), self.seat_label(table.o));
+        let state = format!("STATE {} {}", id, encode_game(&table.game));
+
+        self.leave(user);
+        if let Some(table) = self.tables.get_mut(&id) {
+            table.spectators.push(user);
+        }
+        self.sit(user, id);
+        self.send(user, &line);
+        self.send(user, &state);
+        Ok(())
+    }
+
+    fn play_move(&mut self, user: UserId, pos: (usize, usize)) -> Result<(), String> {
+        let id = self.users.get(&user).and_then(|u| u.table).ok_or("you are not at a table")?;
+        let table = self.tables.get_mut(&id).ok_or("you are not at a table")?;
+        let seat = table.seat_of(user).ok_or("spectators can't move")?;
+        if table.o == Seat::Open {
+            return Err("waiting for an opponent".to_string());
+        }
+        if !table.is_running() {
+            return Err("the game is over".to_string());
+        }
+        let Game::InPlay { set, turn, board } = table.game else { unreachable!("checked by is_running") };
+        if turn.associated_square() != seat {
+            return Err("it is not your turn".to_string());
+        }
+
+        table.game = game::make_move(&pos, &board, &turn, &set).map_err(|_| "that square is taken".to_string())?;
+        self.announce(id);
+        Ok(())
+    }
+
+    /// The game at `user`'s table, if a bot is to move in it.  The move is
+    /// worked out without the lobby and handed back to `bot_moved`.
+    fn bot_turn(&self, user: UserId) -> Option<(TableId, Game)> {
+        let id = self.users.get(&user)?.table?;
+        let table = self.tables.get(&id)?;
+        match table.game {
+            Game::InPlay { turn, .. } if table.is_running() && matches!(turn.player_type(), PlayerType::Computer(_)) => Some((id, table.game)),
+            _ => None
+        }
+    }
+
+    /// Plays a bot's move from `before`, unless the table has gone or its
+    /// game has changed since.
+    fn bot_moved(&mut self, id: TableId, before: Game, updated: Game) {
+        let Some(table) = self.tables.get_mut(&id) else { return; };
+        if table.game == before && table.is_running() {
+            table.game = updated;
+            self.announce(id);
+        }
+    }
+
+    fn say(&mut self, user: UserId, text: &str) -> Result<(), String> {
+        let id = self.users.get(&user).and_then(|u| u.table).ok_or("you are not at a table")?;
+        if text.is_empty() {
+            return Err("say what?".to_string());
+        }
+        let line = format!("CHAT {} {} {}", id, self.name_of(user), text);
+        self.broadcast(id, &line);
+        Ok(())
+    }
+
+    /// Takes `user` away from their table, abandoning a running game they
+    /// play in.  Closes the table if nobody is left at it, or if they were
+    /// waiting at it for an opponent.
+    fn leave(&mut self, user: UserId) {
+        let Some(id) = self.users.get_mut(&user).and_then(|u| u.table.take()) else { return; };
+        let name = self.name_of(user);
+        if let Some(table) = self.tables.get_mut(&id) {
+            table.spectators.retain(|spectator| *spectator != user);
+            if table.seat_of(user).is_some() && table.is_running() {
+                table.abandoned = true;
+                self.broadcast(id, &format!("OVER {} abandoned {}", id, name));
+            }
+        }
+
+        let occupied = self.users.values().any(|u| u.table == Some(id));
+        let waiting = self.tables.get(&id).is_some_and(|table| table.o == Seat::Open && table.x == Seat::User(user));
+        if !occupied || waiting {
+            self.tables.remove(&id);
+            // Spectators of a closed table are at no table.
+            for u in self.users.values_mut().filter(|u| u.table == Some(id)) {
+                u.table = None;
+            }
+        }
+    }
+}
+
+/// Runs the lobby on `listener` until it fails, one thread per connection.
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let lobby = Arc::new(Mutex::new(Lobby::default()));
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let lobby = Arc::clone(&lobby);
+        thread::spawn(move || {
+            // A dropped connection only removes its user.
+            let _ = serve_connection(stream, &lobby);
+        });
+    }
+    Ok(())
+}
+
+fn lock(lobby: &Mutex<Lobby>) -> std::sync::MutexGuard<'_, Lobby> {
+    lobby.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Carries out `user`'s command, then any bot move it leads to.  The bot
+/// thinks without the lobby locked, so other tables carry on meanwhile.
+/// Returns `false` once the user quits.
+fn run_command(lobby: &Mutex<Lobby>, user: UserId, line: &str) -> bool {
+    let (more, bot_turn) = {
+        let mut lobby = lock(lobby);
+        let more = lobby.handle(user, line);
+        (more, lobby.bot_turn(user))
+    };
+    if let Some((id, before @ Game::InPlay { set, turn, board })) = bot_turn {
+        if let Ok(updated) = game::computer_move(&turn, &set, &board) {
+            lock(lobby).bot_moved(id, before, updated);
+        }
+    }
+    more
+}
+
+fn serve_connection(stream: TcpStream, lobby: &Mutex<Lobby>) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let writer = stream.try_clone()?;
+    writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let user = lock(lobby).connect(Box::new(writer));
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let result = loop {
+        line.clear();
+        match reader.by_ref().take(MAX_LINE as u64 + 1).read_line(&mut line) {
+            Ok(0) => break Ok(()),
+            Ok(read) if read > MAX_LINE => break Err(io::Error::new(io::ErrorKind::InvalidData, "line too long")),
+            Ok(_) if !run_command(lobby, user, &line) => break Ok(()),
+            Ok(_) => { },
+            Err(error) => break Err(error)
+        }
+    };
+    lock(lobby).disconnect(user);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    use super::{run_command, valid_name, Lobby};
+
+    /// A writer whose output the test can read back.
+    #[derive(Clone, Default)]
+    struct Outbox(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Outbox {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Outbox {
+        fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        assert!(valid_name("ann_1-b"));
+        assert!(!valid_name(""));
+        assert!(!valid_name("two words"));
+        assert!(!valid_name("bot:easy"));
+
+        let mut lobby = Lobby::default();
+        let (ann, bob) = (Outbox::default(), Outbox::default());
+        let a = lobby.connect(Box::new(ann.clone()));
+        let b = lobby.connect(Box::new(bob.clone()));
+        lobby.handle(a, "LIST");
+        lobby.handle(a, "NAME ann");
+        lobby.handle(b, "NAME ann");
+        assert_eq!(ann.take(), "LOBBY tic-tac-toe 1\nERROR say NAME <name> first\nWELCOME ann\n");
+        assert_eq!(bob.take(), "LOBBY tic-tac-toe 1\nERROR ann is taken\n");
+    }
+
+    #[test]
+    fn test_bot_replies_to_each_move() {
+        let lobby = Mutex::new(Lobby::default());
+        let out = Outbox::default();
+        let user = lobby.lock().unwrap().connect(Box::new(out.clone()));
+        for line in ["NAME ann", "PLAY bot easy", "MOVE B2"] {
+            run_command(&lobby, user, line);
+        }
+        let lines = out.take();
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(&lines[2..5], ["START 1 X bot:easy", "STATE 1 TURN X ---/---/---", "STATE 1 TURN O ---/-X-/---"]);
+        assert!(lines[5].starts_with("STATE 1 TURN X "));
+
+        // Leaving abandons the game and closes the table.
+        let mut lobby = lobby.lock().unwrap();
+        lobby.handle(user, "LEAVE");
+        assert_eq!(out.take(), "OK\n");
+        lobby.handle(user, "LIST");
+        assert_eq!(out.take(), "END\n");
+    }
+}
@@ -1,162 +1,317 @@
-use std::{io, num::ParseIntError};
+use std::{env, io, process};
+use std::path::Path;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
 
-use crate::game::{Game, PlayerSet, Player, PlayerType};
-use crate::board::{Board, XPos, YPos};
-
-mod board;
-mod game;
+use tic_tac_toe::board::{position_name, Square};
+use tic_tac_toe::cli::{self, Command};
+use tic_tac_toe::console::{Console, LineInput};
+use tic_tac_toe::engine::{Engine, ExternalEngine};
+use tic_tac_toe::http;
+use tic_tac_toe::learn::{self, QTable, Settings};
+use tic_tac_toe::lobby;
+use tic_tac_toe::net::{Client, Server};
+use tic_tac_toe::profile::{self, ProfileStore};
+use tic_tac_toe::render::{Renderer, Theme};
+use tic_tac_toe::retrograde;
+use tic_tac_toe::rng::Rng;
+use tic_tac_toe::save::{self, SavedGame};
+use tic_tac_toe::session::Autosave;
+use tic_tac_toe::simulate;
+use tic_tac_toe::solve;
+use tic_tac_toe::tournament::Tournament;
+use tic_tac_toe::tui;
+use tic_tac_toe::web::Web;
 
 fn main() {
-    play_game();
-    println!("Thanks for playing!");
-}
-
-fn play_game() { 
-    let mut game = init_game();
-    
-    let mut is_playing = true;
-    while is_playing { 
-        
-        match game { 
-            Game::Uninitiated => { println!("wait.. this shouldn't happen"); },
-            Game::Tie(board) => { 
-                println!("Game is tied");
-                board.pretty_print(); 
-            },
-            Game::Win(winner, board) => { 
-                println!("{} Wins!", winner.to_string());
-                board.pretty_print();
-            },
-            Game::InPlay { set, turn, board } => { 
-                match turn.player_type() { 
-                    PlayerType::Human => { game = player_input(set, turn, board); },
-                    PlayerType::Computer => { 
-                        let computer_move = game::computer_move(&turn, &set, &board);
-                        match computer_move { 
-                            Ok(updated_game) => { game = updated_game; },
-                            Err(error) => { panic!("THIS shouldn't happpen!! {:?}", error); }
-                        }
-                    }
-                }
-                
-                continue;
-            }
+    let options = match cli::parse(env::args().skip(1)) { 
+        Ok(options) => options,
+        Err(message) => { 
+            eprintln!("{}\n\n{}", message, cli::usage());
+            process::exit(2);
         }
+    };
+
+    let profiles_path = options.profiles.unwrap_or_else(profile::default_path);
 
-        is_playing = false;
+    if let Some(path) = &options.learner { 
+        learn::use_table(path.clone());
     }
-}
 
-fn player_input(set: PlayerSet, turn: Player, board: Board) -> Game { 
+    let palette = if options.color.enabled() { Some(options.palette) } else { None };
+    let renderer = Renderer::new(options.theme.unwrap_or_else(Theme::detect)).with_palette(palette);
 
-    let mut output_game = Game::Uninitiated;
-    let mut is_awaiting_input = true;
+    match options.command { 
+        Command::Stats => { 
+            match ProfileStore::load(&profiles_path) { 
+                Ok(profiles) => { print!("{}", profiles.format_stats()); },
+                Err(error) => { 
+                    eprintln!("Couldn't read profiles from {}: {}", profiles_path.display(), error);
+                    process::exit(1);
+                }
+            }
+        },
+        Command::Host(port) => { 
+            let server = match Server::bind(("0.0.0.0", port)) { 
+                Ok(server) => server,
+                Err(error) => { 
+                    eprintln!("Couldn't listen on port {}: {}", port, error);
+                    process::exit(1);
+                }
+            };
+            println!("Hosting on port {}. The other player runs: tic-tac-toe join <this machine>:{}", port, port);
+            std::thread::spawn(move || server.run());
+            play_network(Client::new(&format!("127.0.0.1:{}", port), Some(Square::X)), &renderer);
+        },
+        Command::Join(address) => { play_network(Client::new(&address, None), &renderer); },
+        Command::Serve(port) => { 
+            let result = TcpListener::bind(("0.0.0.0", port)).and_then(|listener| { 
+                println!("Open http://localhost:{}/ to play in a browser", port);
+                http::serve(listener, Arc::new(Web::default()))
+            });
+            if let Err(error) = result { 
+                eprintln!("Couldn't serve on port {}: {}", port, error);
+                process::exit(1);
+            }
+        },
+        Command::Lobby(port) => { 
+            let result = TcpListener::bind(("0.0.0.0", port)).and_then(|listener| { 
+                println!("Lobby open on port {}. Connect with: nc localhost {}", port, port);
+                lobby::serve(listener)
+            });
+            if let Err(error) = result { 
+                eprintln!("Couldn't run the lobby on port {}: {}", port, error);
+                process::exit(1);
+            }
+        },
+        Command::Simulate(x, o) => { 
+            let games = options.games.unwrap_or(cli::DEFAULT_SIMULATE_GAMES);
+            match simulate::run(&x, &o, games, threads(options.threads)) { 
+                Ok(report) if options.csv => { print!("{}", report.format_csv(&x, &o)); },
+                Ok(report) => { print!("{}", report.format_table(&x, &o)); },
+                Err(error) => { 
+                    eprintln!("Simulation failed: {}", error);
+                    process::exit(1);
+                }
+            }
+        },
+        Command::Tournament(participants) => { 
+            let games = options.games.unwrap_or(cli::DEFAULT_TOURNAMENT_GAMES);
+            let tournament = match Tournament::run(participants, games, threads(options.threads)) { 
+                Ok(tournament) => tournament,
+                Err(error) => { 
+                    eprintln!("Tournament failed: {}", error);
+                    process::exit(1);
+                }
+            };
+            print!("{}", tournament.format_crosstable());
+            if let Some(path) = &options.record { 
+                match tournament.save(path) { 
+                    Ok(()) => { println!("Games recorded in {}", path.display()); },
+                    Err(error) => { 
+                        eprintln!("Couldn't write the record to {}: {}", path.display(), error);
+                        process::exit(1);
+                    }
+                }
+            }
+        },
+        Command::Replay(path, number) => { replay(&path, number, &renderer); },
+        Command::Solve(path) => { 
+            let solution = solve::solve();
+            print!("{}", solution.format_report());
+            let path = path.unwrap_or_else(solve::default_path);
+            match solution.tablebase.save(&path) { 
+                Ok(()) => { println!("Tablebase of {} positions written to {}", solution.tablebase.len(), path.display()); },
+                Err(error) => { 
+                    eprintln!("Couldn't write the tablebase to {}: {}", path.display(), error);
+                    process::exit(1);
+                }
+            }
+        },
+        Command::Retrograde(variant, path) => { 
+            let path = path.unwrap_or_else(|| retrograde::default_path(variant));
+            let memory = options.memory.unwrap_or(retrograde::DEFAULT_MEMORY);
+            match retrograde::solve_to_file(variant, threads(options.threads), memory, &path) { 
+                Ok(report) => { 
+                    print!("{}", report.format_report());
+                    println!("Tablebase written to {}", path.display());
+                },
+                Err(error) => { 
+                    eprintln!("Couldn't solve {}: {}", variant, error);
+                    process::exit(1);
+                }
+            }
+        },
+        Command::Train(opponents) => { 
+            let path = options.learner.clone().unwrap_or_else(learn::default_path);
+            let mut table = match QTable::load(&path) { 
+                Ok(table) => table,
+                Err(error) => { 
+                    eprintln!("Couldn't read the learner from {}: {}", path.display(), error);
+                    process::exit(1);
+                }
+            };
+            let games = options.games.unwrap_or(cli::DEFAULT_TRAIN_GAMES) as u64;
+            let curve = learn::train(&mut table, &opponents, games, &Settings::default(), &mut Rng::from_entropy());
+            if options.csv { 
+                print!("{}", curve.format_csv());
+            } else { 
+                print!("{}", curve.format_table());
+            }
+            match table.save(&path) { 
+                Ok(()) if !options.csv => { println!("The learner has learned from {} games; saved in {}", table.games, path.display()); },
+                Ok(()) => {},
+                Err(error) => { 
+                    eprintln!("Couldn't save the learner to {}: {}", path.display(), error);
+                    process::exit(1);
+                }
+            }
+        },
+        Command::Engine => { 
+            // Not locked: the engine reads on a thread of its own.
+            let mut input = LineInput::new(io::BufReader::new(io::stdin()));
+            if let Err(error) = Engine::default().with_threads(threads(options.threads)).run(&mut input, &mut io::stdout()) { 
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        },
+        Command::Play => { 
+            // Only checked here so a bad file is noticed before the game rather
+            // than after it; results are merged into whatever is on disk at the end.
+            if let Err(error) = ProfileStore::load(&profiles_path) { 
+                eprintln!("Couldn't read profiles from {}: {}; results won't be saved", profiles_path.display(), error);
+            }
 
-    while is_awaiting_input { 
-        
-        board.pretty_print();
-        println!("{}, please enter move A1 thru C3:", turn.to_string());
+            let autosave: Autosave = Arc::new(Mutex::new(None));
+            install_autosave(&autosave);
 
-        let mut in_buffer = String::new();
+            let resume = options.resume.as_ref().map(|path| match SavedGame::load(path) { 
+                Ok(saved) => saved,
+                Err(error) => { 
+                    eprintln!("Couldn't resume from {}: {}", path.display(), error);
+                    process::exit(1);
+                }
+            });
 
-        io::stdin()
-        .read_line(&mut in_buffer)
-        .expect("failed to read");
+            let mut input = LineInput::new(io::stdin().lock());
+            let mut output = io::stdout();
+            let mut console = Console::new(&mut input, &mut output, renderer).with_autosave(autosave);
+            if options.coach { 
+                console = console.with_coach();
+            }
+            if let Some(command) = &options.engine { 
+                match ExternalEngine::spawn(command) { 
+                    Ok(engine) => { console = console.with_engine(engine); },
+                    Err(error) => { 
+                        eprintln!("Couldn't start engine '{}': {}", command, error);
+                        process::exit(1);
+                    }
+                }
+            }
 
-        in_buffer = in_buffer.to_ascii_uppercase();
-        let letter = &in_buffer[..1];
-        let number = &in_buffer[1..];
-        let x_pos: usize;
-        let y_pos: usize;
+            let result = if options.tui { 
+                console.start_session(resume).and_then(|mut session| { 
+                    tui::run(&mut session, &renderer, console.engine()).map(|_| session)
+                })
+            } else { 
+                console.run(resume)
+            };
 
-        match letter { 
-            "A" => { x_pos = XPos::A; },
-            "B" => { x_pos = XPos::B; },
-            "C" => { x_pos = XPos::C; },
-            _ => { 
-                println!("Letter. Please enter Letter (A-C) & Number (1-3) format. ie: A1, C2, etc");
-                continue; 
-            }
-        }
+            let session = match result { 
+                Ok(session) => session,
+                // stdin closed, nobody is left to play.
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => { process::exit(0); },
+                Err(error) => { 
+                    eprintln!("{}", error);
+                    process::exit(1);
+                }
+            };
 
-        match number.trim() { 
-            "1" => { y_pos = YPos::_1; },
-            "2" => { y_pos = YPos::_2; },
-            "3" => { y_pos = YPos::_3; },
-            _ => { 
-                println!("Number. Please enter Letter (A-C) & Number (1-3) format. ie: A1, C2, etc");
-                continue; 
+            let names = &session.names;
+            if let Err(error) = learn::learn_from_game(&session.set, &session.history) { 
+                eprintln!("Couldn't save what the learner learned: {}", error);
             }
-        }
 
-        let copy_board = board.clone();
-        let pos = &(x_pos, y_pos);
-        
-        let move_result = game::make_move(pos, &copy_board, &turn, &set);
-
-        match move_result { 
-            Ok(result_game) => {
-                output_game = result_game;
-                is_awaiting_input = false; 
-            },
-            Err(error) => { 
-                println!("Can't move there. Please choose another move. {:?}", error);
-                continue;
+            if names.x.is_some() || names.o.is_some() { 
+                if let Err(error) = ProfileStore::record_game_at(&profiles_path, &session.set, names, &session.game) { 
+                    eprintln!("Couldn't save profiles to {}: {}", profiles_path.display(), error);
+                }
             }
+            println!("Thanks for playing!");
         }
     }
-
-    output_game
 }
 
-fn init_game() -> Game {
-    let mut game = Game::Uninitiated;
-    let mut is_init = true;
-
-    while is_init {
-        println!("Tic Tac Toe.  Enter an option: (X goes first)");
-        println!("1. X: Human, O: Computer");
-        println!("2: X: Human, O: Human");
-        println!("3: X: Computer, O: Human");
+/// The thread count asked for, or one per CPU.
+fn threads(requested: Option<usize>) -> usize { 
+    requested.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+}
 
-        let mut in_buffer = String::new();
+/// Prints game `number` of a tournament record move by move and exits
+/// with an error if the moves don't give the recorded result.
+fn replay(path: &Path, number: usize, renderer: &Renderer) { 
+    let tournament = match Tournament::load(path) { 
+        Ok(tournament) => tournament,
+        Err(error) => { 
+            eprintln!("Couldn't read the tournament record {}: {}", path.display(), error);
+            process::exit(1);
+        }
+    };
+    let (positions, replayed) = match tournament.replay(number) { 
+        Ok(replay) => replay,
+        Err(error) => { 
+            eprintln!("Game {} can't be replayed: {}", number, error);
+            process::exit(1);
+        }
+    };
 
-        io::stdin()
-        .read_line(&mut in_buffer)
-        .expect("failed to read");
+    let recorded = &tournament.games[number - 1];
+    let name = |index: usize| &tournament.participants[index].name;
+    println!("Game {}: {} (X) vs {} (O), recorded as {}", number, name(recorded.x), name(recorded.o), recorded.result_label());
+    let mut out = io::stdout();
+    for (index, (board, &pos)) in positions.iter().zip(&replayed.moves).enumerate() { 
+        let mark = if index % 2 == 0 { "X" } else { "O" };
+        println!("\n{}. {} {}", index + 1, mark, position_name(pos));
+        // Nothing sensible to do if stdout is gone.
+        let _ = renderer.write_board(&mut out, board, Some(pos));
+    }
 
-        let option: Result<i32, ParseIntError> = in_buffer
-        .trim()
-        .parse();
+    if replayed.winner == recorded.winner { 
+        println!("\nThe moves give {}, as recorded.", replayed.result_label());
+    } else { 
+        println!("\nThe moves give {}, but the record says {}.", replayed.result_label(), recorded.result_label());
+        process::exit(1);
+    }
+}
 
-        if option.is_err() { 
-            println!("Please enter a number");
-            continue; 
+fn play_network(mut client: Client, renderer: &Renderer) { 
+    let mut input = LineInput::new(io::stdin().lock());
+    match client.run(&mut input, &mut io::stdout(), renderer) { 
+        Ok(_) => { println!("Thanks for playing!"); },
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => { process::exit(0); },
+        Err(error) => { 
+            eprintln!("Network game ended: {}", error);
+            process::exit(1);
         }
+    }
+}
 
-        let num_input: i32 = option.unwrap();
-        
-        let mut x_type = PlayerType::Human;
-        let mut o_type = PlayerType::Human;
-
-        match num_input {
-            1 => { o_type = PlayerType::Computer; },
-            2 => { },
-            3 => { x_type = PlayerType::Computer; }
-            _ => { 
-                println!("invalid input, try again");
-                continue;
+/// Ctrl-C writes the game in progress to the autosave file before exiting.
+fn install_autosave(autosave: &Autosave) { 
+    let autosave = Arc::clone(autosave);
+    let result = ctrlc::set_handler(move || { 
+        let snapshot = autosave.lock().map(|saved| saved.clone()).unwrap_or(None);
+        if let Some(saved) = snapshot { 
+            let path = save::autosave_path();
+            match saved.save(&path) { 
+                Ok(()) => { println!("\nGame saved. Resume with --resume {}", path.display()); },
+                Err(error) => { eprintln!("\nCouldn't autosave to {}: {}", path.display(), error); }
             }
         }
+        process::exit(130);
+    });
 
-        let x = Player::X(x_type);
-        let o = Player::O(o_type);
-        let player_set = PlayerSet { x: x, o: o };
-        let board = Board::default();
-
-        game = Game::InPlay { set: player_set, turn: player_set.x, board: board };
-        is_init = false;
+    if let Err(error) = result { 
+        eprintln!("Autosave on Ctrl-C is unavailable: {}", error);
     }
-
-    game
-}
\ No newline at end of file
+}
@@ -0,0 +1,243 @@
+//! Monte Carlo tree search (UCT), the `mcts` difficulty.
+//!
+//! Unlike `minimax` it never looks at the whole game tree: it plays random
+//! games from the position, spends more of them under the moves that have
+//! done well so far, and picks the move it explored most.  It needs
+//! nothing from a game but the [`Position`] trait in `search`, so any
+//! board and rules that implement it can be searched.
+
+use std::time::{Duration, Instant};
+
+use crate::rng::Rng;
+use crate::search::{Outcome, Position};
+
+/// When to stop searching.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Budget {
+    /// Random games played from the root.
+    Iterations(u32),
+    Time(Duration)
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget::Iterations(2000)
+    }
+}
+
+impl Budget {
+    /// A number of iterations, such as `5000`, or a time such as `100ms`.
+    pub fn label(self) -> String {
+        match self {
+            Budget::Iterations(count) => count.to_string(),
+            Budget::Time(time) => format!("{}ms", time.as_millis())
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Budget> {
+        let budget = match label.strip_suffix("ms") {
+            Some(millis) => Budget::Time(Duration::from_millis(millis.parse().ok()?)),
+            None => Budget::Iterations(label.parse().ok()?)
+        };
+        (budget != Budget::Iterations(0) && budget != Budget::Time(Duration::ZERO)).then_some(budget)
+    }
+}
+
+/// Search statistics for one move from the root.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MoveStats<M> {
+    pub mv: M,
+    pub visits: u32,
+    /// Playouts through this move that the side playing it won.
+    pub wins: u32,
+    pub draws: u32
+}
+
+impl<M> MoveStats<M> {
+    pub fn win_rate(&self) -> f64 {
+        if self.visits == 0 { 0.0 } else { self.wins as f64 / self.visits as f64 }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Search<M> {
+    /// The most visited move, or `None` if the game is over.
+    pub best: Option<M>,
+    /// Every root move, most visited first.
+    pub moves: Vec<MoveStats<M>>,
+    pub iterations: u32
+}
+
+/// The exploration constant in UCB1.
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+struct Node<P: Position> {
+    position: P,
+    mv: Option<P::Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<P::Move>,
+    visits: u32,
+    /// From the side that played `mv`: wins count 1 and draws ½.
+    reward: f64,
+    wins: u32,
+    draws: u32
+}
+
+impl<P: Position> Node<P> {
+    fn new(position: P, mv: Option<P::Move>, parent: Option<usize>) -> Self {
+        let untried = position.moves();
+        Node { position, mv, parent, children: Vec::new(), untried, visits: 0, reward: 0.0, wins: 0, draws: 0 }
+    }
+}
+
+/// Searches `root` until `budget` runs out.
+pub fn search<P: Position>(root: &P, budget: Budget, rng: &mut Rng) -> Search<P::Move> {
+    let mut nodes = vec![Node::new(root.clone(), None, None)];
+    let started = Instant::now();
+    let mut iterations = 0;
+
+    while !nodes[0].untried.is_empty() || !nodes[0].children.is_empty() {
+        match budget {
+            Budget::Iterations(limit) if iterations >= limit => break,
+            Budget::Time(limit) if iterations > 0 && started.elapsed() >= limit => break,
+            _ => { }
+        }
+        iterations += 1;
+
+        // Selection: follow UCB1 while every move has been tried.
+        let mut current = 0;
+        while nodes[current].untried.is_empty() && !nodes[current].children.is_empty() {
+            let parent_visits = (nodes[current].visits as f64).ln();
+            current = *nodes[current].children.iter()
+                .max_by(|&&a, &&b| ucb(&nodes[a], parent_visits).total_cmp(&ucb(&nodes[b], parent_visits)))
+                .expect("children is not empty");
+        }
+
+        // Expansion: one new child for a random untried move.
+        if !nodes[current].untried.is_empty() {
+            let index = rng.below(nodes[current].untried.len());
+            let mv = nodes[current].untried.swap_remove(index);
+            let position = nodes[current].position.play(mv);
+            nodes.push(Node::new(position, Some(mv), Some(current)));
+            let child = nodes.len() - 1;
+            nodes[current].children.push(child);
+            current = child;
+        }
+
+        // Simulation: random moves to the end.
+        let mut position = nodes[current].position.clone();
+        let outcome = loop {
+            if let Some(outcome) = position.outcome() {
+                break outcome;
+            }
+            let moves = position.moves();
+            position = position.play(moves[rng.below(moves.len())]);
+        };
+
+        // Backpropagation, scoring each node for the side that moved into it.
+        let mut node = Some(current);
+        while let Some(index) = node {
+            let parent = nodes[index].parent;
+            let mover = parent.map(|parent| nodes[parent].position.side_to_move());
+            let entry = &mut nodes[index];
+            entry.visits += 1;
+            match (outcome, mover) {
+                (Outcome::Win(winner), Some(mover)) if winner == mover => {
+                    entry.reward += 1.0;
+                    entry.wins += 1;
+                },
+                (Outcome::Draw, _) => {
+                    entry.reward += 0.5;
+                    entry.draws += 1;
+                },
+                _ => { }
+            }
+            node = parent;
+        }
+    }
+
+    let mut moves: Vec<MoveStats<P::Move>> = nodes[0].children.iter()
+        .map(|&child| {
+            let node = &nodes[child];
+            MoveStats { mv: node.mv.expect("children have moves"), visits: node.visits, wins: node.wins, draws: node.draws }
+        })
+        .collect();
+    moves.sort_by_key(|stats| std::cmp::Reverse(stats.visits));
+    Search { best: moves.first().map(|stats| stats.mv), moves, iterations }
+}
+
+fn ucb<P: Position>(node: &Node<P>, parent_visits_ln: f64) -> f64 {
+    let visits = node.visits as f64;
+    node.reward / visits + EXPLORATION * (parent_visits_ln / visits).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use crate::board::{Board, XPos, YPos};
+    use crate::rng::Rng;
+
+    use crate::search::{Outcome, Position};
+
+    use super::{search, Budget};
+
+    #[test]
+    fn test_takes_wins_and_blocks() {
+        let mut rng = Rng::new(1);
+        let win = Board::from_str("XX-\nOO-\n---").unwrap();
+        assert_eq!(search(&win, Budget::Iterations(2000), &mut rng).best, Some((XPos::A, YPos::_3)));
+
+        let block = Board::from_str("XX-\n-O-\n---").unwrap();
+        assert_eq!(search(&block, Budget::Iterations(2000), &mut rng).best, Some((XPos::A, YPos::_3)));
+    }
+
+    #[test]
+    fn test_statistics() {
+        let result = search(&Board::default(), Budget::Iterations(900), &mut Rng::new(5));
+        assert_eq!(result.iterations, 900);
+        assert_eq!(result.moves.len(), 9);
+        assert_eq!(result.moves.iter().map(|stats| stats.visits).sum::<u32>(), 900);
+        assert!(result.moves.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+        assert_eq!(result.best, Some(result.moves[0].mv));
+        assert!(result.moves.iter().all(|stats| (0.0..=1.0).contains(&stats.win_rate())));
+
+        let over = Board::from_str("XXX\nOO-\n---").unwrap();
+        let result = search(&over, Budget::Iterations(100), &mut Rng::new(5));
+        assert_eq!((result.best, result.iterations), (None, 0));
+
+        let timed = search(&Board::default(), Budget::Time(Duration::from_millis(20)), &mut Rng::new(5));
+        assert!(timed.iterations > 0 && timed.best.is_some());
+    }
+
+    #[test]
+    fn test_never_loses_to_random_play() {
+        let mut rng = Rng::new(42);
+        for game in 0..60 {
+            let mcts_side = game % 2;
+            let mut board = Board::default();
+            while board.outcome().is_none() {
+                let mv = if Position::side_to_move(&board) == mcts_side {
+                    search(&board, Budget::Iterations(2000), &mut rng).best.unwrap()
+                } else {
+                    let moves = board.moves();
+                    moves[rng.below(moves.len())]
+                };
+                board = board.play(mv);
+            }
+            assert_ne!(board.outcome(), Some(Outcome::Win(1 - mcts_side)), "lost game {}:\n{}", game, board.notation());
+        }
+    }
+
+    #[test]
+    fn test_budget_labels() {
+        assert_eq!(Budget::from_label("5000"), Some(Budget::Iterations(5000)));
+        assert_eq!(Budget::from_label("250ms"), Some(Budget::Time(Duration::from_millis(250))));
+        assert_eq!(Budget::Time(Duration::from_millis(250)).label(), "250ms");
+        for bad in ["0", "0ms", "fast", "-3", "ms"] {
+            assert_eq!(Budget::from_label(bad), None, "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! m,n,k games: tic-tac-toe on a board of `rows` by `cols` squares where
+//! `k` in a row, across, down or diagonally, wins.  Classic tic-tac-toe is
+//! 3,3,3.
+//!
+//! [`MnkBoard`] keeps each side's squares as a bit set, square `row × cols
+//! + col` in bit `row × cols + col`, and implements [`Position`] so the
+//! Monte Carlo search plays any variant.  The retrograde solver in
+//! `retrograde` works on the same bit sets.
+
+use std::fmt;
+
+use crate::board::{Board, Square};
+use crate::search::{Outcome, Position};
+
+/// The most squares a variant may have, so a side fits in a `u32`.
+pub const MAX_SQUARES: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Variant {
+    pub rows: usize,
+    pub cols: usize,
+    /// How many in a row win.
+    pub k: usize
+}
+
+impl Variant {
+    pub const CLASSIC: Variant = Variant { rows: 3, cols: 3, k: 3 };
+
+    /// `None` unless the board has 1 to `MAX_SQUARES` squares and `k` fits
+    /// along at least one side.
+    pub fn new(rows: usize, cols: usize, k: usize) -> Option<Variant> {
+        let squares = rows.checked_mul(cols)?;
+        (rows > 0 && cols > 0 && squares <= MAX_SQUARES && k > 0 && k <= rows.max(cols)).then_some(Variant { rows, cols, k })
+    }
+
+    pub fn squares(self) -> usize {
+        self.rows * self.cols
+    }
+
+    /// `4x4k3`, or `4x4` when `k` is the shorter side.
+    pub fn label(self) -> String {
+        if self.k == self.rows.min(self.cols) {
+            format!("{}x{}", self.rows, self.cols)
+        } else {
+            format!("{}x{}k{}", self.rows, self.cols, self.k)
+        }
+    }
+
+    /// Reads `label`'s forms.
+    pub fn from_label(label: &str) -> Option<Variant> {
+        let (size, k) = match label.split_once('k') {
+            Some((size, k)) => (size, Some(k.parse().ok()?)),
+            None => (label, None)
+        };
+        let (rows, cols) = size.split_once('x')?;
+        let (rows, cols): (usize, usize) = (rows.parse().ok()?, cols.parse().ok()?);
+        Variant::new(rows, cols, k.unwrap_or(rows.min(cols)))
+    }
+
+    /// Every run of `k` squares in a line, as bit sets.
+    pub fn lines(self) -> Vec<u32> {
+        let mut lines = Vec::new();
+        let (rows, cols, k) = (self.rows as isize, self.cols as isize, self.k as isize);
+        for row in 0..rows {
+            for col in 0..cols {
+                for (down, across) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+                    let (end_row, end_col) = (row + down * (k - 1), col + across * (k - 1));
+                    if end_row >= rows || end_col < 0 || end_col >= cols {
+                        continue;
+                    }
+                    let line = (0..k).fold(0u32, |line, step| line | 1 << ((row + down * step) * cols + col + across * step));
+                    if !lines.contains(&line) {
+                        lines.push(line);
+                    }
+                }
+            }
+        }
+        lines
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+/// A position in an m,n,k game.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MnkBoard {
+    pub variant: Variant,
+    pub x: u32,
+    pub o: u32,
+    lines: std::sync::Arc<[u32]>
+}
+
+impl MnkBoard {
+    pub fn new(variant: Variant) -> MnkBoard {
+        MnkBoard { variant, x: 0, o: 0, lines: variant.lines().into() }
+    }
+
+    /// The classic board as a 3,3,3 position.
+    pub fn from_board(board: &Board) -> MnkBoard {
+        let mut mnk = MnkBoard::new(Variant::CLASSIC);
+        for (index, square) in board.squares.iter().flatten().enumerate() {
+            match square {
+                Square::X => { mnk.x |= 1 << index; },
+                Square::O => { mnk.o |= 1 << index; },
+                Square::Empty => { }
+            }
+        }
+        mnk
+    }
+
+    pub fn full(&self) -> u32 {
+        if self.variant.squares() == 32 { u32::MAX } else { (1 << self.variant.squares()) - 1 }
+    }
+
+    pub fn has_line(&self, side: u32) -> bool {
+        self.lines.iter().any(|&line| line & !side == 0)
+    }
+
+    /// Rows of `X`, `O` and `-` joined by `/`, as the network protocol
+    /// writes classic boards.
+    pub fn notation(&self) -> String {
+        (0..self.variant.rows)
+            .map(|row| (0..self.variant.cols).map(|col| {
+                let bit = 1 << (row * self.variant.cols + col);
+                if self.x & bit != 0 { 'X' } else if self.o & bit != 0 { 'O' } else { '-' }
+            }).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+}
+
+impl Position for MnkBoard {
+    /// A square index, `row × cols + col`.
+    type Move = usize;
+
+    fn moves(&self) -> Vec<usize> {
+        if self.outcome().is_some() {
+            return Vec::new();
+        }
+        let taken = self.x | self.o;
+        (0..self.variant.squares()).filter(|square| taken & (1 << square) == 0).collect()
+    }
+
+    fn play(&self, square: usize) -> MnkBoard {
+        let mut after = self.clone();
+        if self.side_to_move() == 0 { after.x |= 1 << square; } else { after.o |= 1 << square; }
+        after
+    }
+
+    fn side_to_move(&self) -> usize {
+        usize::from(self.x.count_ones() > self.o.count_ones())
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        if self.has_line(self.x) {
+            Some(Outcome::Win(0))
+        } else if self.has_line(self.o) {
+            Some(Outcome::Win(1))
+        } else if self.x | self.o == self.full() {
+            Some(Outcome::Draw)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::mcts::{self, Budget};
+    use crate::search::{Outcome, Position};
+    use crate::rng::Rng;
+
+    use super::{MnkBoard, Variant};
+
+    #[test]
+    fn test_variants() {
+        assert_eq!(Variant::CLASSIC.lines().len(), 8);
+        assert_eq!(Variant::new(4, 4, 3).unwrap().lines().len(), 24);
+        assert_eq!(Variant::new(4, 4, 4).unwrap().lines().len(), 10);
+        assert_eq!(Variant::new(3, 4, 3).unwrap().lines().len(), 3 * 2 + 4 + 2 * 2);
+        assert_eq!(Variant::from_label("4x4k3"), Variant::new(4, 4, 3));
+        assert_eq!(Variant::from_label("3x4"), Variant::new(3, 4, 3));
+        assert_eq!(Variant::new(4, 4, 3).unwrap().label(), "4x4k3");
+        assert_eq!(Variant::CLASSIC.label(), "3x3");
+        for bad in ["4x4k5", "0x3", "6x6", "3by3", "3x3k", "x3"] {
+            assert_eq!(Variant::from_label(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_play_and_outcome() {
+        let board = MnkBoard::from_board(&Board::from_str("XX-\nOO-\n---").unwrap());
+        assert_eq!(board.notation(), "XX-/OO-/---");
+        assert_eq!(board.side_to_move(), 0);
+        assert_eq!(board.play(2).outcome(), Some(Outcome::Win(0)));
+        assert_eq!(board.moves(), vec![2, 5, 6, 7, 8]);
+
+        // Monte Carlo search plays the wider board too.
+        let wide = MnkBoard::new(Variant::new(3, 4, 3).unwrap()).play(0).play(4).play(1);
+        assert_eq!(wide.notation(), "XX--/O---/----");
+        let result = mcts::search(&wide, Budget::Iterations(2000), &mut Rng::new(3));
+        assert_eq!(result.best, Some(2));
+    }
+}
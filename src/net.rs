@@ -0,0 +1,558 @@
+//! Two-player games over TCP.
+//!
+//! The host runs a [`Server`] that owns the only real copy of the game.
+//! Each player, the host's own included, is a [`Client`] holding one seat.
+//! Clients only propose moves; the server checks them with
+//! [`game::make_move`] and broadcasts the result.
+//!
+//! # Protocol
+//!
+//! Plain text, one message per `\n`-terminated line, words separated by
+//! single spaces.  Boards are the three rows A, B and C of `X`, `O` and `-`
+//! joined by `/`, for example `X--/-O-/---`.
+//!
+//! Client to server:
+//!
+//! | Message | Meaning |
+//! |---|---|
+//! | `HELLO tic-tac-toe <version> [X\|O]` | First line of every connection.  The seat is optional; a reconnecting client asks for the seat it had. |
+//! | `MOVE <square>` | Play a square such as `B2`. |
+//! | `BYE` | Leave. |
+//!
+//! Server to client:
+//!
+//! | Message | Meaning |
+//! |---|---|
+//! | `WELCOME <version> <X\|O>` | Handshake accepted; the seat this connection plays. |
+//! | `WAITING <X\|O>` | That seat is empty, so moves are on hold until it (re)connects. |
+//! | `STATE TURN <X\|O> <board>` | The game after the latest move, and who moves next. |
+//! | `STATE WIN <X\|O> <board>` | The game is over and won. |
+//! | `STATE TIE <board>` | The game is over and tied. |
+//! | `ERROR <message>` | The last message was refused.  After a failed handshake the server hangs up. |
+//!
+//! The version is [`PROTOCOL_VERSION`]; a server refuses any other.  When a
+//! connection drops mid-game its seat frees up, the other player is sent
+//! `WAITING`, and the next `HELLO` for that seat takes it over and gets the
+//! current `STATE`.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::board::{parse_position, position_name, Board, Square};
+use crate::console::Input;
+use crate::game::{self, Game, Player, PlayerSet, PlayerType};
+use crate::render::Renderer;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// A line sent by a client.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ClientMessage {
+    Hello { version: u32, seat: Option<Square> },
+    Move((usize, usize)),
+    Bye
+}
+
+/// A line sent by the server.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ServerMessage {
+    Welcome { version: u32, seat: Square },
+    Waiting(Square),
+    State(Game),
+    Error(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtocolError(pub String);
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "protocol error: {}", self.0)
+    }
+}
+
+fn protocol_error(message: &str) -> ProtocolError {
+    ProtocolError(message.to_string())
+}
+
+/// Both seats are people; the network decides who is on the other end.
+pub fn player_set() -> PlayerSet {
+    PlayerSet { x: Player::X(PlayerType::Human), o: Player::O(PlayerType::Human) }
+}
+
+fn parse_seat(text: &str) -> Result<Square, ProtocolError> {
+    match text {
+        "X" => Ok(Square::X),
+        "O" => Ok(Square::O),
+        _ => Err(protocol_error(&format!("'{}' is not a seat", text)))
+    }
+}
+
+fn player_for(seat: Square) -> Player {
+    let set = player_set();
+    if seat == Square::X { set.x } else { set.o }
+}
+
+/// `X--/-O-/---`: the board rows joined by `/`.
+pub fn encode_board(board: &Board) -> String {
+    board.notation().replace('\n', "/")
+}
+
+pub fn decode_board(text: &str) -> Result<Board, ProtocolError> {
+    Board::from_str(&text.replace('/', "\n")).map_err(|_| protocol_error(&format!("bad board '{}'", text)))
+}
+
+/// The words after `STATE`: `TURN X <board>`, `WIN O <board>` or
+/// `TIE <board>`.
+pub fn encode_game(game: &Game) -> String {
+    match game {
+        Game::InPlay { turn, board, .. } => format!("TURN {} {}", turn.associated_square().notation(), encode_board(board)),
+        Game::Win(winner, board) => format!("WIN {} {}", winner.associated_square().notation(), encode_board(board)),
+        Game::Tie(board) => format!("TIE {}", encode_board(board)),
+        Game::Uninitiated => String::new()
+    }
+}
+
+impl fmt::Display for ClientMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hello { version, seat: None } => write!(f, "HELLO tic-tac-toe {}", version),
+            Self::Hello { version, seat: Some(seat) } => write!(f, "HELLO tic-tac-toe {} {}", version, seat.notation()),
+            Self::Move(pos) => write!(f, "MOVE {}", position_name(*pos)),
+            Self::Bye => write!(f, "BYE")
+        }
+    }
+}
+
+impl FromStr for ClientMessage {
+    type Err = ProtocolError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["HELLO", "tic-tac-toe", version, rest @ ..] if rest.len() <= 1 => {
+                let version = version.parse().map_err(|_| protocol_error("bad version"))?;
+                let seat = rest.first().map(|seat| parse_seat(seat)).transpose()?;
+                Ok(Self::Hello { version, seat })
+            },
+            ["MOVE", square] => parse_position(square)
+                .map(Self::Move)
+                .ok_or_else(|| protocol_error(&format!("'{}' is not a square", square))),
+            ["BYE"] => Ok(Self::Bye),
+            _ => Err(protocol_error(&format!("unknown message '{}'", line.trim())))
+        }
+    }
+}
+
+impl fmt::Display for ServerMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Welcome { version, seat } => write!(f, "WELCOME {} {}", version, seat.notation()),
+            Self::Waiting(seat) => write!(f, "WAITING {}", seat.notation()),
+            Self::State(Game::Uninitiated) => write!(f, "ERROR no game"),
+            Self::State(game) => write!(f, "STATE {}", encode_game(game)),
+            Self::Error(message) => write!(f, "ERROR {}", message)
+        }
+    }
+}
+
+impl FromStr for ServerMessage {
+    type Err = ProtocolError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        if let Some(message) = line.strip_prefix("ERROR ") {
+            return Ok(Self::Error(message.to_string()));
+        }
+
+        let set = player_set();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["WELCOME", version, seat] => Ok(Self::Welcome {
+                version: version.parse().map_err(|_| protocol_error("bad version"))?,
+                seat: parse_seat(seat)?
+            }),
+            ["WAITING", seat] => Ok(Self::Waiting(parse_seat(seat)?)),
+            ["STATE", "TURN", seat, board] => Ok(Self::State(Game::InPlay {
+                set,
+                turn: player_for(parse_seat(seat)?),
+                board: decode_board(board)?
+            })),
+            ["STATE", "WIN", seat, board] => Ok(Self::State(Game::Win(player_for(parse_seat(seat)?), decode_board(board)?))),
+            ["STATE", "TIE", board] => Ok(Self::State(Game::Tie(decode_board(board)?))),
+            _ => Err(protocol_error(&format!("unknown message '{}'", line)))
+        }
+    }
+}
+
+fn send(stream: &mut TcpStream, message: &impl fmt::Display) -> io::Result<()> {
+    writeln!(stream, "{}", message)
+}
+
+fn seat_index(seat: Square) -> usize {
+    if seat == Square::X { 0 } else { 1 }
+}
+
+fn other_seat(seat: Square) -> Square {
+    if seat == Square::X { Square::O } else { Square::X }
+}
+
+struct Connection {
+    id: u64,
+    stream: TcpStream
+}
+
+struct Shared {
+    game: Game,
+    seats: [Option<Connection>; 2],
+    next_id: u64
+}
+
+impl Shared {
+    fn broadcast(&mut self, message: &ServerMessage) {
+        for seat in self.seats.iter_mut() {
+            let failed = match seat {
+                Some(connection) => send(&mut connection.stream, message).is_err(),
+                None => false
+            };
+            if failed {
+                *seat = None;
+            }
+        }
+    }
+
+    /// Sends the game to both players, or tells whoever is there which
+    /// seat is still empty.
+    fn announce(&mut self) {
+        match &self.seats {
+            [Some(_), Some(_)] => {
+                let state = ServerMessage::State(self.game);
+                self.broadcast(&state);
+            },
+            [None, _] => self.broadcast(&ServerMessage::Waiting(Square::X)),
+            [_, None] => self.broadcast(&ServerMessage::Waiting(Square::O))
+        }
+    }
+}
+
+/// The authoritative side of a network game.
+pub struct Server {
+    listener: TcpListener,
+    shared: Arc<Mutex<Shared>>
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Server> {
+        let set = player_set();
+        let shared = Shared {
+            game: Game::InPlay { set, turn: set.x, board: Board::default() },
+            seats: [None, None],
+            next_id: 0
+        };
+        Ok(Server { listener: TcpListener::bind(addr)?, shared: Arc::new(Mutex::new(shared)) })
+    }
+
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts players until the game ends, then returns the final game.
+    pub fn run(self) -> io::Result<Game> {
+        let (finished_tx, finished_rx) = mpsc::channel();
+        let shared = Arc::clone(&self.shared);
+        let listener = self.listener;
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = Arc::clone(&shared);
+                let finished_tx = finished_tx.clone();
+                thread::spawn(move || {
+                    // A dropped connection only frees its seat.
+                    let _ = serve_connection(stream, &shared, &finished_tx);
+                });
+            }
+        });
+
+        finished_rx.recv().map_err(|_| io::Error::other("server stopped"))
+    }
+}
+
+fn lock(shared: &Mutex<Shared>) -> std::sync::MutexGuard<'_, Shared> {
+    shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn serve_connection(stream: TcpStream, shared: &Mutex<Shared>, finished: &Sender<Game>) -> io::Result<()> {
+    // One short line per move: send each straight away.
+    stream.set_nodelay(true)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let requested = match line.parse::<ClientMessage>() {
+        Ok(ClientMessage::Hello { version, seat }) if version == PROTOCOL_VERSION => seat,
+        Ok(ClientMessage::Hello { version, .. }) => {
+            let message = format!("version {} is not supported, this host speaks {}", version, PROTOCOL_VERSION);
+            return send(&mut writer, &ServerMessage::Error(message));
+        },
+        _ => return send(&mut writer, &ServerMessage::Error("expected HELLO".to_string()))
+    };
+
+    let (id, seat) = {
+        let mut state = lock(shared);
+        let seat = match requested {
+            Some(seat) if state.seats[seat_index(seat)].is_none() => seat,
+            Some(seat) => {
+                return send(&mut writer, &ServerMessage::Error(format!("seat {} is taken", seat.notation())));
+            },
+            None => match state.seats.iter().position(Option::is_none) {
+                Some(0) => Square::X,
+                Some(_) => Square::O,
+                None => return send(&mut writer, &ServerMessage::Error("game is full".to_string()))
+            }
+        };
+
+        let id = state.next_id;
+        state.next_id += 1;
+        send(&mut writer, &ServerMessage::Welcome { version: PROTOCOL_VERSION, seat })?;
+        state.seats[seat_index(seat)] = Some(Connection { id, stream: writer.try_clone()? });
+        state.announce();
+        (id, seat)
+    };
+
+    let result = read_moves(&mut reader, &mut writer, shared, seat, finished);
+
+    let mut state = lock(shared);
+    let still_ours = state.seats[seat_index(seat)].as_ref().is_some_and(|connection| connection.id == id);
+    if still_ours {
+        state.seats[seat_index(seat)] = None;
+        if let Game::InPlay { .. } = state.game {
+            state.broadcast(&ServerMessage::Waiting(seat));
+        }
+    }
+    result
+}
+
+fn read_moves(
+    reader: &mut impl BufRead,
+    writer: &mut TcpStream,
+    shared: &Mutex<Shared>,
+    seat: Square,
+    finished: &Sender<Game>) -> io::Result<()> {
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let pos = match line.parse::<ClientMessage>() {
+            Ok(ClientMessage::Move(pos)) => pos,
+            Ok(ClientMessage::Bye) => return Ok(()),
+            Ok(ClientMessage::Hello { .. }) => {
+                send(writer, &ServerMessage::Error("already connected".to_string()))?;
+                continue;
+            },
+            Err(ProtocolError(message)) => {
+                send(writer, &ServerMessage::Error(message))?;
+                continue;
+            }
+        };
+
+        let mut state = lock(shared);
+        let Game::InPlay { set, turn, board } = state.game else {
+            send(writer, &ServerMessage::Error("the game is over".to_string()))?;
+            continue;
+        };
+        if state.seats[seat_index(other_seat(seat))].is_none() {
+            send(writer, &ServerMessage::Error(format!("waiting for {} to connect", other_seat(seat).notation())))?;
+            continue;
+        }
+        if turn.associated_square() != seat {
+            send(writer, &ServerMessage::Error("it is not your turn".to_string()))?;
+            continue;
+        }
+
+        match game::make_move(&pos, &board, &turn, &set) {
+            Ok(updated) => {
+                let is_over = !matches!(updated, Game::InPlay { .. });
+                state.game = updated;
+                state.announce();
+                if is_over {
+                    let _ = finished.send(state.game);
+                }
+            },
+            Err(_) => { send(writer, &ServerMessage::Error(format!("{} is taken", position_name(pos))))?; }
+        }
+    }
+}
+
+/// One player's end of a network game.
+pub struct Client {
+    addr: String,
+    seat: Option<Square>,
+    /// How often, and how far apart, to try again after losing the host.
+    pub reconnect_attempts: u32,
+    pub reconnect_delay: Duration
+}
+
+impl Client {
+    pub fn new(addr: &str, seat: Option<Square>) -> Self {
+        Client { addr: addr.to_string(), seat, reconnect_attempts: 10, reconnect_delay: Duration::from_secs(1) }
+    }
+
+    fn connect(&mut self) -> io::Result<(BufReader<TcpStream>, TcpStream)> {
+        let mut stream = TcpStream::connect(&self.addr)?;
+        stream.set_nodelay(true)?;
+        send(&mut stream, &ClientMessage::Hello { version: PROTOCOL_VERSION, seat: self.seat })?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        match line.parse::<ServerMessage>() {
+            Ok(ServerMessage::Welcome { seat, .. }) => {
+                self.seat = Some(seat);
+                Ok((reader, stream))
+            },
+            Ok(ServerMessage::Error(message)) => Err(io::Error::new(io::ErrorKind::ConnectionRefused, message)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "host did not answer the handshake"))
+        }
+    }
+
+    fn reconnect(&mut self, output: &mut dyn Write) -> io::Result<(BufReader<TcpStream>, TcpStream)> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotConnected, "lost the host");
+        for _ in 0..self.reconnect_attempts {
+            writeln!(output, "Lost the connection, reconnecting...")?;
+            thread::sleep(self.reconnect_delay);
+            match self.connect() {
+                Ok(connection) => return Ok(connection),
+                Err(error) => { last_error = error; }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Plays until the game ends and returns the final game.
+    pub fn run(&mut self, input: &mut dyn Input, output: &mut dyn Write, renderer: &Renderer) -> io::Result<Game> {
+        let (mut reader, mut stream) = self.connect()?;
+        let me = player_for(self.seat.unwrap_or(Square::X));
+        writeln!(output, "Connected to {} as {}", self.addr, renderer.player(&me))?;
+
+        let mut last_board = Board::default();
+        // Whether the host is waiting on our move, so a refusal means
+        // choosing again.
+        let mut my_turn = false;
+        loop {
+            let mut line = String::new();
+            let read = reader.read_line(&mut line);
+            if !matches!(read, Ok(n) if n > 0) {
+                (reader, stream) = self.reconnect(output)?;
+                continue;
+            }
+
+            let message = line.parse::<ServerMessage>().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            match message {
+                ServerMessage::Welcome { .. } => { },
+                ServerMessage::Waiting(seat) => {
+                    writeln!(output, "Waiting for {} to connect...", renderer.player(&player_for(seat)))?;
+                },
+                ServerMessage::Error(message) => {
+                    writeln!(output, "Host says: {}", message)?;
+                    if my_turn {
+                        let pos = prompt_move(input, output, renderer, &me, &last_board)?;
+                        send(&mut stream, &ClientMessage::Move(pos))?;
+                    }
+                },
+                ServerMessage::State(game) => {
+                    let board = *game.board().unwrap_or(&last_board);
+                    let last_move = last_board.changed_position(&board);
+                    last_board = board;
+                    my_turn = matches!(game, Game::InPlay { turn, .. } if turn == me);
+
+                    match game {
+                        Game::InPlay { turn, .. } if turn == me => {
+                            renderer.write_board(output, &board, last_move)?;
+                            let pos = prompt_move(input, output, renderer, &me, &board)?;
+                            send(&mut stream, &ClientMessage::Move(pos))?;
+                        },
+                        Game::InPlay { turn, .. } => {
+                            renderer.write_board(output, &board, last_move)?;
+                            writeln!(output, "Waiting for {} to move...", renderer.player(&turn))?;
+                        },
+                        _ => {
+                            renderer.write_game(output, &game, last_move)?;
+                            let _ = send(&mut stream, &ClientMessage::Bye);
+                            return Ok(game);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Asks for an open square on `board`.
+fn prompt_move(input: &mut dyn Input, output: &mut dyn Write, renderer: &Renderer, me: &Player, board: &Board) -> io::Result<(usize, usize)> {
+    loop {
+        writeln!(output, "{}, please enter move A1 thru C3:", renderer.player(me))?;
+        let line = input.read_line()?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "input closed"))?;
+        match parse_position(&line) {
+            Some(pos) if board.squares[pos.0][pos.1] == Square::Empty => return Ok(pos),
+            Some(pos) => { writeln!(output, "{} is taken, please choose another square.", position_name(pos))?; },
+            None => { writeln!(output, "Please enter Letter (A-C) & Number (1-3) format. ie: A1, C2, etc")?; }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::board::{Board, Square};
+    use crate::game::Game;
+
+    use super::{player_set, ClientMessage, ServerMessage};
+
+    #[test]
+    fn test_client_messages_round_trip() {
+        let messages = [
+            ClientMessage::Hello { version: 1, seat: None },
+            ClientMessage::Hello { version: 3, seat: Some(Square::O) },
+            ClientMessage::Move((2, 0)),
+            ClientMessage::Bye
+        ];
+        for message in messages {
+            assert_eq!(message.to_string().parse::<ClientMessage>().unwrap(), message);
+        }
+        assert_eq!(ClientMessage::Move((2, 0)).to_string(), "MOVE C1");
+        assert!("MOVE D4".parse::<ClientMessage>().is_err());
+        assert!("HELLO chess 1".parse::<ClientMessage>().is_err());
+    }
+
+    #[test]
+    fn test_server_messages_round_trip() {
+        let set = player_set();
+        let board = Board::from_str("X--\n-O-\n---").unwrap();
+        let messages = [
+            ServerMessage::Welcome { version: 1, seat: Square::X },
+            ServerMessage::Waiting(Square::O),
+            ServerMessage::State(Game::InPlay { set, turn: set.x, board }),
+            ServerMessage::State(Game::Win(set.o, board)),
+            ServerMessage::State(Game::Tie(board)),
+            ServerMessage::Error("it is not your turn".to_string())
+        ];
+        for message in messages {
+            assert_eq!(message.to_string().parse::<ServerMessage>().unwrap(), message);
+        }
+        assert_eq!(ServerMessage::State(Game::InPlay { set, turn: set.x, board }).to_string(), "STATE TURN X X--/-O-/---");
+        assert!("STATE TURN X X--/-O-".parse::<ServerMessage>().is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! Nim, the second game the engine in `search` plays: two sides take turns
+//! removing any number of stones from one heap, and whoever takes the last
+//! stone wins.
+//!
+//! It is here to keep `search` and `mcts` honest about knowing nothing of
+//! tic-tac-toe, and because its theory is simple enough to check them
+//! against: the side to move loses exactly when the heaps' sizes XOR to 0.
+
+use crate::search::{Outcome, Position};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Nim {
+    pub heaps: Vec<u32>,
+    /// 0 for the side that moved first, 1 for the other.
+    side: usize
+}
+
+impl Nim {
+    pub fn new(heaps: &[u32]) -> Nim {
+        Nim { heaps: heaps.to_vec(), side: 0 }
+    }
+
+    /// The XOR of the heaps' sizes; 0 means the side to move loses.
+    pub fn nim_sum(&self) -> u32 {
+        self.heaps.iter().fold(0, |sum, &heap| sum ^ heap)
+    }
+}
+
+impl Position for Nim {
+    /// A heap index and how many stones to take from it.
+    type Move = (usize, u32);
+
+    fn moves(&self) -> Vec<(usize, u32)> {
+        self.heaps.iter().enumerate()
+            .flat_map(|(heap, &stones)| (1..=stones).map(move |take| (heap, take)))
+            .collect()
+    }
+
+    fn play(&self, (heap, take): (usize, u32)) -> Nim {
+        let mut after = self.clone();
+        after.heaps[heap] -= take;
+        after.side = 1 - self.side;
+        after
+    }
+
+    fn side_to_move(&self) -> usize {
+        self.side
+    }
+
+    /// Whoever took the last stone, which is the side not to move.
+    fn outcome(&self) -> Option<Outcome> {
+        self.heaps.iter().all(|&heap| heap == 0).then_some(Outcome::Win(1 - self.side))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mcts::{self, Budget};
+    use crate::rng::Rng;
+    use crate::search::{self, Outcome, Position};
+
+    use super::Nim;
+
+    #[test]
+    fn test_rules() {
+        let nim = Nim::new(&[1, 2]);
+        assert_eq!(nim.moves(), vec![(0, 1), (1, 1), (1, 2)]);
+        let after = nim.play((1, 2));
+        assert_eq!((after.heaps.clone(), after.side_to_move()), (vec![1, 0], 1));
+        let over = after.play((0, 1));
+        assert_eq!(over.outcome(), Some(Outcome::Win(1)));
+        assert!(over.moves().is_empty());
+    }
+
+    #[test]
+    fn test_search_matches_theory() {
+        for a in 0..4 {
+            for b in 0..4 {
+                for c in 0..4 {
+                    let nim = Nim::new(&[a, b, c]);
+                    let expected = if nim.nim_sum() == 0 { -1 } else { 1 };
+                    assert_eq!(search::value(&nim), expected, "{:?}", nim.heaps);
+                    if let Some(mv) = search::best_move(&nim) {
+                        if nim.nim_sum() != 0 {
+                            assert_eq!(nim.play(mv).nim_sum(), 0, "{:?}", nim.heaps);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mcts_plays_nim() {
+        // The only winning move takes the third heap down to 1 ^ 2 = 3.
+        let nim = Nim::new(&[1, 2, 5]);
+        let result = mcts::search(&nim, Budget::Iterations(3000), &mut Rng::new(5));
+        assert_eq!(result.best, Some((2, 2)));
+    }
+}
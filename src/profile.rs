@@ -0,0 +1,456 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::game::{Difficulty, Game, Player, PlayerSet, PlayerType};
+
+const FILE_HEADER: &str = "tic-tac-toe-profiles 1";
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Opponent {
+    Human,
+    Computer(Difficulty),
+    Engine
+}
+
+impl Opponent {
+    pub fn of(player: &Player) -> Opponent {
+        match player.player_type() {
+            PlayerType::Human => Opponent::Human,
+            PlayerType::Computer(difficulty) => Opponent::Computer(*difficulty),
+            PlayerType::Engine => Opponent::Engine
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            Self::Human => PlayerType::Human.label(),
+            Self::Computer(difficulty) => PlayerType::Computer(difficulty).label(),
+            Self::Engine => PlayerType::Engine.label()
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Opponent> {
+        match PlayerType::from_label(label)? {
+            PlayerType::Human => Some(Opponent::Human),
+            PlayerType::Computer(difficulty) => Some(Opponent::Computer(difficulty)),
+            PlayerType::Engine => Some(Opponent::Engine)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Win, Loss, Draw
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32
+}
+
+impl Record {
+    fn add(&mut self, other: &Record) {
+        self.wins += other.wins;
+        self.losses += other.losses;
+        self.draws += other.draws;
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Streaks {
+    pub current_wins: u32,
+    pub longest_wins: u32,
+    pub current_unbeaten: u32,
+    pub longest_unbeaten: u32
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub records: BTreeMap<Opponent, Record>,
+    pub streaks: Streaks
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Profile { name: name.to_string(), records: BTreeMap::new(), streaks: Streaks::default() }
+    }
+
+    pub fn total(&self) -> Record {
+        let mut total = Record::default();
+        for record in self.records.values() {
+            total.add(record);
+        }
+        total
+    }
+
+    pub fn record(&mut self, opponent: Opponent, outcome: Outcome) {
+        let record = self.records.entry(opponent).or_default();
+        let streaks = &mut self.streaks;
+
+        match outcome {
+            Outcome::Win => {
+                record.wins += 1;
+                streaks.current_wins += 1;
+                streaks.current_unbeaten += 1;
+            },
+            Outcome::Draw => {
+                record.draws += 1;
+                streaks.current_wins = 0;
+                streaks.current_unbeaten += 1;
+            },
+            Outcome::Loss => {
+                record.losses += 1;
+                streaks.current_wins = 0;
+                streaks.current_unbeaten = 0;
+            }
+        }
+
+        streaks.longest_wins = streaks.longest_wins.max(streaks.current_wins);
+        streaks.longest_unbeaten = streaks.longest_unbeaten.max(streaks.current_unbeaten);
+    }
+}
+
+/// Names of the people behind each side of a game.  `None` means a guest
+/// whose results are not kept.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct PlayerNames {
+    pub x: Option<String>,
+    pub o: Option<String>
+}
+
+#[derive(Debug)]
+pub struct ProfileParseError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for ProfileParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "profile data line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct ProfileStore {
+    pub profiles: BTreeMap<String, Profile>
+}
+
+impl ProfileStore {
+    /// Loads the store at `path`.  A missing file is an empty store.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => ProfileStore::parse(&text)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(ProfileStore::default()),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Saves the store to `path`.  The text goes to a temporary file in the
+    /// same directory first and is renamed over `path`, so a crash part way
+    /// through leaves the old profiles intact.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temporary = dir.join(format!(".{}.{}.tmp", name, process::id()));
+        let written = fs::File::create(&temporary).and_then(|mut file| {
+            file.write_all(self.serialize().as_bytes())?;
+            file.sync_all()
+        });
+        match written.and_then(|()| fs::rename(&temporary, path)) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let _ = fs::remove_file(&temporary);
+                Err(e)
+            }
+        }
+    }
+
+    /// Records a finished game in the store at `path`.  The file is read
+    /// again rather than trusting a copy loaded earlier, so results another
+    /// session saved in the meantime are kept.
+    pub fn record_game_at(path: &Path, set: &PlayerSet, names: &PlayerNames, game: &Game) -> io::Result<()> {
+        let mut store = ProfileStore::load(path)?;
+        store.record_game(set, names, game);
+        store.save(path)
+    }
+
+    pub fn record(&mut self, name: &str, opponent: Opponent, outcome: Outcome) {
+        self.profiles
+            .entry(name.to_string())
+            .or_insert_with(|| Profile::new(name))
+            .record(opponent, outcome);
+    }
+
+    /// Updates the named players' profiles from a finished game.  Games that
+    /// are still in play are ignored.
+    pub fn record_game(&mut self, set: &PlayerSet, names: &PlayerNames, game: &Game) {
+        let sides = [(set.x, names.x.as_deref()), (set.o, names.o.as_deref())];
+
+        for (player, name) in sides {
+            let Some(name) = name else { continue; };
+            let outcome = match game {
+                Game::Win(winner, _) if winner.associated_square() == player.associated_square() => Outcome::Win,
+                Game::Win(..) => Outcome::Loss,
+                Game::Tie(_) => Outcome::Draw,
+                _ => return
+            };
+            let opponent = Opponent::of(&set.opposite_player(&player));
+            self.record(name, opponent, outcome);
+        }
+    }
+
+    /// Profiles ordered for the leaderboard: most wins first, then fewest
+    /// losses, then by name.
+    pub fn leaderboard(&self) -> Vec<&Profile> {
+        let mut profiles: Vec<&Profile> = self.profiles.values().collect();
+        profiles.sort_by(|a, b| {
+            let (ta, tb) = (a.total(), b.total());
+            tb.wins.cmp(&ta.wins)
+                .then(ta.losses.cmp(&tb.losses))
+                .then(a.name.cmp(&b.name))
+        });
+        profiles
+    }
+
+    pub fn format_stats(&self) -> String {
+        if self.profiles.is_empty() {
+            return "No player profiles yet.\n".to_string();
+        }
+
+        let mut out = String::new();
+        for (rank, profile) in self.leaderboard().into_iter().enumerate() {
+            out.push_str(&format!("{}. {}\n", rank + 1, profile.name));
+            for (opponent, record) in &profile.records {
+                out.push_str(&format_record_line(&format!("vs {}", opponent.label()), record));
+            }
+            out.push_str(&format_record_line("total", &profile.total()));
+            out.push_str(&format!(
+                "   longest win streak {}, longest unbeaten streak {}\n",
+                profile.streaks.longest_wins,
+                profile.streaks.longest_unbeaten
+            ));
+        }
+        out
+    }
+
+    fn serialize(&self) -> String {
+        let mut out = format!("{}\n", FILE_HEADER);
+        for profile in self.profiles.values() {
+            let s = &profile.streaks;
+            out.push_str(&format!("profile {}\n", profile.name));
+            out.push_str(&format!(
+                "streaks {} {} {} {}\n",
+                s.current_wins, s.longest_wins, s.current_unbeaten, s.longest_unbeaten
+            ));
+            for (opponent, r) in &profile.records {
+                out.push_str(&format!("record {} {} {} {}\n", opponent.label(), r.wins, r.losses, r.draws));
+            }
+        }
+        out
+    }
+
+    fn parse(text: &str) -> Result<Self, ProfileParseError> {
+        let mut store = ProfileStore::default();
+        let mut current: Option<Profile> = None;
+        let mut lines = text.lines().enumerate();
+
+        match lines.next() {
+            Some((_, header)) if header.trim() == FILE_HEADER => { },
+            _ => { return Err(ProfileParseError { line: 1, message: "unknown file format".to_string() }); }
+        }
+
+        for (index, line) in lines {
+            let err = |message: &str| ProfileParseError { line: index + 1, message: message.to_string() };
+            let line = line.trim();
+            if line.is_empty() { continue; }
+
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match keyword {
+                "profile" => {
+                    if let Some(done) = current.take() {
+                        store.profiles.insert(done.name.clone(), done);
+                    }
+                    if rest.trim().is_empty() { return Err(err("profile without a name")); }
+                    current = Some(Profile::new(rest.trim()));
+                },
+                "streaks" => {
+                    let profile = current.as_mut().ok_or_else(|| err("streaks before profile"))?;
+                    let n = parse_numbers::<4>(rest).ok_or_else(|| err("expected four numbers"))?;
+                    profile.streaks = Streaks {
+                        current_wins: n[0], longest_wins: n[1], current_unbeaten: n[2], longest_unbeaten: n[3]
+                    };
+                },
+                "record" => {
+                    let profile = current.as_mut().ok_or_else(|| err("record before profile"))?;
+                    let (label, counts) = rest.split_once(' ').ok_or_else(|| err("incomplete record"))?;
+                    let opponent = Opponent::from_label(label).ok_or_else(|| err("unknown opponent"))?;
+                    let n = parse_numbers::<3>(counts).ok_or_else(|| err("expected three numbers"))?;
+                    profile.records.insert(opponent, Record { wins: n[0], losses: n[1], draws: n[2] });
+                },
+                _ => { return Err(err("unknown entry")); }
+            }
+        }
+
+        if let Some(done) = current.take() {
+            store.profiles.insert(done.name.clone(), done);
+        }
+        Ok(store)
+    }
+}
+
+fn format_record_line(label: &str, record: &Record) -> String {
+    format!("   {:<22} W {:>3}  L {:>3}  D {:>3}\n", label, record.wins, record.losses, record.draws)
+}
+
+fn parse_numbers<const N: usize>(text: &str) -> Option<[u32; N]> {
+    let mut numbers = [0; N];
+    let mut parts = text.split_whitespace();
+    for number in numbers.iter_mut() {
+        *number = parts.next()?.parse().ok()?;
+    }
+    if parts.next().is_some() { return None; }
+    Some(numbers)
+}
+
+/// `$XDG_CONFIG_HOME/tic-tac-toe/profiles.txt`, falling back to
+/// `~/.config` and, on Windows, `%APPDATA%`.
+pub fn default_path() -> PathBuf {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    config_dir.join("tic-tac-toe").join("profiles.txt")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::game::{Difficulty, Game, Player, PlayerSet, PlayerType};
+
+    use super::{Opponent, Outcome, PlayerNames, ProfileStore};
+
+    fn human_vs_perfect() -> PlayerSet {
+        PlayerSet {
+            x: Player::X(PlayerType::Human),
+            o: Player::O(PlayerType::Computer(Difficulty::Perfect))
+        }
+    }
+
+    #[test]
+    fn test_streaks() {
+        let mut store = ProfileStore::default();
+        let outcomes = [Outcome::Win, Outcome::Win, Outcome::Draw, Outcome::Win, Outcome::Loss, Outcome::Win];
+        for outcome in outcomes {
+            store.record("ann", Opponent::Human, outcome);
+        }
+
+        let profile = &store.profiles["ann"];
+        assert_eq!(profile.streaks.longest_wins, 2);
+        assert_eq!(profile.streaks.longest_unbeaten, 4);
+        assert_eq!(profile.streaks.current_wins, 1);
+        assert_eq!(profile.streaks.current_unbeaten, 1);
+
+        let total = profile.total();
+        assert_eq!((total.wins, total.losses, total.draws), (4, 1, 1));
+    }
+
+    #[test]
+    fn test_record_game() {
+        let set = human_vs_perfect();
+        let names = PlayerNames { x: Some("bob".to_string()), o: None };
+        let board = Board::from_str("XXX\nOO-\n---").unwrap();
+
+        let mut store = ProfileStore::default();
+        store.record_game(&set, &names, &Game::Win(set.x, board));
+        store.record_game(&set, &names, &Game::Win(set.o, board));
+        store.record_game(&set, &names, &Game::Tie(board));
+        store.record_game(&set, &names, &Game::InPlay { set, turn: set.x, board });
+
+        assert_eq!(store.profiles.len(), 1);
+        let record = store.profiles["bob"].records[&Opponent::Computer(Difficulty::Perfect)];
+        assert_eq!((record.wins, record.losses, record.draws), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_sessions_keep_each_others_results() {
+        let dir = env::temp_dir().join(format!("tic-tac-toe-profiles-{}", process::id()));
+        let path = dir.join("profiles.txt");
+        let set = human_vs_perfect();
+        let board = Board::from_str("XXX\nOO-\n---").unwrap();
+
+        // Both sessions started before either finished.
+        let bob = PlayerNames { x: Some("bob".to_string()), o: None };
+        let cat = PlayerNames { x: Some("cat".to_string()), o: None };
+        ProfileStore::record_game_at(&path, &set, &bob, &Game::Win(set.x, board)).unwrap();
+        ProfileStore::record_game_at(&path, &set, &cat, &Game::Tie(board)).unwrap();
+        ProfileStore::record_game_at(&path, &set, &bob, &Game::Win(set.o, board)).unwrap();
+
+        let store = ProfileStore::load(&path).unwrap();
+        let names: Vec<&str> = store.profiles.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["bob", "cat"]);
+        let total = store.profiles["bob"].total();
+        assert_eq!((total.wins, total.losses, total.draws), (1, 1, 0));
+
+        // Nothing but the store itself is left behind.
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(files, vec!["profiles.txt"]);
+
+        // A file that doesn't parse is left alone rather than replaced.
+        fs::write(&path, "garbage").unwrap();
+        assert!(ProfileStore::record_game_at(&path, &set, &bob, &Game::Tie(board)).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "garbage");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut store = ProfileStore::default();
+        store.record("Carol Smith", Opponent::Human, Outcome::Win);
+        store.record("Carol Smith", Opponent::Computer(Difficulty::Easy), Outcome::Loss);
+        store.record("dave", Opponent::Computer(Difficulty::Random), Outcome::Draw);
+
+        let text = store.serialize();
+        let parsed = ProfileStore::parse(&text).unwrap();
+        assert_eq!(parsed, store);
+    }
+
+    #[test]
+    fn test_parse_rejects_garbage() {
+        assert!(ProfileStore::parse("").is_err());
+        assert!(ProfileStore::parse("tic-tac-toe-profiles 1\nrecord human 1 2 3").is_err());
+        assert!(ProfileStore::parse("tic-tac-toe-profiles 1\nprofile a\nrecord robot 1 2 3").is_err());
+        assert!(ProfileStore::parse("tic-tac-toe-profiles 1\nprofile a\nrecord human 1 2").is_err());
+    }
+
+    #[test]
+    fn test_leaderboard_order() {
+        let mut store = ProfileStore::default();
+        store.record("zed", Opponent::Human, Outcome::Win);
+        store.record("zed", Opponent::Human, Outcome::Win);
+        store.record("amy", Opponent::Human, Outcome::Win);
+        store.record("bea", Opponent::Human, Outcome::Win);
+        store.record("bea", Opponent::Human, Outcome::Loss);
+
+        let names: Vec<&str> = store.leaderboard().iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["zed", "amy", "bea"]);
+    }
+}
@@ -0,0 +1,298 @@
+//! The `tic_tac_toe` Python extension module, behind the `python` feature.
+//!
+//! Build it in place and run its tests with the local interpreter:
+//!
+//! ```text
+//! cargo build --release --features python
+//! cp target/release/libtic_tac_toe.so python/tic_tac_toe.so
+//! PYTHONPATH=python python3 -m unittest discover -s python/tests
+//! ```
+//!
+//! or install it into the active environment with `pip install .`, which
+//! builds through maturin.  `cargo test --features python` runs the same
+//! Python tests against a fresh build.
+//!
+//! ```python
+//! import tic_tac_toe as ttt
+//!
+//! board = ttt.Board("X--/-O-/---")
+//! board["B2"]          # 'O'
+//! ttt.analyse(board)   # {'A2': 0, 'A3': 0, ...}, scores for X, to move
+//! ttt.search(board, "5000")  # {'A3': (1804, 0.41), ...}, Monte Carlo
+//!
+//! game = ttt.Game(x="human", o="computer:perfect")
+//! game.play("B2")
+//! game.computer_move() # 'A1'
+//! game.status, game.turn, game.board.rows
+//! ```
+//!
+//! Squares are named `A1` to `C3`, marks are `'X'`, `'O'` and `'-'`, and
+//! scores are 1 for a win, 0 for a draw and -1 for a loss with perfect
+//! play.  Bad squares, taken squares and moves after the game is over
+//! raise `ValueError`.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::board::{parse_position, position_name, Board, Square};
+use crate::game::{self, Game, Player, PlayerSet, PlayerType};
+use crate::mcts::{self, Budget};
+use crate::net::encode_board;
+use crate::profile::PlayerNames;
+use crate::rng::Rng;
+use crate::session::Session;
+
+fn value_error(message: String) -> PyErr {
+    PyValueError::new_err(message)
+}
+
+fn square(name: &str) -> PyResult<(usize, usize)> {
+    parse_position(name).ok_or_else(|| value_error(format!("'{}' is not a square from A1 to C3", name)))
+}
+
+fn mark(square: Square) -> String {
+    square.notation().to_string()
+}
+
+/// The player on `board` whose turn it is, with perfect play assumed for
+/// both sides.
+fn analysis_players(board: &Board) -> PyResult<(Player, PlayerSet)> {
+    let perfect = PlayerType::Computer(game::Difficulty::Perfect);
+    let set = PlayerSet { x: Player::X(perfect), o: Player::O(perfect) };
+    match board.side_to_move() {
+        Some(Square::X) => Ok((set.x, set)),
+        Some(_) => Ok((set.o, set)),
+        None => Err(value_error("X moves first, so it has as many marks as O or one more".to_string()))
+    }
+}
+
+/// A position: rows A, B and C of `X`, `O` and `-`.  Boards never change;
+/// `play` returns a new one.
+#[pyclass(name = "Board", module = "tic_tac_toe", frozen, eq)]
+#[derive(Clone, PartialEq)]
+struct PyBoard {
+    board: Board
+}
+
+#[pymethods]
+impl PyBoard {
+    /// An empty board, or one from rows separated by newlines or `/`.
+    #[new]
+    #[pyo3(signature = (rows = None))]
+    fn new(rows: Option<&str>) -> PyResult<Self> {
+        let board = match rows {
+            None => Board::default(),
+            Some(rows) => Board::from_str(&rows.replace('/', "\n"))
+                .map_err(|_| value_error(format!("'{}' is not three rows of X, O and -", rows)))?
+        };
+        Ok(PyBoard { board })
+    }
+
+    /// The three rows, top to bottom.
+    #[getter]
+    fn rows(&self) -> Vec<String> {
+        self.board.notation().lines().map(str::to_string).collect()
+    }
+
+    fn __getitem__(&self, name: &str) -> PyResult<String> {
+        let (x, y) = square(name)?;
+        Ok(mark(self.board.squares[x][y]))
+    }
+
+    fn __str__(&self) -> String {
+        encode_board(&self.board)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Board('{}')", encode_board(&self.board))
+    }
+
+    fn empty_squares(&self) -> Vec<String> {
+        self.board.get_empty_squares().into_iter().map(position_name).collect()
+    }
+
+    /// `'X'` or `'O'`, or `None` if the marks could not come from a game.
+    fn side_to_move(&self) -> Option<String> {
+        self.board.side_to_move().map(mark)
+    }
+
+    fn winner(&self) -> Option<String> {
+        game::winning_line(&self.board).map(|line| mark(self.board.squares[line[0].0][line[0].1]))
+    }
+
+    fn winning_line(&self) -> Option<Vec<String>> {
+        game::winning_line(&self.board).map(|line| line.into_iter().map(position_name).collect())
+    }
+
+    /// This board with the side to move's mark on `name`.
+    fn play(&self, name: &str) -> PyResult<PyBoard> {
+        let pos = square(name)?;
+        let (turn, set) = analysis_players(&self.board)?;
+        match game::make_move(&pos, &self.board, &turn, &set) {
+            Ok(updated) => Ok(PyBoard { board: *updated.board().unwrap_or(&self.board) }),
+            Err(_) => Err(value_error(format!("{} is already taken", position_name(pos))))
+        }
+    }
+}
+
+/// A game between two players, each a `PlayerType` label: `human`,
+/// `computer:random`, `computer:easy`, `computer:perfect` or
+/// `computer:mcts`.
+#[pyclass(name = "Game", module = "tic_tac_toe")]
+struct PyGame {
+    session: Session
+}
+
+#[pymethods]
+impl PyGame {
+    #[new]
+    #[pyo3(signature = (x = "human", o = "computer:perfect"))]
+    fn new(x: &str, o: &str) -> PyResult<Self> {
+        let player_type = |label: &str| match PlayerType::from_label(label) {
+            Some(PlayerType::Engine) | None => Err(value_error(format!("unknown player type '{}'", label))),
+            Some(player_type) => Ok(player_type)
+        };
+        let set = PlayerSet { x: Player::X(player_type(x)?), o: Player::O(player_type(o)?) };
+        let game = Game::InPlay { set, turn: set.x, board: Board::default() };
+        Ok(PyGame { session: Session::new(set, game, PlayerNames::default()) })
+    }
+
+    /// `'in_play'`, `'won'` or `'tied'`.
+    #[getter]
+    fn status(&self) -> &'static str {
+        match self.session.game {
+            Game::InPlay { .. } | Game::Uninitiated => "in_play",
+            Game::Win(..) => "won",
+            Game::Tie(_) => "tied"
+        }
+    }
+
+    /// `'X'` or `'O'`, or `None` once the game is over.
+    #[getter]
+    fn turn(&self) -> Option<String> {
+        match self.session.game {
+            Game::InPlay { turn, .. } => Some(mark(turn.associated_square())),
+            _ => None
+        }
+    }
+
+    #[getter]
+    fn winner(&self) -> Option<String> {
+        match self.session.game {
+            Game::Win(winner, _) => Some(mark(winner.associated_square())),
+            _ => None
+        }
+    }
+
+    #[getter]
+    fn board(&self) -> PyBoard {
+        PyBoard { board: self.session.game.board().copied().unwrap_or_default() }
+    }
+
+    /// The squares played so far, in order.
+    #[getter]
+    fn moves(&self) -> Vec<String> {
+        self.session.history.iter().copied().map(position_name).collect()
+    }
+
+    /// The player type labels, keyed by `'x'` and `'o'`.
+    #[getter]
+    fn players(&self) -> BTreeMap<&'static str, String> {
+        BTreeMap::from([("x", self.session.set.x.player_type().label()), ("o", self.session.set.o.player_type().label())])
+    }
+
+    /// Plays `name` for the side to move, whoever is playing it.
+    fn play(&mut self, name: &str) -> PyResult<()> {
+        let pos = square(name)?;
+        let (set, turn, board) = self.in_play()?;
+        let updated = game::make_move(&pos, &board, &turn, &set)
+            .map_err(|_| value_error(format!("{} is already taken", position_name(pos))))?;
+        self.session.advance(updated);
+        Ok(())
+    }
+
+    /// Plays the side to move's computer move, perfect for a human, and
+    /// returns the square.
+    fn computer_move(&mut self) -> PyResult<String> {
+        let (set, turn, board) = self.in_play()?;
+        let updated = game::computer_move(&turn, &set, &board).map_err(|e| value_error(e.to_string()))?;
+        self.session.advance(updated);
+        Ok(self.session.last_move().map(position_name).unwrap_or_default())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Game(status='{}', board='{}')", self.status(), encode_board(&self.board().board))
+    }
+}
+
+impl PyGame {
+    fn in_play(&self) -> PyResult<(PlayerSet, Player, Board)> {
+        match self.session.game {
+            Game::InPlay { set, turn, board } => Ok((set, turn, board)),
+            _ => Err(value_error("the game is over".to_string()))
+        }
+    }
+}
+
+/// Reads a square name such as `'b3'` into `(row, column)` from 0 to 2.
+#[pyfunction(name = "parse_position")]
+fn py_parse_position(name: &str) -> PyResult<(usize, usize)> {
+    square(name)
+}
+
+/// The name of `(row, column)`, such as `'B3'`.
+#[pyfunction(name = "position_name")]
+fn py_position_name(pos: (usize, usize)) -> PyResult<String> {
+    if pos.0 > 2 || pos.1 > 2 {
+        return Err(value_error(format!("{:?} is off the board", pos)));
+    }
+    Ok(position_name(pos))
+}
+
+/// The side to move's result on `board` with perfect play.
+#[pyfunction]
+fn evaluate(board: &PyBoard) -> PyResult<i32> {
+    let (turn, set) = analysis_players(&board.board)?;
+    Ok(game::perfect_score(&turn, &set, &board.board))
+}
+
+/// Each empty square's score for the side to move, by square name.
+#[pyfunction]
+fn analyse(board: &PyBoard) -> PyResult<BTreeMap<String, i32>> {
+    let (turn, set) = analysis_players(&board.board)?;
+    if game::winning_line(&board.board).is_some() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(board.board.get_empty_squares().into_iter()
+        .map(|pos| (position_name(pos), game::move_score(&turn, &set, &board.board, pos)))
+        .collect())
+}
+
+/// Monte Carlo tree search from `board` within `budget`, a number of
+/// playouts or a time such as `'100ms'`: each move's visits and the share
+/// of them its side won, by square name.
+#[pyfunction]
+#[pyo3(signature = (board, budget = "2000"))]
+fn search(board: &PyBoard, budget: &str) -> PyResult<BTreeMap<String, (u32, f64)>> {
+    analysis_players(&board.board)?;
+    let budget = Budget::from_label(budget)
+        .ok_or_else(|| value_error(format!("'{}' is not a number of playouts or a time such as 100ms", budget)))?;
+    let result = mcts::search(&board.board, budget, &mut Rng::from_entropy());
+    Ok(result.moves.into_iter().map(|stats| (position_name(stats.mv), (stats.visits, stats.win_rate()))).collect())
+}
+
+/// Tic-tac-toe boards, games and perfect-play and Monte Carlo analysis.
+#[pymodule]
+fn tic_tac_toe(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyBoard>()?;
+    module.add_class::<PyGame>()?;
+    module.add_function(wrap_pyfunction!(py_parse_position, module)?)?;
+    module.add_function(wrap_pyfunction!(py_position_name, module)?)?;
+    module.add_function(wrap_pyfunction!(evaluate, module)?)?;
+    module.add_function(wrap_pyfunction!(analyse, module)?)?;
+    module.add_function(wrap_pyfunction!(search, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,364 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+
+use crate::board::{Board, Square, XPos, YPos};
+use crate::game::{self, Game, Player};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Theme {
+    /// Plain `X`, `O` and `|`/`-` grid lines.  Safe everywhere.
+    Ascii,
+    /// Box-drawing grid with `X` and `O` pieces.
+    Unicode,
+    /// The original ❌ ⭕️ ⬜️ look.
+    Emoji
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Ascii, Theme::Unicode, Theme::Emoji];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ascii => "ascii",
+            Self::Unicode => "unicode",
+            Self::Emoji => "emoji"
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Theme> {
+        Self::ALL.into_iter().find(|theme| theme.label() == label)
+    }
+
+    /// Picks a theme for stdout.  Pipes, CI logs and dumb terminals get
+    /// ASCII; UTF-8 terminals get box drawing, or emoji on terminals known
+    /// to draw them at a consistent width.
+    pub fn detect() -> Theme {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+
+        if !io::stdout().is_terminal() || var("TERM") == "dumb" || env::var_os("CI").is_some() {
+            return Theme::Ascii;
+        }
+
+        let locale = [var("LC_ALL"), var("LC_CTYPE"), var("LANG")]
+            .into_iter()
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        let is_utf8 = locale.contains("UTF-8") || locale.contains("UTF8") || env::var_os("WT_SESSION").is_some();
+        if !is_utf8 {
+            return Theme::Ascii;
+        }
+
+        let emoji_terminals = ["Apple_Terminal", "iTerm.app", "vscode", "WezTerm"];
+        if emoji_terminals.contains(&var("TERM_PROGRAM").as_str()) || env::var_os("WT_SESSION").is_some() {
+            Theme::Emoji
+        } else {
+            Theme::Unicode
+        }
+    }
+}
+
+/// ANSI colours for the pieces and highlights.  Pieces use 256-colour
+/// codes; the last move is underlined and the winning line gets a
+/// background, so highlights never rely on hue alone.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Palette {
+    /// Red X, blue O.
+    Standard,
+    /// Okabe–Ito orange X and blue O, distinguishable with any common
+    /// colour-vision deficiency.
+    OkabeIto,
+    /// Bright yellow X and cyan O, bold, differing mostly in lightness.
+    HighContrast
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Standard, Palette::OkabeIto, Palette::HighContrast];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::OkabeIto => "okabe-ito",
+            Self::HighContrast => "high-contrast"
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Palette> {
+        Self::ALL.into_iter().find(|palette| palette.label() == label)
+    }
+
+    fn piece(self, square: Square) -> &'static str {
+        match (self, square) {
+            (_, Square::Empty) => "",
+            (Self::Standard, Square::X) => "38;5;196",
+            (Self::Standard, Square::O) => "38;5;33",
+            (Self::OkabeIto, Square::X) => "38;5;214",
+            (Self::OkabeIto, Square::O) => "38;5;25",
+            (Self::HighContrast, Square::X) => "1;38;5;226",
+            (Self::HighContrast, Square::O) => "1;38;5;51"
+        }
+    }
+
+    fn winning_background(self) -> &'static str {
+        match self {
+            Self::Standard => "48;5;22",
+            Self::OkabeIto | Self::HighContrast => "48;5;240"
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorChoice {
+    Auto, Always, Never
+}
+
+impl ColorChoice {
+    /// Whether stdout should get colour.  `Auto` honours `NO_COLOR`
+    /// (<https://no-color.org>) and turns colour off for pipes and dumb
+    /// terminals.
+    pub fn enabled(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                let dumb = env::var("TERM").is_ok_and(|term| term == "dumb");
+                !no_color && !dumb && io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// Draws boards and game results in one theme to any `io::Write`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Renderer {
+    pub theme: Theme,
+    /// `None` draws without any escape codes.
+    pub palette: Option<Palette>
+}
+
+impl Renderer {
+    pub fn new(theme: Theme) -> Self {
+        Renderer { theme, palette: None }
+    }
+
+    pub fn with_palette(self, palette: Option<Palette>) -> Self {
+        Renderer { palette, ..self }
+    }
+
+    pub fn square(&self, square: Square) -> &'static str {
+        match (self.theme, square) {
+            (Theme::Emoji, square) => square.to_string(),
+            (_, Square::X) => "X",
+            (_, Square::O) => "O",
+            (Theme::Ascii, Square::Empty) | (Theme::Unicode, Square::Empty) => " "
+        }
+    }
+
+    /// How a player is named in prompts and results.
+    pub fn player(&self, player: &Player) -> String {
+        let square = player.associated_square();
+        self.paint(self.square(square), square, false, false)
+    }
+
+    /// Writes the board.  With a palette, `last_move` is highlighted and so
+    /// is the winning line, if the board has one.
+    pub fn write_board(&self, out: &mut dyn Write, board: &Board, last_move: Option<(usize, usize)>) -> io::Result<()> {
+        let highlights = Highlights { last_move, line: game::winning_line(board) };
+        match self.theme {
+            Theme::Emoji => self.write_emoji_board(out, board, &highlights),
+            Theme::Ascii => self.write_grid_board(out, board, &highlights, &ASCII_GRID),
+            Theme::Unicode => self.write_grid_board(out, board, &highlights, &UNICODE_GRID)
+        }
+    }
+
+    /// Writes the board, headed by the result once the game is over.
+    pub fn write_game(&self, out: &mut dyn Write, game: &Game, last_move: Option<(usize, usize)>) -> io::Result<()> {
+        match game {
+            Game::Uninitiated => writeln!(out, "uninitiated"),
+            Game::Tie(board) => {
+                writeln!(out, "Game is tied")?;
+                self.write_board(out, board, last_move)
+            },
+            Game::Win(winner, board) => {
+                writeln!(out, "{} Wins!", self.player(winner))?;
+                self.write_board(out, board, last_move)
+            },
+            Game::InPlay { board, .. } => self.write_board(out, board, last_move)
+        }
+    }
+
+    /// Wraps `text` in the escape codes for `square`, underlined when it is
+    /// the last move and on the winning background when it is in the line.
+    fn paint(&self, text: &str, square: Square, is_last: bool, in_line: bool) -> String {
+        let Some(palette) = self.palette else { return text.to_string(); };
+
+        let mut codes: Vec<&str> = Vec::new();
+        if in_line { codes.push("1"); codes.push(palette.winning_background()); }
+        if is_last { codes.push("4"); }
+        if !palette.piece(square).is_empty() { codes.push(palette.piece(square)); }
+
+        if codes.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+    }
+
+    fn cell(&self, board: &Board, pos: (usize, usize), highlights: &Highlights, padding: &str) -> String {
+        let square = board.squares[pos.0][pos.1];
+        let text = format!("{}{}{}", padding, self.square(square), padding);
+        let is_last = highlights.last_move == Some(pos);
+        let in_line = highlights.line.is_some_and(|line| line.contains(&pos));
+        self.paint(&text, square, is_last, in_line)
+    }
+
+    fn write_emoji_board(&self, out: &mut dyn Write, board: &Board, highlights: &Highlights) -> io::Result<()> {
+        for x in XPos::A ..= XPos::C {
+            let squares = (YPos::_1 ..= YPos::_3)
+                .map(|y| self.cell(board, (x, y), highlights, ""))
+                .collect::<String>();
+            writeln!(out, "{} {}", XPos::letter_from(x), squares)?;
+        }
+
+        writeln!(out, "  1️⃣ 2️⃣ 3️⃣")
+    }
+
+    fn write_grid_board(&self, out: &mut dyn Write, board: &Board, highlights: &Highlights, grid: &Grid) -> io::Result<()> {
+        writeln!(out, "    1   2   3")?;
+        if let Some(top) = grid.top {
+            writeln!(out, "  {}", top)?;
+        }
+
+        for x in XPos::A ..= XPos::C {
+            let cells = (YPos::_1 ..= YPos::_3)
+                .map(|y| self.cell(board, (x, y), highlights, " "))
+                .collect::<Vec<String>>()
+                .join(grid.column);
+            let line = format!("{} {}{}{}", XPos::letter_from(x), grid.edge, cells, grid.edge);
+            writeln!(out, "{}", line.trim_end())?;
+
+            if x != XPos::C {
+                writeln!(out, "  {}", grid.between)?;
+            }
+        }
+
+        if let Some(bottom) = grid.bottom {
+            writeln!(out, "  {}", bottom)?;
+        }
+        Ok(())
+    }
+}
+
+struct Highlights {
+    last_move: Option<(usize, usize)>,
+    line: Option<[(usize, usize); 3]>
+}
+
+struct Grid {
+    top: Option<&'static str>,
+    between: &'static str,
+    bottom: Option<&'static str>,
+    column: &'static str,
+    edge: &'static str
+}
+
+const ASCII_GRID: Grid = Grid {
+    top: None,
+    between: " ---+---+---",
+    bottom: None,
+    column: "|",
+    edge: " "
+};
+
+const UNICODE_GRID: Grid = Grid {
+    top: Some("┌───┬───┬───┐"),
+    between: "├───┼───┼───┤",
+    bottom: Some("└───┴───┴───┘"),
+    column: "│",
+    edge: "│"
+};
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::game::{Game, Player, PlayerType};
+
+    use super::{Palette, Renderer, Theme};
+
+    fn render(theme: Theme, game: &Game) -> String {
+        let mut out = Vec::new();
+        Renderer::new(theme).write_game(&mut out, game, Some((1, 1))).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn board() -> Board {
+        Board::from_str("X-O\n-X-\nO--").unwrap()
+    }
+
+    #[test]
+    fn test_ascii_board() {
+        let expected = "    1   2   3\n\
+                        A   X |   | O\n   ---+---+---\n\
+                        B     | X |\n   ---+---+---\n\
+                        C   O |   |\n";
+        assert_eq!(render(Theme::Ascii, &Game::Tie(board())), format!("Game is tied\n{}", expected));
+    }
+
+    #[test]
+    fn test_unicode_board() {
+        let expected = "    1   2   3\n  ┌───┬───┬───┐\n\
+                        A │ X │   │ O │\n  ├───┼───┼───┤\n\
+                        B │   │ X │   │\n  ├───┼───┼───┤\n\
+                        C │ O │   │   │\n  └───┴───┴───┘\n";
+        let winner = Player::X(PlayerType::Human);
+        assert_eq!(render(Theme::Unicode, &Game::Win(winner, board())), format!("X Wins!\n{}", expected));
+    }
+
+    #[test]
+    fn test_emoji_board() {
+        let expected = "A ❌⬜️⭕️\nB ⬜️❌⬜️\nC ⭕️⬜️⬜️\n  1️⃣ 2️⃣ 3️⃣\n";
+        let winner = Player::O(PlayerType::Human);
+        assert_eq!(render(Theme::Emoji, &Game::Win(winner, board())), format!("⭕️ Wins!\n{}", expected));
+    }
+
+    #[test]
+    fn test_colour_highlights() {
+        let renderer = Renderer::new(Theme::Ascii).with_palette(Some(Palette::Standard));
+        let board = Board::from_str("XXX\nOO-\n---").unwrap();
+        let mut out = Vec::new();
+        renderer.write_game(&mut out, &Game::Win(Player::X(PlayerType::Human), board), Some((0, 2))).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "\x1b[38;5;196mX\x1b[0m Wins!");
+        assert_eq!(
+            lines[2],
+            "A  \x1b[1;48;5;22;38;5;196m X \x1b[0m|\x1b[1;48;5;22;38;5;196m X \x1b[0m|\x1b[1;48;5;22;4;38;5;196m X \x1b[0m"
+        );
+        assert_eq!(lines[4], "B  \x1b[38;5;33m O \x1b[0m|\x1b[38;5;33m O \x1b[0m|");
+    }
+
+    #[test]
+    fn test_no_palette_has_no_escapes() {
+        let board = Board::from_str("XXX\nOO-\n---").unwrap();
+        for theme in Theme::ALL {
+            let mut out = Vec::new();
+            Renderer::new(theme).write_board(&mut out, &board, Some((0, 2))).unwrap();
+            assert!(!out.contains(&0x1b));
+        }
+    }
+
+    #[test]
+    fn test_theme_labels() {
+        for theme in Theme::ALL {
+            assert_eq!(Theme::from_label(theme.label()), Some(theme));
+        }
+        assert_eq!(Theme::from_label("auto"), None);
+
+        for palette in Palette::ALL {
+            assert_eq!(Palette::from_label(palette.label()), Some(palette));
+        }
+    }
+}
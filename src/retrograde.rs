@@ -0,0 +1,430 @@
+//! Retrograde analysis of m,n,k games, for `tic-tac-toe retrograde`.
+//!
+//! Instead of searching forward from the empty board, the solver values
+//! every arrangement of pieces, a layer at a time from the full board back
+//! to the empty one: a position is worth the best of its moves, each worth
+//! what the position after it is to the other side, and those are all in
+//! the layer already done.  Only two layers are ever held, one byte a
+//! position, so memory is bounded by the largest neighbouring pair, and
+//! each layer is split across threads.
+//!
+//! A layer of `p` pieces has X holding `⌈p/2⌉` of them.  Its positions are
+//! numbered by the colex rank of the occupied squares among all sets of
+//! `p`, times the number of ways to pick X's squares among them, plus the
+//! colex rank of X's squares within the occupied ones.
+//!
+//! The tablebase file is little-endian binary, written as the layers are
+//! solved, full board first:
+//!
+//! ```text
+//! "TTTR"  1 (u8 version)  rows (u8)  cols (u8)  k (u8)
+//! then per layer:  pieces (u8)  positions (u32)  values, four to a byte
+//! ```
+//!
+//! Each value takes two bits, low bits first: 0 for a loss for the side to
+//! move, 1 for a draw and 2 for a win.  Arrangements no game reaches, such
+//! as both sides having a line, are valued all the same and never read.
+
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::mnk::{MnkBoard, Variant, MAX_SQUARES};
+use crate::profile;
+
+const MAGIC: &[u8; 4] = b"TTTR";
+const VERSION: u8 = 1;
+
+/// How much memory the solver may use unless told otherwise.
+pub const DEFAULT_MEMORY: usize = 1 << 30;
+
+/// `binomial[n][r]` is n choose r.
+struct Binomials([[u64; MAX_SQUARES + 1]; MAX_SQUARES + 1]);
+
+impl Binomials {
+    fn new() -> Binomials {
+        let mut table = [[0; MAX_SQUARES + 1]; MAX_SQUARES + 1];
+        for n in 0..=MAX_SQUARES {
+            table[n][0] = 1;
+            for r in 1..=n {
+                table[n][r] = table[n - 1][r - 1] + table[n - 1][r];
+            }
+        }
+        Binomials(table)
+    }
+
+    fn choose(&self, n: usize, r: usize) -> u64 {
+        if r > n { 0 } else { self.0[n][r] }
+    }
+
+    /// Where the set `bits` falls among all sets of its size, in colex
+    /// order.
+    fn rank(&self, bits: u32) -> u64 {
+        let mut rank = 0;
+        let mut remaining = bits;
+        let mut taken = 0;
+        while remaining != 0 {
+            let square = remaining.trailing_zeros() as usize;
+            taken += 1;
+            rank += self.choose(square, taken);
+            remaining &= remaining - 1;
+        }
+        rank
+    }
+
+    /// The set of `size` squares with colex rank `rank`.
+    fn unrank(&self, mut rank: u64, size: usize) -> u32 {
+        let mut bits = 0;
+        for taken in (1..=size).rev() {
+            let mut square = taken - 1;
+            while self.choose(square + 1, taken) <= rank {
+                square += 1;
+            }
+            rank -= self.choose(square, taken);
+            bits |= 1 << square;
+        }
+        bits
+    }
+}
+
+/// Squares in `within`, in order, picked out by the low bits of `bits`.
+fn spread(bits: u32, within: u32) -> u32 {
+    let mut spread = 0;
+    let mut remaining = within;
+    let mut index = 0;
+    while remaining != 0 {
+        let square = remaining & remaining.wrapping_neg();
+        if bits & (1 << index) != 0 {
+            spread |= square;
+        }
+        remaining &= remaining - 1;
+        index += 1;
+    }
+    spread
+}
+
+/// The reverse of `spread`: which of `within`'s squares `bits` holds.
+fn pack(bits: u32, within: u32) -> u32 {
+    let mut packed = 0;
+    let mut remaining = within;
+    let mut index = 0;
+    while remaining != 0 {
+        let square = remaining & remaining.wrapping_neg();
+        if bits & square != 0 {
+            packed |= 1 << index;
+        }
+        remaining &= remaining - 1;
+        index += 1;
+    }
+    packed
+}
+
+struct Layers {
+    variant: Variant,
+    binomials: Binomials,
+    lines: Vec<u32>,
+    full: u32
+}
+
+impl Layers {
+    fn new(variant: Variant) -> Layers {
+        Layers { variant, binomials: Binomials::new(), lines: variant.lines(), full: MnkBoard::new(variant).full() }
+    }
+
+    fn size(&self, pieces: usize) -> u64 {
+        self.binomials.choose(self.variant.squares(), pieces) * self.binomials.choose(pieces, pieces.div_ceil(2))
+    }
+
+    fn index(&self, x: u32, o: u32) -> u64 {
+        let occupied = x | o;
+        let pieces = occupied.count_ones() as usize;
+        self.binomials.rank(occupied) * self.binomials.choose(pieces, pieces.div_ceil(2)) + self.binomials.rank(pack(x, occupied))
+    }
+
+    fn position(&self, pieces: usize, index: u64) -> (u32, u32) {
+        let xs = pieces.div_ceil(2);
+        let ways = self.binomials.choose(pieces, xs);
+        let occupied = self.binomials.unrank(index / ways, pieces);
+        let x = spread(self.binomials.unrank(index % ways, xs), occupied);
+        (x, occupied & !x)
+    }
+
+    fn has_line(&self, side: u32) -> bool {
+        self.lines.iter().any(|&line| line & !side == 0)
+    }
+
+    /// The value to the side to move, given the values of the layer after.
+    fn value(&self, pieces: usize, x: u32, o: u32, next: &[i8]) -> i8 {
+        let x_to_move = pieces.is_multiple_of(2);
+        let (mover, last) = if x_to_move { (x, o) } else { (o, x) };
+        if self.has_line(last) {
+            return -1;
+        }
+        if self.has_line(mover) {
+            return 1;
+        }
+        if pieces == self.variant.squares() {
+            return 0;
+        }
+        let mut best = -1;
+        let mut empty = !(x | o) & self.full;
+        while empty != 0 {
+            let square = empty & empty.wrapping_neg();
+            let after = if x_to_move { self.index(x | square, o) } else { self.index(x, o | square) };
+            best = best.max(-next[after as usize]);
+            if best == 1 {
+                break;
+            }
+            empty &= empty - 1;
+        }
+        best
+    }
+}
+
+/// What solving a variant found.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Report {
+    pub variant: Variant,
+    /// The empty board's value to the first player: 1, 0 or -1.
+    pub value: i8,
+    pub positions: u64,
+    /// The most memory the layers held at once.
+    pub peak_bytes: usize,
+    pub threads: usize,
+    pub elapsed: Duration
+}
+
+impl Report {
+    pub fn result_label(&self) -> &'static str {
+        match self.value {
+            1 => "the first player wins",
+            -1 => "the first player loses",
+            _ => "draw"
+        }
+    }
+
+    pub fn format_report(&self) -> String {
+        let mut out = format!("{} ({} in a row on {} by {})\n", self.variant, self.variant.k, self.variant.rows, self.variant.cols);
+        let _ = writeln!(out, "   with perfect play    {}", self.result_label());
+        let _ = writeln!(out, "   positions            {}", self.positions);
+        let _ = writeln!(out, "   peak memory          {:.1} MiB", self.peak_bytes as f64 / (1024.0 * 1024.0));
+        let _ = writeln!(out, "   threads              {}", self.threads);
+        let _ = writeln!(out, "   time                 {:.2}s", self.elapsed.as_secs_f64());
+        out
+    }
+}
+
+/// The memory the solver needs for `variant`: its largest pair of
+/// neighbouring layers, and the smaller one packed for writing out.
+pub fn memory_needed(variant: Variant) -> usize {
+    let layers = Layers::new(variant);
+    (0..variant.squares())
+        .map(|pieces| (layers.size(pieces) + layers.size(pieces + 1) + layers.size(pieces).div_ceil(4)) as usize)
+        .max()
+        .unwrap_or(1)
+}
+
+/// Solves `variant` on `threads` threads, writing the tablebase to `out`.
+/// Fails without starting if it would need more than `max_memory` bytes,
+/// or if a layer has more positions than the file format can count.
+pub fn solve(variant: Variant, threads: usize, max_memory: usize, out: &mut dyn Write) -> io::Result<Report> {
+    let layers = Layers::new(variant);
+    let mut sizes = Vec::with_capacity(variant.squares() + 1);
+    for pieces in 0..=variant.squares() {
+        let size = u32::try_from(layers.size(pieces)).map_err(|_| io::Error::other(format!(
+            "{} has {} positions with {} pieces, too many for a tablebase file", variant, layers.size(pieces), pieces)))?;
+        sizes.push(size);
+    }
+    let needed = memory_needed(variant);
+    if needed > max_memory {
+        return Err(io::Error::other(format!(
+            "{} needs {} MiB, more than the {} MiB allowed", variant, needed >> 20, max_memory >> 20)));
+    }
+
+    let started = Instant::now();
+    out.write_all(MAGIC)?;
+    out.write_all(&[VERSION, variant.rows as u8, variant.cols as u8, variant.k as u8])?;
+
+    let threads = threads.max(1);
+    let mut next: Vec<i8> = Vec::new();
+    let mut positions = 0;
+    for pieces in (0..=variant.squares()).rev() {
+        let size = sizes[pieces] as usize;
+        let chunk = size.div_ceil(threads).max(1);
+        let mut values = vec![0i8; size];
+        thread::scope(|scope| {
+            for (number, part) in values.chunks_mut(chunk).enumerate() {
+                let (layers, next) = (&layers, &next);
+                scope.spawn(move || {
+                    for (offset, value) in part.iter_mut().enumerate() {
+                        let (x, o) = layers.position(pieces, (number * chunk + offset) as u64);
+                        *value = layers.value(pieces, x, o, next);
+                    }
+                });
+            }
+        });
+
+        out.write_all(&[pieces as u8])?;
+        out.write_all(&sizes[pieces].to_le_bytes())?;
+        out.write_all(&pack_values(&values))?;
+        positions += size as u64;
+        next = values;
+    }
+    out.flush()?;
+
+    Ok(Report { variant, value: next[0], positions, peak_bytes: needed, threads, elapsed: started.elapsed() })
+}
+
+fn pack_values(values: &[i8]) -> Vec<u8> {
+    values.chunks(4)
+        .map(|four| four.iter().enumerate().fold(0u8, |byte, (slot, &value)| byte | ((value + 1) as u8) << (slot * 2)))
+        .collect()
+}
+
+/// `tablebase-<variant>.bin` next to the profiles.
+pub fn default_path(variant: Variant) -> PathBuf {
+    profile::default_path().with_file_name(format!("tablebase-{}.bin", variant))
+}
+
+/// Solves `variant` into the file at `path`.
+pub fn solve_to_file(variant: Variant, threads: usize, max_memory: usize, path: &Path) -> io::Result<Report> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    solve(variant, threads, max_memory, &mut BufWriter::new(File::create(path)?))
+}
+
+/// A tablebase read back, every layer in memory.
+pub struct Tablebase {
+    layers: Layers,
+    /// Packed values by number of pieces.
+    values: Vec<Vec<u8>>
+}
+
+impl Tablebase {
+    pub fn read(input: &mut dyn Read) -> io::Result<Tablebase> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut header = [0; 8];
+        input.read_exact(&mut header).map_err(|_| invalid("not a retrograde tablebase"))?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid("not a retrograde tablebase, or an unknown version"));
+        }
+        let variant = Variant::new(header[5] as usize, header[6] as usize, header[7] as usize).ok_or_else(|| invalid("bad variant"))?;
+        let layers = Layers::new(variant);
+        let mut values = vec![Vec::new(); variant.squares() + 1];
+        for _ in 0..=variant.squares() {
+            let mut layer = [0; 5];
+            input.read_exact(&mut layer)?;
+            let pieces = layer[0] as usize;
+            let size = u32::from_le_bytes([layer[1], layer[2], layer[3], layer[4]]) as u64;
+            if pieces > variant.squares() || size != layers.size(pieces) || !values[pieces].is_empty() {
+                return Err(invalid("bad layer"));
+            }
+            let mut packed = vec![0; size.div_ceil(4) as usize];
+            input.read_exact(&mut packed)?;
+            values[pieces] = packed;
+        }
+        Ok(Tablebase { layers, values })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Tablebase> {
+        Tablebase::read(&mut io::BufReader::new(File::open(path)?))
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.layers.variant
+    }
+
+    /// `board`'s value to the side to move, or `None` if it is another
+    /// variant or has the wrong number of pieces for either side.
+    pub fn value(&self, board: &MnkBoard) -> Option<i8> {
+        let pieces = (board.x | board.o).count_ones() as usize;
+        if board.variant != self.variant() || board.x.count_ones() as usize != pieces.div_ceil(2) || board.x & board.o != 0 {
+            return None;
+        }
+        let index = self.layers.index(board.x, board.o) as usize;
+        let byte = self.values[pieces][index / 4];
+        Some(((byte >> (index % 4 * 2)) & 3) as i8 - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::search::Position;
+    use crate::mnk::{MnkBoard, Variant};
+    use crate::solve as forward;
+
+    use super::{memory_needed, pack, solve, spread, Binomials, Layers, Tablebase, DEFAULT_MEMORY};
+
+    #[test]
+    fn test_ranking() {
+        let binomials = Binomials::new();
+        for (size, count) in [(0, 1), (2, 66), (5, 792)] {
+            for rank in 0..count {
+                let bits = binomials.unrank(rank, size);
+                assert_eq!(bits.count_ones() as usize, size);
+                assert!(bits < 1 << 12);
+                assert_eq!(binomials.rank(bits), rank);
+            }
+        }
+        assert_eq!(pack(spread(0b101, 0b1011_0000), 0b1011_0000), 0b101);
+
+        let layers = Layers::new(Variant::new(3, 4, 3).unwrap());
+        for index in 0..layers.size(5) {
+            let (x, o) = layers.position(5, index);
+            assert_eq!((x.count_ones(), o.count_ones()), (3, 2));
+            assert_eq!(layers.index(x, o), index);
+        }
+    }
+
+    #[test]
+    fn test_classic_is_a_draw() {
+        let mut file = Vec::new();
+        let report = solve(Variant::CLASSIC, 3, DEFAULT_MEMORY, &mut file).unwrap();
+        assert_eq!(report.value, 0);
+        assert_eq!(report.result_label(), "draw");
+
+        // Every reachable position agrees with the forward solver.
+        let table = Tablebase::read(&mut Cursor::new(file)).unwrap();
+        let solved = forward::solve().tablebase;
+        for index in 0..19683 {
+            let board = forward::board_from_index(index);
+            if let Some(entry) = solved.entry(&board) {
+                assert_eq!(table.value(&MnkBoard::from_board(&board)), Some(entry.value), "{}", board.notation());
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_variants() {
+        let value = |label: &str| solve(Variant::from_label(label).unwrap(), 2, DEFAULT_MEMORY, &mut Vec::new()).unwrap().value;
+        assert_eq!(value("3x4"), 1);
+        assert_eq!(value("3x3k2"), 1);
+        assert_eq!(value("2x3k3"), 0);
+        assert_eq!(value("3x4k4"), 0);
+
+        // Threads don't change the file.
+        let variant = Variant::from_label("3x4").unwrap();
+        let (mut one, mut four) = (Vec::new(), Vec::new());
+        solve(variant, 1, DEFAULT_MEMORY, &mut one).unwrap();
+        solve(variant, 4, DEFAULT_MEMORY, &mut four).unwrap();
+        assert_eq!(one, four);
+
+        let table = Tablebase::read(&mut Cursor::new(one)).unwrap();
+        let start = MnkBoard::new(variant);
+        assert_eq!(table.value(&start), Some(1));
+        assert_eq!(table.value(&start.play(0).play(1)), Some(1));
+        assert_eq!(table.value(&MnkBoard::new(Variant::CLASSIC)), None);
+
+        assert!(memory_needed(Variant::new(4, 4, 4).unwrap()) < 5 << 20);
+        assert!(solve(Variant::new(4, 4, 4).unwrap(), 1, 1 << 20, &mut Vec::new()).is_err());
+        // 5x5's middle layers don't fit the file's 32-bit counts.
+        let error = solve(Variant::new(5, 5, 4).unwrap(), 1, usize::MAX, &mut Vec::new()).unwrap_err();
+        assert!(error.to_string().contains("too many"), "{}", error);
+    }
+}
@@ -0,0 +1,77 @@
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Small xorshift64* generator.  Good enough for picking moves; not for
+/// anything that needs real randomness.
+#[derive(Clone, Debug)]
+pub struct Rng { 
+    state: u64
+}
+
+impl Rng { 
+    pub fn new(seed: u64) -> Self { 
+        // xorshift gets stuck on zero, so nudge it off.
+        Rng { state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } }
+    }
+
+    pub fn from_entropy() -> Self { 
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        let local = 0u8;
+        let addr = &local as *const u8 as u64;
+        Rng::new(nanos ^ addr.rotate_left(32))
+    }
+
+    pub fn next_u64(&mut self) -> u64 { 
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform-ish value in `0..bound`.  `bound` must be non-zero.
+    pub fn below(&mut self, bound: usize) -> usize { 
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+thread_local! { 
+    static THREAD_STATE: Cell<u64> = Cell::new(Rng::from_entropy().next_u64());
+}
+
+/// Draws from a per-thread generator seeded from the clock.
+pub fn below(bound: usize) -> usize { 
+    THREAD_STATE.with(|state| { 
+        let mut rng = Rng::new(state.get());
+        let value = rng.below(bound);
+        state.set(rng.state);
+        value
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn test_same_seed_same_sequence() { 
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..16 { 
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_below_stays_in_range() { 
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 { 
+            assert!(rng.below(9) < 9);
+        }
+        assert!(super::below(3) < 3);
+    }
+}
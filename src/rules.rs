@@ -0,0 +1,182 @@
+//! The `rules` difficulty: Newell and Simon's tic-tac-toe program, which
+//! searches nothing and plays the first of these rules that applies:
+//!
+//! 1. win: complete a line;
+//! 2. block: stop the opponent completing one;
+//! 3. fork: make two threats at once;
+//! 4. block fork: stop the opponent forking, by threatening somewhere that
+//!    the forced reply doesn't fork, or else by taking their fork square;
+//! 5. centre;
+//! 6. opposite corner: the corner opposite one the opponent holds;
+//! 7. empty corner;
+//! 8. empty side.
+//!
+//! Followed in that order the rules never lose, and every move comes with
+//! the rule behind it, so the game can say why it played there.
+
+use crate::board::{position_name, Board, Square};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rule {
+    Win, Block, Fork, BlockFork, Centre, OppositeCorner, EmptyCorner, EmptySide
+}
+
+impl Rule {
+    pub const ALL: [Rule; 8] = [
+        Rule::Win, Rule::Block, Rule::Fork, Rule::BlockFork,
+        Rule::Centre, Rule::OppositeCorner, Rule::EmptyCorner, Rule::EmptySide
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Win => "win",
+            Rule::Block => "block",
+            Rule::Fork => "fork",
+            Rule::BlockFork => "block fork",
+            Rule::Centre => "centre",
+            Rule::OppositeCorner => "opposite corner",
+            Rule::EmptyCorner => "empty corner",
+            Rule::EmptySide => "empty side"
+        }
+    }
+}
+
+/// A move and the rule that picked it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Choice {
+    pub rule: Rule,
+    pub pos: (usize, usize)
+}
+
+impl Choice {
+    /// Why the move was played, to the opponent: `blocking your fork at B3`.
+    pub fn explain(&self) -> String {
+        let square = position_name(self.pos);
+        match self.rule {
+            Rule::Win => format!("winning at {}", square),
+            Rule::Block => format!("blocking your line at {}", square),
+            Rule::Fork => format!("forking at {}", square),
+            Rule::BlockFork => format!("blocking your fork at {}", square),
+            Rule::Centre => format!("taking the centre at {}", square),
+            Rule::OppositeCorner => format!("taking the corner opposite yours at {}", square),
+            Rule::EmptyCorner => format!("taking a corner at {}", square),
+            Rule::EmptySide => format!("taking a side at {}", square)
+        }
+    }
+}
+
+const CENTRE: (usize, usize) = (1, 1);
+const CORNERS: [(usize, usize); 4] = [(0, 0), (0, 2), (2, 0), (2, 2)];
+const SIDES: [(usize, usize); 4] = [(0, 1), (1, 0), (1, 2), (2, 1)];
+
+/// The move for the side to move on `board`, or `None` once the game is
+/// over or the board could not come from a real game.
+pub fn choose(board: &Board) -> Option<Choice> {
+    let me = board.side_to_move()?;
+    let them = if me == Square::X { Square::O } else { Square::X };
+    if crate::game::winning_line(board).is_some() || board.get_empty_squares().is_empty() {
+        return None;
+    }
+    let empty = |pos: &(usize, usize)| board.squares[pos.0][pos.1] == Square::Empty;
+
+    let (rule, pos) = if let Some(&pos) = board.threats(me).first() {
+        (Rule::Win, pos)
+    } else if let Some(&pos) = board.threats(them).first() {
+        (Rule::Block, pos)
+    } else if let Some(&pos) = board.forks(me).first() {
+        (Rule::Fork, pos)
+    } else if let Some(pos) = block_fork(board, me, them) {
+        (Rule::BlockFork, pos)
+    } else if empty(&CENTRE) {
+        (Rule::Centre, CENTRE)
+    } else if let Some(&pos) = CORNERS.iter().find(|&&(x, y)| empty(&(x, y)) && board.squares[2 - x][2 - y] == them) {
+        (Rule::OppositeCorner, pos)
+    } else if let Some(&pos) = CORNERS.iter().find(|pos| empty(pos)) {
+        (Rule::EmptyCorner, pos)
+    } else {
+        (Rule::EmptySide, *SIDES.iter().find(|pos| empty(pos))?)
+    };
+    Some(Choice { rule, pos })
+}
+
+/// With one fork square, takes it.  With more, makes a threat whose forced
+/// block is no fork for `them`, preferring one that takes a fork square.
+fn block_fork(board: &Board, me: Square, them: Square) -> Option<(usize, usize)> {
+    let forks = board.forks(them);
+    if forks.len() <= 1 {
+        return forks.first().copied();
+    }
+    let forcing = |pos: &(usize, usize)| {
+        let mut after = *board;
+        after.squares[pos.0][pos.1] = me;
+        let forks = after.forks(them);
+        after.threats(me).iter().all(|block| !forks.contains(block)) && !after.threats(me).is_empty()
+    };
+    let empty = board.get_empty_squares();
+    empty.iter().find(|pos| forks.contains(pos) && forcing(pos))
+        .or_else(|| empty.iter().find(|pos| forcing(pos)))
+        .or(forks.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::board::{parse_position, position_name, Board};
+    use crate::search::{Outcome, Position};
+
+    use super::{choose, Rule};
+
+    fn rule_at(board: &str) -> (Rule, String) {
+        let choice = choose(&Board::from_str(board).unwrap()).unwrap();
+        (choice.rule, position_name(choice.pos))
+    }
+
+    #[test]
+    fn test_rule_order() {
+        assert_eq!(rule_at("XX-\nOO-\n---"), (Rule::Win, "A3".to_string()));
+        assert_eq!(rule_at("XX-\n-O-\n---"), (Rule::Block, "A3".to_string()));
+        assert_eq!(rule_at("XO-\n-X-\n--O"), (Rule::Fork, "B1".to_string()));
+        assert_eq!(rule_at("---\n---\n---"), (Rule::Centre, "B2".to_string()));
+        assert_eq!(rule_at("X--\n---\n---"), (Rule::Centre, "B2".to_string()));
+        assert_eq!(rule_at("O--\n-X-\n---"), (Rule::OppositeCorner, "C3".to_string()));
+        assert_eq!(rule_at("-X-\n-O-\n---"), (Rule::EmptyCorner, "A1".to_string()));
+        assert_eq!(rule_at("XOX\nXOO\nOX-"), (Rule::EmptyCorner, "C3".to_string()));
+        assert!(choose(&Board::from_str("XXX\nOO-\n---").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_block_fork() {
+        // X holds opposite corners, so O must threaten from a side rather
+        // than take a corner.
+        let choice = choose(&Board::from_str("X--\n-O-\n--X").unwrap()).unwrap();
+        assert_eq!(choice.rule, Rule::BlockFork);
+        assert!(["A2", "B1", "B3", "C2"].contains(&position_name(choice.pos).as_str()));
+
+        // One fork square: O takes it.
+        let choice = choose(&Board::from_str("X--\n-OX\n---").unwrap()).unwrap();
+        assert_eq!(choice.rule, Rule::BlockFork);
+        assert_eq!(choice.explain(), format!("blocking your fork at {}", position_name(choice.pos)));
+        assert_eq!(choice.pos, parse_position("A3").unwrap());
+    }
+
+    /// Every game the rules can face as `side`, against every reply.
+    fn never_loses(board: Board, side: usize) {
+        match board.outcome() {
+            Some(outcome) => assert_ne!(outcome, Outcome::Win(1 - side), "lost:\n{}", board.notation()),
+            None if Position::side_to_move(&board) == side => never_loses(board.play(choose(&board).unwrap().pos), side),
+            None => {
+                for pos in board.moves() {
+                    never_loses(board.play(pos), side);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_never_loses() {
+        never_loses(Board::default(), 0);
+        never_loses(Board::default(), 1);
+    }
+}
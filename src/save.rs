@@ -0,0 +1,221 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::board::{parse_position, position_name, Board};
+use crate::game::{self, Game, Player, PlayerSet, PlayerType};
+use crate::profile::{self, PlayerNames};
+
+const FILE_HEADER: &str = "tic-tac-toe-save 1";
+
+/// A game in progress, as written by `save <file>` and read back by
+/// `load <file>` or `--resume <file>`.
+///
+/// The file is plain text:
+///
+/// ```text
+/// tic-tac-toe-save 1
+/// x human ann
+/// o computer:perfect
+/// turn X
+/// moves A1 B2
+/// board
+/// X--
+/// -O-
+/// ---
+/// ```
+///
+/// `x` and `o` give each side's player type and an optional profile name.
+/// `moves` is the history from the empty board, X first.  The board rows
+/// are A, B and C.  On load the history is replayed and has to agree with
+/// `turn` and `board`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SavedGame {
+    pub set: PlayerSet,
+    pub turn: Player,
+    pub board: Board,
+    pub history: Vec<(usize, usize)>,
+    pub names: PlayerNames
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveFormatError {
+    pub message: String
+}
+
+impl fmt::Display for SaveFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid save file: {}", self.message)
+    }
+}
+
+fn format_error(message: &str) -> SaveFormatError {
+    SaveFormatError { message: message.to_string() }
+}
+
+impl SavedGame {
+    pub fn game(&self) -> Game {
+        Game::InPlay { set: self.set, turn: self.turn, board: self.board }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.serialize())
+    }
+
+    pub fn load(path: &Path) -> io::Result<SavedGame> {
+        let text = fs::read_to_string(path)?;
+        SavedGame::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    pub fn serialize(&self) -> String {
+        let side = |player: Player, name: &Option<String>| {
+            match name {
+                Some(name) => format!("{} {}", player.player_type().label(), name),
+                None => player.player_type().label()
+            }
+        };
+        let moves: Vec<String> = self.history.iter().map(|pos| position_name(*pos)).collect();
+
+        format!(
+            "{}\nx {}\no {}\nturn {}\nmoves {}\nboard\n{}\n",
+            FILE_HEADER,
+            side(self.set.x, &self.names.x),
+            side(self.set.o, &self.names.o),
+            self.turn.associated_square().notation(),
+            moves.join(" "),
+            self.board.notation()
+        )
+    }
+
+    pub fn parse(text: &str) -> Result<SavedGame, SaveFormatError> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        if lines.next() != Some(FILE_HEADER) {
+            return Err(format_error("unknown file format or version"));
+        }
+
+        let (x_type, x_name) = parse_side(lines.next(), "x")?;
+        let (o_type, o_name) = parse_side(lines.next(), "o")?;
+        let set = PlayerSet { x: Player::X(x_type), o: Player::O(o_type) };
+        let names = PlayerNames { x: x_name, o: o_name };
+
+        let turn = match lines.next().and_then(|line| line.strip_prefix("turn")).map(str::trim) {
+            Some("X") => set.x,
+            Some("O") => set.o,
+            _ => { return Err(format_error("expected 'turn X' or 'turn O'")); }
+        };
+
+        let moves = lines.next()
+            .and_then(|line| line.strip_prefix("moves"))
+            .ok_or_else(|| format_error("expected a moves line"))?;
+        let history = moves.split_whitespace()
+            .map(|name| parse_position(name).ok_or_else(|| format_error(&format!("bad move '{}'", name))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if lines.next() != Some("board") {
+            return Err(format_error("expected a board section"));
+        }
+        let rows: Vec<&str> = lines.by_ref().take(3).collect();
+        let board = Board::from_str(&rows.join("\n")).map_err(|_| format_error("board must be three rows of X, O and -"))?;
+        if lines.next().is_some() {
+            return Err(format_error("unexpected data after the board"));
+        }
+
+        let saved = SavedGame { set, turn, board, history, names };
+        saved.check_history()?;
+        Ok(saved)
+    }
+
+    /// Replays the history and makes sure it lands on the saved board with
+    /// the saved player to move.
+    fn check_history(&self) -> Result<(), SaveFormatError> {
+        let mut current = Game::InPlay { set: self.set, turn: self.set.x, board: Board::default() };
+
+        for pos in &self.history {
+            let Game::InPlay { set, turn, board } = current else {
+                return Err(format_error("moves continue after the game ended"));
+            };
+            current = game::make_move(pos, &board, &turn, &set)
+                .map_err(|_| format_error(&format!("{} is played twice", position_name(*pos))))?;
+        }
+
+        if current != self.game() {
+            return Err(format_error("moves don't match the board and turn"));
+        }
+        Ok(())
+    }
+}
+
+fn parse_side(line: Option<&str>, key: &str) -> Result<(PlayerType, Option<String>), SaveFormatError> {
+    let missing = || format_error(&format!("expected an '{}' player line", key));
+    let rest = line.and_then(|line| line.strip_prefix(key)).ok_or_else(missing)?;
+    let mut parts = rest.trim().splitn(2, ' ');
+
+    let label = parts.next().unwrap_or("");
+    let player_type = PlayerType::from_label(label)
+        .ok_or_else(|| format_error(&format!("unknown player type '{}'", label)))?;
+    let name = parts.next().map(str::trim).filter(|name| !name.is_empty()).map(String::from);
+
+    Ok((player_type, name))
+}
+
+/// Where Ctrl-C writes the game in progress, next to the player profiles.
+pub fn autosave_path() -> PathBuf {
+    profile::default_path().with_file_name("autosave.txt")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::game::{Difficulty, Player, PlayerSet, PlayerType};
+    use crate::profile::PlayerNames;
+
+    use super::SavedGame;
+
+    fn saved_game() -> SavedGame {
+        let set = PlayerSet {
+            x: Player::X(PlayerType::Human),
+            o: Player::O(PlayerType::Computer(Difficulty::Easy))
+        };
+        SavedGame {
+            set,
+            turn: set.x,
+            board: Board::from_str("X--\n-O-\n---").unwrap(),
+            history: vec![(0, 0), (1, 1)],
+            names: PlayerNames { x: Some("Ann Lee".to_string()), o: None }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let saved = saved_game();
+        let text = saved.serialize();
+        assert_eq!(text, "tic-tac-toe-save 1\nx human Ann Lee\no computer:easy\nturn X\nmoves A1 B2\nboard\nX--\n-O-\n---\n");
+        assert_eq!(SavedGame::parse(&text).unwrap(), saved);
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_files() {
+        let text = saved_game().serialize();
+
+        assert!(SavedGame::parse(&text.replace("save 1", "save 9")).is_err());
+        assert!(SavedGame::parse(&text.replace("turn X", "turn O")).is_err());
+        assert!(SavedGame::parse(&text.replace("moves A1 B2", "moves A1 A1")).is_err());
+        assert!(SavedGame::parse(&text.replace("moves A1 B2", "moves A1 B3")).is_err());
+        assert!(SavedGame::parse(&text.replace("-O-", "-OO")).is_err());
+        assert!(SavedGame::parse(&text.replace("computer:easy", "robot")).is_err());
+    }
+
+    #[test]
+    fn test_rejects_finished_game() {
+        let text = "tic-tac-toe-save 1\nx human\no human\nturn O\nmoves A1 B1 A2 B2 A3\nboard\nXXX\nOO-\n---\n";
+        assert!(SavedGame::parse(text).is_err());
+    }
+}
@@ -0,0 +1,585 @@
+//! The game-agnostic side of the computer players: the [`Position`] trait
+//! every searchable game implements, and an exhaustive alpha-beta search
+//! over it.
+//!
+//! Nothing here knows about tic-tac-toe.  The classic `Board`, the m,n,k
+//! boards in `mnk` and the Nim in `nim` all implement `Position`, and the
+//! same search, and the Monte Carlo search in `mcts`, play each of them.
+//! Values are from the side to move: 1 for a win, 0 for a draw and -1 for
+//! a loss, with both sides playing perfectly.
+//!
+//! [`value`] and [`best_move`] search to the end, which is quick for 3×3
+//! and hopeless for most of the larger boards.  [`iterative`] deepens one
+//! ply at a time within [`Limits`] instead, and can be stopped through its
+//! [`Control`] from another thread, which also always holds the best move
+//! found so far.  Past the depth it has reached a position counts as a
+//! draw, so a win or loss it reports is proven and a draw may not be.
+//! [`parallel`] is the same search with each depth's root moves split
+//! across threads, and gives the same answer; `benches/search.rs` times
+//! the two.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board::{Board, Square};
+use crate::game;
+
+/// How a game ends, by side: 0 moves first.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Win(usize),
+    Draw
+}
+
+/// A two-player zero-sum game with perfect information, seen from one
+/// position.
+pub trait Position: Clone {
+    type Move: Copy + PartialEq;
+
+    /// The legal moves; none once the game is over.
+    fn moves(&self) -> Vec<Self::Move>;
+
+    /// The position after the side to move plays `mv`.
+    fn play(&self, mv: Self::Move) -> Self;
+
+    /// 0 for the side that moved first, 1 for the other.
+    fn side_to_move(&self) -> usize;
+
+    /// `None` while the game goes on.
+    fn outcome(&self) -> Option<Outcome>;
+
+    fn is_terminal(&self) -> bool {
+        self.outcome().is_some()
+    }
+
+    /// What the finished game is worth to `side`: 1 for a win, 0 for a
+    /// draw and -1 for a loss.  0 while the game goes on.
+    fn utility(&self, side: usize) -> i32 {
+        match self.outcome() {
+            Some(Outcome::Win(winner)) if winner == side => 1,
+            Some(Outcome::Win(_)) => -1,
+            _ => 0
+        }
+    }
+}
+
+impl Position for Board {
+    type Move = (usize, usize);
+
+    fn moves(&self) -> Vec<(usize, usize)> {
+        if game::winning_line(self).is_some() { Vec::new() } else { self.get_empty_squares() }
+    }
+
+    fn play(&self, pos: (usize, usize)) -> Board {
+        let mut after = *self;
+        after.squares[pos.0][pos.1] = if Position::side_to_move(self) == 0 { Square::X } else { Square::O };
+        after
+    }
+
+    fn side_to_move(&self) -> usize {
+        usize::from(Board::side_to_move(self) == Some(Square::O))
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        match game::winning_line(self) {
+            Some(line) => Some(Outcome::Win(usize::from(self.squares[line[0].0][line[0].1] == Square::O))),
+            None if self.get_empty_squares().is_empty() => Some(Outcome::Draw),
+            None => None
+        }
+    }
+}
+
+/// The position's value to the side to move.
+pub fn value<P: Position>(position: &P) -> i32 {
+    negamax(position, -1, 1)
+}
+
+/// What playing `mv` is worth to the side that plays it.
+pub fn move_value<P: Position>(position: &P, mv: P::Move) -> i32 {
+    -value(&position.play(mv))
+}
+
+/// The first move, in the order `moves` gives them, with the highest
+/// value, or `None` once the game is over.
+pub fn best_move<P: Position>(position: &P) -> Option<P::Move> {
+    let mut best: Option<(P::Move, i32)> = None;
+    for mv in position.moves() {
+        let score = move_value(position, mv);
+        if best.is_none_or(|(_, high)| score > high) {
+            best = Some((mv, score));
+        }
+        if score == 1 {
+            break;
+        }
+    }
+    best.map(|(mv, _)| mv)
+}
+
+/// The value to the side to move, exact when it lies between `alpha` and
+/// `beta`, and otherwise only as far as that bound.
+fn negamax<P: Position>(position: &P, mut alpha: i32, beta: i32) -> i32 {
+    if position.is_terminal() {
+        return position.utility(position.side_to_move());
+    }
+    let mut best = -1;
+    for mv in position.moves() {
+        best = best.max(-negamax(&position.play(mv), -beta, -alpha));
+        alpha = alpha.max(best);
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
+
+/// How far an iterative search may go.  `None` leaves that limit off, and
+/// the default has none, searching to the end.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Limits {
+    /// In plies.
+    pub depth: Option<u32>,
+    /// Positions visited, over all depths.
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>
+}
+
+/// Where an iterative search has got to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Progress<M> {
+    /// The best move at `depth`, or `None` if the game is over.
+    pub best: Option<M>,
+    /// What `best` is worth to the side to move, seen `depth` plies ahead.
+    pub value: i32,
+    /// The deepest search completed; 0 before the first.
+    pub depth: u32,
+    pub nodes: u64,
+    /// Whether `value` is exact, as `value` would give it.
+    pub solved: bool
+}
+
+/// Shared between an iterative search and whoever started it: a flag to
+/// stop the search and its progress so far.
+pub struct Control<M> {
+    stopped: AtomicBool,
+    /// Positions visited, by every thread and at every depth, including
+    /// any depth cut short.
+    visited: AtomicU64,
+    progress: Mutex<Option<Progress<M>>>
+}
+
+impl<M> Default for Control<M> {
+    fn default() -> Self {
+        Control { stopped: AtomicBool::new(false), visited: AtomicU64::new(0), progress: Mutex::new(None) }
+    }
+}
+
+impl<M: Copy> Control<M> {
+    /// Asks the search to finish as soon as it can.  It returns with the
+    /// last depth it completed.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// The search's progress, `None` until it has started.  From then on
+    /// `best` holds a legal move for as long as the game goes on.
+    pub fn progress(&self) -> Option<Progress<M>> {
+        *self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// All the positions the search has visited so far, including root
+    /// moves `parallel` searched that `iterative` would have skipped.
+    pub fn visited(&self) -> u64 {
+        self.visited.load(Ordering::Relaxed)
+    }
+
+    fn visit(&self, nodes: u64) {
+        self.visited.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    fn report(&self, progress: Progress<M>) {
+        *self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(progress);
+    }
+}
+
+/// How many positions to visit between looks at the clock and the stop
+/// flag.
+const CHECK_EVERY: u64 = 256;
+
+/// Searches `root` one ply deeper at a time until it is solved, a limit is
+/// reached or `control` is stopped.  Once solved it picks the same move as
+/// `best_move`.
+pub fn iterative<P: Position>(root: &P, limits: Limits, control: &Control<P::Move>) -> Progress<P::Move> {
+    deepen(root, limits, control, |rest, depth, alpha, budget, clock| {
+        let mut searched = vec![None; rest.len()];
+        let mut spent = 0;
+        for (index, &mv) in rest.iter().enumerate() {
+            let result = search_move(root, mv, depth, alpha, budget.map(|nodes| nodes.saturating_sub(spent)), clock);
+            spent += result.nodes;
+            searched[index] = Some(result);
+            if result.aborted || result.score == 1 {
+                break;
+            }
+        }
+        searched
+    })
+}
+
+/// `iterative` with each depth's root moves after the first split across
+/// `threads` threads.  Every completed depth gives the same progress as
+/// `iterative`, nodes included, whatever the thread count, and a `nodes`
+/// limit stops both at the same depth: each move is only charged for the
+/// moves before it, as `iterative` would search them.  Only a time limit
+/// or `control` stopping it can make where it ends depend on timing.
+pub fn parallel<P>(root: &P, limits: Limits, control: &Control<P::Move>, threads: usize) -> Progress<P::Move>
+where
+    P: Position + Sync,
+    P::Move: Send + Sync
+{
+    if threads <= 1 {
+        return iterative(root, limits, control);
+    }
+    deepen(root, limits, control, |rest, depth, alpha, budget, clock| {
+        // Moves are handed out in order, and none after a known win is
+        // started, so every move up to the first win or cut-off one gets
+        // searched.  Each may use the nodes left after the finished moves
+        // before it, at least as many as `iterative` would leave it, so it
+        // is only cut off where `iterative` would cut off the depth too.
+        let next = AtomicUsize::new(0);
+        let first_win = AtomicUsize::new(usize::MAX);
+        let searched = Mutex::new(vec![None; rest.len()]);
+        thread::scope(|scope| {
+            for _ in 0..threads.min(rest.len()) {
+                scope.spawn(|| loop {
+                    let (index, spent) = {
+                        let searched = searched.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                        (next.fetch_add(1, Ordering::Relaxed), spent_before(&searched))
+                    };
+                    if index >= rest.len() || index > first_win.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let budget = match budget {
+                        // Already over: `deepen` gives up on this depth.
+                        Some(nodes) if spent > nodes => break,
+                        Some(nodes) => Some(nodes - spent),
+                        None => None
+                    };
+                    let result = search_move(root, rest[index], depth, alpha, budget, clock);
+                    if result.score == 1 && !result.aborted {
+                        first_win.fetch_min(index, Ordering::Relaxed);
+                    }
+                    searched.lock().unwrap_or_else(|poisoned| poisoned.into_inner())[index] = Some(result);
+                    if result.aborted {
+                        break;
+                    }
+                });
+            }
+        });
+        searched.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+    })
+}
+
+/// The nodes used by the finished moves at the front of `searched`, up to
+/// the first that wins.
+fn spent_before(searched: &[Option<Searched>]) -> u64 {
+    let mut spent = 0;
+    for result in searched.iter().map_while(|result| *result) {
+        spent += result.nodes;
+        if result.score == 1 && !result.aborted {
+            break;
+        }
+    }
+    spent
+}
+
+/// One root move searched to some depth.
+#[derive(Clone, Copy, Debug)]
+struct Searched {
+    /// To the side that plays the move: exact if above the search's
+    /// `alpha`, otherwise only known to be at most `alpha`.
+    score: i32,
+    nodes: u64,
+    /// Whether the search stopped short of the end anywhere.
+    horizon: bool,
+    /// Whether a limit or the stop flag cut it off.
+    aborted: bool
+}
+
+/// What stops a search early besides its node budget: the time limit and
+/// the stop flag.
+struct Clock<'a, M> {
+    started: Instant,
+    time: Option<Duration>,
+    control: &'a Control<M>
+}
+
+/// The loop shared by `iterative` and `parallel`, which differ only in how
+/// they search the root moves after the first.  `search_rest` gets those
+/// moves, the depth, the first move's score to search them against and
+/// the nodes left for them, and returns a result for each move up to and
+/// including the first that wins or is cut off.
+fn deepen<P, F>(root: &P, limits: Limits, control: &Control<P::Move>, mut search_rest: F) -> Progress<P::Move>
+where
+    P: Position,
+    F: FnMut(&[P::Move], u32, i32, Option<u64>, &Clock<'_, P::Move>) -> Vec<Option<Searched>>
+{
+    let moves = root.moves();
+    let mut progress = Progress { best: moves.first().copied(), value: 0, depth: 0, nodes: 0, solved: moves.is_empty() };
+    if progress.solved {
+        progress.value = root.utility(root.side_to_move());
+    }
+    control.report(progress);
+
+    let clock = Clock { started: Instant::now(), time: limits.time, control };
+    while !progress.solved && limits.depth.is_none_or(|limit| progress.depth < limit) {
+        let depth = progress.depth + 1;
+
+        // The first move is searched on its own so the rest can be
+        // searched against its score, the same score on every thread.
+        let budget = limits.nodes.map(|limit| limit.saturating_sub(progress.nodes));
+        let first = search_move(root, moves[0], depth, -1, budget, &clock);
+        let mut searched = vec![Some(first)];
+        if !first.aborted && first.score != 1 {
+            let left = budget.map(|nodes| nodes - first.nodes);
+            searched.extend(search_rest(&moves[1..], depth, first.score, left, &clock));
+        }
+
+        let (mut best, mut nodes, mut horizon, mut aborted) = (None, 0, false, false);
+        for (&mv, result) in moves.iter().zip(searched) {
+            let result = result.expect("every move up to the first win is searched");
+            nodes += result.nodes;
+            horizon |= result.horizon;
+            // `parallel` gives each move the nodes left before it, so only the
+            // total shows that the moves between them went over.
+            aborted |= result.aborted || limits.nodes.is_some_and(|limit| progress.nodes + nodes > limit);
+            if aborted {
+                break;
+            }
+            if best.is_none_or(|(_, high)| result.score > high) {
+                best = Some((mv, result.score));
+            }
+            if result.score == 1 {
+                break;
+            }
+        }
+        if aborted {
+            break;
+        }
+        let (mv, value) = best.expect("the game goes on");
+        progress = Progress { best: Some(mv), value, depth, nodes: progress.nodes + nodes, solved: value != 0 || !horizon };
+        control.report(progress);
+    }
+    progress
+}
+
+/// Searches `mv` from `root` to `depth` plies in all, against `alpha`,
+/// cut off after visiting more than `budget` positions.
+fn search_move<P: Position>(root: &P, mv: P::Move, depth: u32, alpha: i32, budget: Option<u64>, clock: &Clock<'_, P::Move>) -> Searched {
+    let mut search = Deepening { clock, budget, nodes: 0, horizon: false, aborted: false };
+    let score = -search.negamax(&root.play(mv), depth - 1, -1, -alpha);
+    clock.control.visit(search.nodes);
+    Searched { score, nodes: search.nodes, horizon: search.horizon, aborted: search.aborted }
+}
+
+/// The state of one root move's search.
+struct Deepening<'a, M> {
+    clock: &'a Clock<'a, M>,
+    budget: Option<u64>,
+    nodes: u64,
+    horizon: bool,
+    aborted: bool
+}
+
+impl<M: Copy> Deepening<'_, M> {
+    /// `negamax` cut off `depth` plies down, where an unfinished game counts
+    /// as a draw.  Gives 0 once aborted.
+    fn negamax<P: Position<Move = M>>(&mut self, position: &P, depth: u32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        let checking = self.nodes.is_multiple_of(CHECK_EVERY);
+        self.aborted = self.aborted
+            || self.budget.is_some_and(|budget| self.nodes > budget)
+            || (checking && self.clock.control.is_stopped())
+            || (checking && self.clock.time.is_some_and(|limit| self.clock.started.elapsed() >= limit));
+        if self.aborted {
+            return 0;
+        }
+
+        if position.is_terminal() {
+            return position.utility(position.side_to_move());
+        }
+        if depth == 0 {
+            self.horizon = true;
+            return 0;
+        }
+        let mut best = -1;
+        for mv in position.moves() {
+            best = best.max(-self.negamax(&position.play(mv), depth - 1, -beta, -alpha));
+            if self.aborted {
+                return 0;
+            }
+            alpha = alpha.max(best);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::board::{parse_position, Board};
+    use crate::mnk::{MnkBoard, Variant};
+    use crate::nim::Nim;
+    use crate::solve;
+
+    use super::{best_move, iterative, move_value, parallel, value, Control, Limits, Outcome, Position};
+
+    #[test]
+    fn test_board_position() {
+        let board = Board::from_str("XX-\nOO-\n---").unwrap();
+        assert_eq!(Position::side_to_move(&board), 0);
+        assert_eq!(board.moves().len(), 5);
+        let won = board.play(parse_position("A3").unwrap());
+        assert_eq!(won.outcome(), Some(Outcome::Win(0)));
+        assert!(won.moves().is_empty() && won.is_terminal());
+        assert_eq!((won.utility(0), won.utility(1)), (1, -1));
+        assert_eq!(Board::from_str("XOX\nXOO\nOXX").unwrap().outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn test_search_board() {
+        assert_eq!(value(&Board::default()), 0);
+        let board = Board::from_str("XX-\nOO-\n---").unwrap();
+        assert_eq!(value(&board), 1);
+        assert_eq!(best_move(&board), parse_position("A3"));
+        assert_eq!(move_value(&board, parse_position("C3").unwrap()), -1);
+        assert_eq!(best_move(&board.play(parse_position("A3").unwrap())), None);
+
+        // The same values as the tablebase walked forwards.
+        let table = solve::solve().tablebase;
+        for index in (0..19683).step_by(7) {
+            let board = solve::board_from_index(index);
+            if let Some(entry) = table.entry(&board) {
+                if board.get_empty_squares().len() <= 6 {
+                    assert_eq!(value(&board), i32::from(entry.value), "{}", board.notation());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_variants() {
+        // Two in a row on 2 by 2 is a first-player win; three in a row
+        // along the 2 by 4 board's rows is a draw, since O blocks both of
+        // a row's lines by taking one of its middle squares.
+        let small = MnkBoard::new(Variant::new(2, 2, 2).unwrap());
+        assert_eq!(value(&small), 1);
+        let narrow = MnkBoard::new(Variant::new(2, 4, 3).unwrap());
+        assert_eq!(value(&narrow), 0);
+        let double = narrow.play(1).play(5).play(2);
+        assert_eq!(value(&double), -1);
+        let single = narrow.play(0).play(4).play(1).play(5);
+        assert_eq!(best_move(&single), Some(2));
+    }
+
+    #[test]
+    fn test_iterative_matches_full_search() {
+        for board in ["---\n---\n---", "X--\n---\n---", "XX-\nOO-\n---", "X--\n-O-\n--X", "XOX\n-O-\n---"] {
+            let board = Board::from_str(board).unwrap();
+            let progress = iterative(&board, Limits::default(), &Control::default());
+            assert!(progress.solved);
+            assert_eq!((progress.best, progress.value), (best_move(&board), value(&board)), "{}", board.notation());
+        }
+        let nim = Nim::new(&[1, 2, 5]);
+        assert_eq!(iterative(&nim, Limits::default(), &Control::default()).best, best_move(&nim));
+
+        // A win on the next move is proven at depth 1.
+        let board = Board::from_str("XX-\nOO-\n---").unwrap();
+        let progress = iterative(&board, Limits { depth: Some(1), ..Limits::default() }, &Control::default());
+        assert_eq!((progress.best, progress.value, progress.depth, progress.solved), (parse_position("A3"), 1, 1, true));
+
+        let over = Board::from_str("XXX\nOO-\n---").unwrap();
+        let progress = iterative(&over, Limits::default(), &Control::default());
+        assert_eq!((progress.best, progress.value, progress.depth, progress.solved), (None, -1, 0, true));
+    }
+
+    #[test]
+    fn test_iterative_limits() {
+        let wide = MnkBoard::new(Variant::new(5, 5, 4).unwrap());
+        let control = Control::default();
+        let progress = iterative(&wide, Limits { depth: Some(3), ..Limits::default() }, &control);
+        assert_eq!((progress.depth, progress.solved), (3, false));
+        assert_eq!(control.progress(), Some(progress));
+        assert!(progress.best.is_some());
+
+        let progress = iterative(&wide, Limits { nodes: Some(10_000), ..Limits::default() }, &Control::default());
+        assert!(progress.nodes <= 10_000 && progress.depth >= 2 && !progress.solved);
+
+        let progress = iterative(&wide, Limits { time: Some(Duration::from_millis(20)), ..Limits::default() }, &Control::default());
+        assert!(progress.best.is_some() && !progress.solved);
+    }
+
+    #[test]
+    fn test_iterative_stops() {
+        let wide = MnkBoard::new(Variant::new(5, 5, 4).unwrap());
+        let control = Arc::new(Control::default());
+        let searching = {
+            let control = Arc::clone(&control);
+            thread::spawn(move || iterative(&wide, Limits::default(), &control))
+        };
+        while control.progress().is_none_or(|progress| progress.depth < 2) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(control.progress().unwrap().best.is_some());
+        control.stop();
+        let progress = searching.join().unwrap();
+        assert!(progress.depth >= 2 && !progress.solved);
+        assert_eq!(control.progress(), Some(progress));
+    }
+
+    #[test]
+    fn test_parallel_matches_iterative() {
+        let boards = ["---\n---\n---", "X--\n---\n---", "XX-\nOO-\n---", "X--\n-O-\n--X", "XXX\nOO-\n---"];
+        for board in boards {
+            let board = Board::from_str(board).unwrap();
+            let expected = iterative(&board, Limits::default(), &Control::default());
+            for threads in [1, 2, 3, 8] {
+                assert_eq!(parallel(&board, Limits::default(), &Control::default(), threads), expected, "{}", board.notation());
+            }
+        }
+        let nim = Nim::new(&[3, 4, 5]);
+        assert_eq!(parallel(&nim, Limits::default(), &Control::default(), 4), iterative(&nim, Limits::default(), &Control::default()));
+
+        // Depth and node limits stop both at the same place too, node limits
+        // even at depths where a root move wins and the moves after it that
+        // only `parallel` starts use up nodes.
+        let wide = MnkBoard::new(Variant::new(4, 4, 3).unwrap()).play(5);
+        for limits in [Limits { depth: Some(4), ..Limits::default() }, Limits { nodes: Some(20_000), ..Limits::default() }] {
+            let expected = iterative(&wide, limits, &Control::default());
+            for threads in [2, 4] {
+                let control = Control::default();
+                assert_eq!(parallel(&wide, limits, &control, threads), expected, "{:?}", limits);
+                assert_eq!(control.progress(), Some(expected));
+            }
+        }
+        let winning = MnkBoard::new(Variant::new(4, 4, 3).unwrap()).play(5).play(0);
+        for nodes in 1..300 {
+            let limits = Limits { nodes: Some(nodes), ..Limits::default() };
+            let expected = iterative(&winning, limits, &Control::default());
+            for threads in [2, 3, 8] {
+                assert_eq!(parallel(&winning, limits, &Control::default(), threads), expected, "{} nodes on {} threads", nodes, threads);
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! `Serialize` and `Deserialize` for the game types, behind the `serde`
+//! feature.
+//!
+//! The shapes, shown as JSON, are meant to stay stable:
+//!
+//! | Type | Shape |
+//! |---|---|
+//! | `Square` | `"X"`, `"O"` or `"-"` |
+//! | `Board` | Rows A, B and C: `["X--", "-O-", "---"]` |
+//! | `PlayerType` | Its label: `"human"`, `"computer:perfect"`, `"engine"` |
+//! | `Player` | `{"mark": "X", "type": "human"}` |
+//! | `PlayerSet` | `{"x": "human", "o": "computer:easy"}` |
+//! | `Game` | `{"status": "uninitiated"}` |
+//! | | `{"status": "in_play", "players": <PlayerSet>, "turn": "O", "board": <Board>}` |
+//! | | `{"status": "tied", "board": <Board>}` |
+//! | | `{"status": "won", "winner": <Player>, "board": <Board>}` |
+//!
+//! Deserializing checks that the data could come from a real game: X
+//! moves first so it has as many marks as O or one more, at most one
+//! player has a line, and a game's status and turn agree with its board.
+
+use std::str::FromStr;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::board::{Board, Square};
+use crate::game::{self, Game, Player, PlayerSet, PlayerType};
+
+impl Serialize for Square {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.notation().to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Square {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Square::from_str(&text).map_err(|_| D::Error::custom(format!("expected \"X\", \"O\" or \"-\", got {:?}", text)))
+    }
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let notation = self.notation();
+        let rows: Vec<&str> = notation.lines().collect();
+        rows.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rows = Vec::<String>::deserialize(deserializer)?;
+        if rows.len() != 3 {
+            return Err(D::Error::custom(format!("a board has 3 rows, not {}", rows.len())));
+        }
+        let board = Board::from_str(&rows.join("\n"))
+            .map_err(|_| D::Error::custom("each row is 3 of \"X\", \"O\" and \"-\""))?;
+        check_board(&board).map_err(D::Error::custom)?;
+        Ok(board)
+    }
+}
+
+/// The winner's mark, if any, once the board is known to be reachable.
+fn check_board(board: &Board) -> Result<Option<Square>, String> {
+    let Some(to_move) = board.side_to_move() else {
+        return Err("X moves first, so it has as many marks as O or one more".to_string());
+    };
+
+    let has_line = |mark| game::LINES.iter().any(|line| line.iter().all(|&(x, y)| board.squares[x][y] == mark));
+    match (has_line(Square::X), has_line(Square::O)) {
+        (true, true) => Err("both players have a line".to_string()),
+        (true, false) if to_move != Square::O => Err("X won, so X moved last".to_string()),
+        (false, true) if to_move != Square::X => Err("O won, so O moved last".to_string()),
+        (true, false) => Ok(Some(Square::X)),
+        (false, true) => Ok(Some(Square::O)),
+        (false, false) => Ok(None)
+    }
+}
+
+impl Serialize for PlayerType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.label())
+    }
+}
+
+impl<'de> Deserialize<'de> for PlayerType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let label = String::deserialize(deserializer)?;
+        PlayerType::from_label(&label).ok_or_else(|| D::Error::custom(format!("unknown player type {:?}", label)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlayerRepr {
+    mark: Square,
+    #[serde(rename = "type")]
+    player_type: PlayerType
+}
+
+impl Serialize for Player {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PlayerRepr { mark: self.associated_square(), player_type: *self.player_type() }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Player {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = PlayerRepr::deserialize(deserializer)?;
+        match repr.mark {
+            Square::X => Ok(Player::X(repr.player_type)),
+            Square::O => Ok(Player::O(repr.player_type)),
+            Square::Empty => Err(D::Error::custom("a player's mark is \"X\" or \"O\""))
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlayerSetRepr {
+    x: PlayerType,
+    o: PlayerType
+}
+
+impl Serialize for PlayerSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PlayerSetRepr { x: *self.x.player_type(), o: *self.o.player_type() }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PlayerSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = PlayerSetRepr::deserialize(deserializer)?;
+        Ok(PlayerSet { x: Player::X(repr.x), o: Player::O(repr.o) })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case", deny_unknown_fields)]
+enum GameRepr {
+    Uninitiated,
+    InPlay { players: PlayerSet, turn: Square, board: Board },
+    Tied { board: Board },
+    Won { winner: Player, board: Board }
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match *self {
+            Game::Uninitiated => GameRepr::Uninitiated,
+            Game::InPlay { set, turn, board } => GameRepr::InPlay { players: set, turn: turn.associated_square(), board },
+            Game::Tie(board) => GameRepr::Tied { board },
+            Game::Win(winner, board) => GameRepr::Won { winner, board }
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let is_full = |board: &Board| board.get_empty_squares().is_empty();
+        // Boards have already passed `check_board`, so this cannot fail.
+        let winner_of = |board: &Board| check_board(board).unwrap_or(None);
+
+        match GameRepr::deserialize(deserializer)? {
+            GameRepr::Uninitiated => Ok(Game::Uninitiated),
+            GameRepr::InPlay { players, turn, board } => {
+                if winner_of(&board).is_some() || is_full(&board) {
+                    return Err(D::Error::custom("a game in play has no line and an empty square"));
+                }
+                if board.side_to_move() != Some(turn) {
+                    return Err(D::Error::custom(format!("it is not {}'s turn on this board", turn.notation())));
+                }
+                let turn = if turn == Square::X { players.x } else { players.o };
+                Ok(Game::InPlay { set: players, turn, board })
+            },
+            GameRepr::Tied { board } => {
+                if winner_of(&board).is_some() || !is_full(&board) {
+                    return Err(D::Error::custom("a tied board is full with no line"));
+                }
+                Ok(Game::Tie(board))
+            },
+            GameRepr::Won { winner, board } => {
+                if winner_of(&board) != Some(winner.associated_square()) {
+                    return Err(D::Error::custom(format!("{} has no line on this board", winner.associated_square().notation())));
+                }
+                Ok(Game::Win(winner, board))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use serde_json::{from_str, json, to_value};
+
+    use crate::board::{Board, Square};
+    use crate::game::{Difficulty, Game, Player, PlayerSet, PlayerType};
+
+    fn board(rows: &str) -> Board {
+        Board::from_str(rows).unwrap()
+    }
+
+    fn set() -> PlayerSet {
+        PlayerSet { x: Player::X(PlayerType::Human), o: Player::O(PlayerType::Computer(Difficulty::Perfect)) }
+    }
+
+    #[test]
+    fn test_documented_shapes() {
+        assert_eq!(to_value(Square::Empty).unwrap(), json!("-"));
+        assert_eq!(to_value(board("X--\n-O-\n---")).unwrap(), json!(["X--", "-O-", "---"]));
+        assert_eq!(to_value(PlayerType::Computer(Difficulty::Easy)).unwrap(), json!("computer:easy"));
+        assert_eq!(to_value(Player::O(PlayerType::Engine)).unwrap(), json!({"mark": "O", "type": "engine"}));
+        assert_eq!(to_value(set()).unwrap(), json!({"x": "human", "o": "computer:perfect"}));
+
+        let game = Game::InPlay { set: set(), turn: set().o, board: board("X--\n---\n---") };
+        assert_eq!(
+            to_value(game).unwrap(),
+            json!({"status": "in_play", "players": {"x": "human", "o": "computer:perfect"}, "turn": "O", "board": ["X--", "---", "---"]}));
+        assert_eq!(to_value(Game::Tie(board("XOX\nXOO\nOXX"))).unwrap(), json!({"status": "tied", "board": ["XOX", "XOO", "OXX"]}));
+        assert_eq!(to_value(Game::Uninitiated).unwrap(), json!({"status": "uninitiated"}));
+    }
+
+    #[test]
+    fn test_round_trips() {
+        for square in [Square::X, Square::O, Square::Empty] {
+            assert_eq!(from_str::<Square>(&serde_json::to_string(&square).unwrap()).unwrap(), square);
+        }
+        for player_type in [PlayerType::Human, PlayerType::Engine, PlayerType::Computer(Difficulty::Random)] {
+            let player = Player::X(player_type);
+            assert_eq!(from_str::<Player>(&serde_json::to_string(&player).unwrap()).unwrap(), player);
+        }
+        assert_eq!(from_str::<PlayerSet>(&serde_json::to_string(&set()).unwrap()).unwrap(), set());
+
+        let games = [
+            Game::Uninitiated,
+            Game::InPlay { set: set(), turn: set().x, board: Board::default() },
+            Game::InPlay { set: set(), turn: set().o, board: board("XO-\n-X-\n---") },
+            Game::Tie(board("XOX\nXOO\nOXX")),
+            Game::Win(set().x, board("XXX\nOO-\n---")),
+            Game::Win(set().o, board("OOO\nXX-\nX--"))
+        ];
+        for game in games {
+            let text = serde_json::to_string(&game).unwrap();
+            assert_eq!(from_str::<Game>(&text).unwrap(), game, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_rejects_impossible_data() {
+        let rejected = [
+            json!(["XX-", "---", "---"]),
+            json!(["O--", "---", "---"]),
+            json!(["XXX", "OOO", "---"]),
+            json!(["XXX", "OOO", "X--"]),
+            json!(["XXX", "OO-", "O--"]),
+            json!(["X--", "---"]),
+            json!(["X-", "---", "---"]),
+            json!(["x--", "---", "---"])
+        ];
+        for value in rejected {
+            assert!(serde_json::from_value::<Board>(value.clone()).is_err(), "{}", value);
+        }
+
+        let players = json!({"x": "human", "o": "human"});
+        let rejected = [
+            json!({"status": "in_play", "players": players, "turn": "X", "board": ["X--", "---", "---"]}),
+            json!({"status": "in_play", "players": players, "turn": "O", "board": ["XXX", "OO-", "---"]}),
+            json!({"status": "tied", "board": ["XO-", "---", "---"]}),
+            json!({"status": "won", "winner": {"mark": "O", "type": "human"}, "board": ["XXX", "OO-", "---"]}),
+            json!({"status": "won", "winner": {"mark": "-", "type": "human"}, "board": ["XXX", "OO-", "---"]}),
+            json!({"status": "finished", "board": ["---", "---", "---"]})
+        ];
+        for value in rejected {
+            assert!(serde_json::from_value::<Game>(value.clone()).is_err(), "{}", value);
+        }
+        assert!(serde_json::from_value::<PlayerSet>(json!({"x": "human", "o": "wizard"})).is_err());
+    }
+}
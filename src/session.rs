@@ -0,0 +1,89 @@
+use std::sync::{Arc, Mutex};
+
+use crate::game::{Game, PlayerSet};
+use crate::profile::PlayerNames;
+use crate::save::SavedGame;
+
+/// The latest state of the game, kept for a Ctrl-C handler.
+pub type Autosave = Arc<Mutex<Option<SavedGame>>>;
+
+/// Everything about the game being played that a save file needs.
+#[derive(Debug)]
+pub struct Session { 
+    pub game: Game,
+    pub set: PlayerSet,
+    pub history: Vec<(usize, usize)>,
+    pub names: PlayerNames
+}
+
+impl Session { 
+    pub fn new(set: PlayerSet, game: Game, names: PlayerNames) -> Self { 
+        Session { game, set, history: Vec::new(), names }
+    }
+
+    pub fn resume(saved: SavedGame) -> Self { 
+        Session { game: saved.game(), set: saved.set, history: saved.history, names: saved.names }
+    }
+
+    /// Moves on to `updated`, the game one move after the current one, and
+    /// adds that move to the history.
+    pub fn advance(&mut self, updated: Game) { 
+        let before = self.game.board();
+        let after = updated.board();
+        if let (Some(before), Some(after)) = (before, after) { 
+            if let Some(pos) = before.changed_position(after) { 
+                self.history.push(pos);
+            }
+        }
+        self.game = updated;
+    }
+
+    pub fn last_move(&self) -> Option<(usize, usize)> { 
+        self.history.last().copied()
+    }
+
+    pub fn snapshot(&self) -> Option<SavedGame> { 
+        match self.game { 
+            Game::InPlay { set, turn, board } => Some(SavedGame { 
+                set,
+                turn,
+                board,
+                history: self.history.clone(),
+                names: self.names.clone()
+            }),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::game::{self, Difficulty, Game, Player, PlayerSet, PlayerType};
+    use crate::profile::PlayerNames;
+
+    use super::Session;
+
+    #[test]
+    fn test_advance_records_history() { 
+        let set = PlayerSet { 
+            x: Player::X(PlayerType::Human),
+            o: Player::O(PlayerType::Computer(Difficulty::Perfect))
+        };
+        let board = Board::default();
+        let mut session = Session::new(set, Game::InPlay { set, turn: set.x, board }, PlayerNames::default());
+
+        let after_x = game::make_move(&(1, 1), &board, &set.x, &set).unwrap();
+        session.advance(after_x);
+        let Game::InPlay { turn, board, .. } = session.game else { panic!("game ended early"); };
+        session.advance(game::computer_move(&turn, &set, &board).unwrap());
+
+        assert_eq!(session.history.len(), 2);
+        assert_eq!(session.history[0], (1, 1));
+        assert_eq!(session.last_move(), Some(session.history[1]));
+
+        let saved = session.snapshot().unwrap();
+        assert_eq!(saved.history, session.history);
+        assert_eq!(Session::resume(saved).game, session.game);
+    }
+}
@@ -0,0 +1,279 @@
+//! Headless games between two strategies, for `tic-tac-toe simulate`.
+//!
+//! Games are shared out between threads.  Each thread keeps its own
+//! engine processes, if a side needs one, and plays through
+//! `engine::play_turn`, so built-in strategies go through `computer_move`
+//! and `make_move` exactly as in a normal game.  Monte Carlo strategies
+//! with their own budget search here and then play through `make_move`.
+
+use std::fmt::Write as _;
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board::{position_name, Board, Square, XPos};
+use crate::engine::{self, ExternalEngine};
+use crate::game::{self, Difficulty, Game, Player, PlayerSet, PlayerType};
+use crate::mcts::{self, Budget};
+use crate::rng::Rng;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Strategy {
+    Builtin(Difficulty),
+    /// Monte Carlo tree search with this budget.
+    Mcts(Budget),
+    /// An engine protocol program, started with this command.
+    Engine(String)
+}
+
+impl Strategy {
+    pub fn label(&self) -> String {
+        match self {
+            Strategy::Builtin(difficulty) => difficulty.label().to_string(),
+            Strategy::Mcts(budget) => format!("mcts:{}", budget.label()),
+            Strategy::Engine(_) => "engine".to_string()
+        }
+    }
+
+    /// A difficulty label, `mcts:` and a budget such as `5000` or `100ms`,
+    /// or `engine`, whose command is left empty for the caller to fill in.
+    pub fn from_label(label: &str) -> Option<Strategy> {
+        match label.split_once(':') {
+            Some(("mcts", budget)) => Budget::from_label(budget).map(Strategy::Mcts),
+            Some(_) => None,
+            None if label == "engine" => Some(Strategy::Engine(String::new())),
+            None => Difficulty::from_label(label).map(Strategy::Builtin)
+        }
+    }
+
+    pub fn player_type(&self) -> PlayerType {
+        match self {
+            Strategy::Builtin(difficulty) => PlayerType::Computer(*difficulty),
+            Strategy::Mcts(_) => PlayerType::Computer(Difficulty::Mcts),
+            Strategy::Engine(_) => PlayerType::Engine
+        }
+    }
+
+    /// The engine process this strategy plays through, if it needs one.
+    pub fn start(&self) -> io::Result<Option<ExternalEngine>> {
+        match self {
+            Strategy::Builtin(_) | Strategy::Mcts(_) => Ok(None),
+            Strategy::Engine(command) => ExternalEngine::spawn(command).map(Some)
+        }
+    }
+
+    /// Plays `turn`'s move on `board` with this strategy.
+    pub fn play_turn(&self, engine: Option<&mut ExternalEngine>, turn: &Player, set: &PlayerSet, board: &Board) -> io::Result<Game> {
+        let Strategy::Mcts(budget) = self else { return engine::play_turn(engine, turn, set, board); };
+        let pos = mcts::search(board, *budget, &mut Rng::from_entropy()).best
+            .ok_or_else(|| io::Error::other("no move to play"))?;
+        game::make_move(&pos, board, turn, set).map_err(|error| io::Error::other(error.to_string()))
+    }
+}
+
+/// Totals over a run, with results from X's side.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Report {
+    pub games: usize,
+    pub x_wins: usize,
+    pub draws: usize,
+    pub o_wins: usize,
+    /// Moves over all games.
+    pub moves: usize,
+    /// How often X opened on each square, by `[row][column]`.
+    pub first_moves: [[usize; 3]; 3],
+    pub elapsed: Duration
+}
+
+impl Report {
+    pub fn record(&mut self, game: &Game, moves: &[(usize, usize)]) {
+        self.games += 1;
+        match game {
+            Game::Win(winner, _) if winner.associated_square() == Square::X => { self.x_wins += 1; },
+            Game::Win(..) => { self.o_wins += 1; },
+            _ => { self.draws += 1; }
+        }
+        self.moves += moves.len();
+        if let Some(&(x, y)) = moves.first() {
+            self.first_moves[x][y] += 1;
+        }
+    }
+
+    /// Adds another report's games; the elapsed time is left alone.
+    pub fn merge(&mut self, other: &Report) {
+        self.games += other.games;
+        self.x_wins += other.x_wins;
+        self.draws += other.draws;
+        self.o_wins += other.o_wins;
+        self.moves += other.moves;
+        for (row, other_row) in self.first_moves.iter_mut().zip(other.first_moves) {
+            for (count, other_count) in row.iter_mut().zip(other_row) {
+                *count += other_count;
+            }
+        }
+    }
+
+    fn rate(&self, count: usize) -> f64 {
+        if self.games == 0 { 0.0 } else { 100.0 * count as f64 / self.games as f64 }
+    }
+
+    pub fn average_length(&self) -> f64 {
+        if self.games == 0 { 0.0 } else { self.moves as f64 / self.games as f64 }
+    }
+
+    pub fn games_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0.0 { 0.0 } else { self.games as f64 / seconds }
+    }
+
+    pub fn format_table(&self, x: &Strategy, o: &Strategy) -> String {
+        let mut out = format!("{} (X) vs {} (O), {} games\n", x.label(), o.label(), self.games);
+        for (label, count) in [("X wins", self.x_wins), ("draws", self.draws), ("O wins", self.o_wins)] {
+            let _ = writeln!(out, "   {:<10} {:>8} {:>6.1}%", label, count, self.rate(count));
+        }
+        let _ = writeln!(out, "   average length {:.2} moves", self.average_length());
+        let _ = writeln!(out, "   first moves         1       2       3");
+        for (x, row) in self.first_moves.iter().enumerate() {
+            let _ = write!(out, "   {:<10}", XPos::letter_from(x));
+            for &count in row {
+                let _ = write!(out, " {:>6.1}%", self.rate(count));
+            }
+            out.push('\n');
+        }
+        let _ = writeln!(out, "   {:.0} games/s ({:.2} s)", self.games_per_second(), self.elapsed.as_secs_f64());
+        out
+    }
+
+    /// A header line and one row, with rates as fractions.
+    pub fn format_csv(&self, x: &Strategy, o: &Strategy) -> String {
+        let mut header = "x,o,games,x_wins,draws,o_wins,x_win_rate,draw_rate,o_win_rate,average_length".to_string();
+        let mut row = format!(
+            "{},{},{},{},{},{},{:.4},{:.4},{:.4},{:.4}",
+            x.label(), o.label(), self.games, self.x_wins, self.draws, self.o_wins,
+            self.rate(self.x_wins) / 100.0, self.rate(self.draws) / 100.0, self.rate(self.o_wins) / 100.0,
+            self.average_length());
+        for (x, counts) in self.first_moves.iter().enumerate() {
+            for (y, &count) in counts.iter().enumerate() {
+                let _ = write!(header, ",first_{}", position_name((x, y)));
+                let _ = write!(row, ",{}", count);
+            }
+        }
+        format!("{},games_per_second\n{},{:.1}\n", header, row, self.games_per_second())
+    }
+}
+
+/// Plays `games` games of `x` against `o` over up to `threads` threads.
+pub fn run(x: &Strategy, o: &Strategy, games: usize, threads: usize) -> io::Result<Report> {
+    let threads = threads.clamp(1, games.max(1));
+    let start = Instant::now();
+
+    let reports = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|index| {
+                let share = games / threads + usize::from(index < games % threads);
+                scope.spawn(move || play_games(x, o, share))
+            })
+            .collect();
+        handles.into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|_| Err(io::Error::other("a simulation thread panicked"))))
+            .collect::<io::Result<Vec<Report>>>()
+    })?;
+
+    let mut report = Report::default();
+    for part in &reports {
+        report.merge(part);
+    }
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
+
+fn play_games(x: &Strategy, o: &Strategy, games: usize) -> io::Result<Report> {
+    let mut engines = [x.start()?, o.start()?];
+    let mut report = Report::default();
+    for _ in 0..games {
+        let (game, moves) = play_game([x, o], &mut engines)?;
+        report.record(&game, &moves);
+    }
+    Ok(report)
+}
+
+/// One game and its moves, with X's strategy and engine (if any) first.
+pub fn play_game(strategies: [&Strategy; 2], engines: &mut [Option<ExternalEngine>; 2]) -> io::Result<(Game, Vec<(usize, usize)>)> {
+    for engine in engines.iter_mut().flatten() {
+        engine.new_game()?;
+    }
+
+    let set = PlayerSet { x: Player::X(strategies[0].player_type()), o: Player::O(strategies[1].player_type()) };
+    let mut game = Game::InPlay { set, turn: set.x, board: Board::default() };
+    let mut moves = Vec::new();
+    while let Game::InPlay { turn, board, .. } = game {
+        let side = usize::from(turn.associated_square() == Square::O);
+        game = strategies[side].play_turn(engines[side].as_mut(), &turn, &set, &board)?;
+        moves.extend(game.board().and_then(|after| board.changed_position(after)));
+    }
+    Ok((game, moves))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game::Difficulty;
+    use crate::mcts::Budget;
+
+    use super::{run, Report, Strategy};
+
+    #[test]
+    fn test_perfect_play_always_draws() {
+        let perfect = Strategy::Builtin(Difficulty::Perfect);
+        let report = run(&perfect, &perfect, 5, 2).unwrap();
+        assert_eq!((report.games, report.draws), (5, 5));
+        assert_eq!(report.moves, 45);
+        assert_eq!(report.first_moves[0][0], 5);
+    }
+
+    #[test]
+    fn test_perfect_never_loses_to_random() {
+        let report = run(&Strategy::Builtin(Difficulty::Random), &Strategy::Builtin(Difficulty::Perfect), 40, 4).unwrap();
+        assert_eq!(report.games, 40);
+        assert_eq!(report.x_wins, 0);
+        assert_eq!(report.first_moves.iter().flatten().sum::<usize>(), 40);
+        assert!(report.average_length() >= 5.0);
+    }
+
+    #[test]
+    fn test_mcts_never_loses_to_random() {
+        let report = run(&Strategy::Builtin(Difficulty::Random), &Strategy::Mcts(Budget::Iterations(3000)), 20, 4).unwrap();
+        assert_eq!(report.games, 20);
+        assert_eq!(report.x_wins, 0);
+    }
+
+    #[test]
+    fn test_labels_and_formats() {
+        assert_eq!(Strategy::from_label("easy"), Some(Strategy::Builtin(Difficulty::Easy)));
+        assert_eq!(Strategy::from_label("engine"), Some(Strategy::Engine(String::new())));
+        assert_eq!(Strategy::from_label("clever"), None);
+        assert_eq!(Strategy::from_label("mcts"), Some(Strategy::Builtin(Difficulty::Mcts)));
+        let timed = Strategy::from_label("mcts:50ms").unwrap();
+        assert_eq!(timed, Strategy::Mcts(Budget::Time(Duration::from_millis(50))));
+        assert_eq!(timed.label(), "mcts:50ms");
+        assert_eq!(Strategy::from_label("mcts:lots"), None);
+        assert_eq!(Strategy::from_label("easy:1"), None);
+
+        let mut report = Report { games: 4, x_wins: 1, draws: 2, o_wins: 1, moves: 30, ..Report::default() };
+        report.first_moves[1][1] = 4;
+        let (x, o) = (Strategy::Builtin(Difficulty::Easy), Strategy::Builtin(Difficulty::Random));
+
+        let csv = report.format_csv(&x, &o);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("x,o,games,x_wins,draws,o_wins,x_win_rate,draw_rate,o_win_rate,average_length,first_A1,"));
+        assert!(lines[1].starts_with("easy,random,4,1,2,1,0.2500,0.5000,0.2500,7.5000,0,0,0,0,4,0,"));
+        assert_eq!(lines[0].split(',').count(), lines[1].split(',').count());
+
+        let table = report.format_table(&x, &o);
+        assert!(table.starts_with("easy (X) vs random (O), 4 games\n"));
+        assert!(table.contains("   draws             2   50.0%\n"));
+        assert!(table.contains("   B             0.0%  100.0%    0.0%\n"));
+    }
+}
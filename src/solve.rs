@@ -0,0 +1,394 @@
+//! The whole game solved: every position reachable from the empty board,
+//! for `tic-tac-toe solve`, and the tablebase the `perfect` difficulty
+//! answers from.
+//!
+//! Walking every game from the empty board gives the known figures:
+//! 255,168 games, 5,478 positions and 765 positions once turns and mirror
+//! images are counted as one.  The tablebase keeps one entry per such
+//! canonical position (`Board::canonical`): its value to the side to move
+//! and every move that keeps that value, in the canonical orientation.
+//!
+//! The file is little-endian binary:
+//!
+//! ```text
+//! "TTTB"  1 (u8 version)  765 (u16 entries)
+//! then per entry, by board index:  index (u16)  value (i8)  moves (u16)
+//! ```
+//!
+//! A board's index reads its squares in notation order as a base-3 number,
+//! with empty 0, X 1 and O 2.  The value is 1 for a win, 0 for a draw and
+//! -1 for a loss, and bit `3x + y` of the moves is set for each best move
+//! `(x, y)`.  Finished positions have no moves.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::board::{untransform_position, Board, Square};
+use crate::search::{Outcome, Position};
+use crate::profile;
+
+const MAGIC: &[u8; 4] = b"TTTB";
+const VERSION: u8 = 1;
+const ENTRY_BYTES: usize = 5;
+
+/// A canonical position's value to the side to move and its best moves.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Entry {
+    pub value: i8,
+    /// Bit `3x + y` for each best move `(x, y)`.
+    pub moves: u16
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Tablebase {
+    entries: BTreeMap<u16, Entry>
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TablebaseError(pub String);
+
+impl fmt::Display for TablebaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The board's squares in notation order as a base-3 number.
+pub fn board_index(board: &Board) -> u16 {
+    board.squares.iter().flatten().fold(0, |index, square| index * 3 + match square {
+        Square::Empty => 0,
+        Square::X => 1,
+        Square::O => 2
+    })
+}
+
+pub fn board_from_index(mut index: u16) -> Board {
+    let mut board = Board::default();
+    for cell in (0..9).rev() {
+        board.squares[cell / 3][cell % 3] = match index % 3 {
+            0 => Square::Empty,
+            1 => Square::X,
+            _ => Square::O
+        };
+        index /= 3;
+    }
+    board
+}
+
+impl Tablebase {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entry for `board` turned into its canonical orientation, or
+    /// `None` if no game reaches it.
+    pub fn entry(&self, board: &Board) -> Option<Entry> {
+        self.entries.get(&board_index(&board.canonical().0)).copied()
+    }
+
+    /// Every move on `board` that keeps its value, in notation order.
+    pub fn best_moves(&self, board: &Board) -> Option<Vec<(usize, usize)>> {
+        let (canonical, symmetry) = board.canonical();
+        let entry = self.entries.get(&board_index(&canonical))?;
+        let mut moves: Vec<(usize, usize)> = (0..9)
+            .filter(|bit| entry.moves & (1 << bit) != 0)
+            .map(|bit| untransform_position((bit / 3, bit % 3), symmetry))
+            .collect();
+        moves.sort();
+        Some(moves)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(self.entries.len() as u16).to_le_bytes());
+        for (index, entry) in &self.entries {
+            bytes.extend_from_slice(&index.to_le_bytes());
+            bytes.push(entry.value as u8);
+            bytes.extend_from_slice(&entry.moves.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Tablebase, TablebaseError> {
+        let error = |message: &str| TablebaseError(message.to_string());
+        if bytes.len() < 7 || &bytes[..4] != MAGIC {
+            return Err(error("not a tablebase"));
+        }
+        if bytes[4] != VERSION {
+            return Err(TablebaseError(format!("unknown tablebase version {}", bytes[4])));
+        }
+        let count = u16::from_le_bytes([bytes[5], bytes[6]]) as usize;
+        let body = &bytes[7..];
+        if body.len() != count * ENTRY_BYTES {
+            return Err(error("the tablebase is the wrong length"));
+        }
+        let mut table = Tablebase::default();
+        for record in body.chunks(ENTRY_BYTES) {
+            let index = u16::from_le_bytes([record[0], record[1]]);
+            let entry = Entry { value: record[2] as i8, moves: u16::from_le_bytes([record[3], record[4]]) };
+            if index >= 19683 || !(-1..=1).contains(&entry.value) || entry.moves >= 1 << 9 {
+                return Err(TablebaseError(format!("bad entry for board {}", index)));
+            }
+            if table.entries.insert(index, entry).is_some() {
+                return Err(TablebaseError(format!("board {} is listed twice", index)));
+            }
+        }
+        Ok(table)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Tablebase> {
+        Tablebase::from_bytes(&fs::read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+/// What walking the whole game tree found.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Solution {
+    /// Distinct move sequences from the empty board to a finished game.
+    pub games: u64,
+    /// Of those, won by X, won by O and drawn.
+    pub x_wins: u64,
+    pub o_wins: u64,
+    pub draws: u64,
+    /// Distinct reachable positions, the empty board and finished ones
+    /// included.
+    pub positions: usize,
+    pub finished_positions: usize,
+    /// Reachable positions with symmetric ones counted once.
+    pub canonical_positions: usize,
+    pub tablebase: Tablebase
+}
+
+impl Solution {
+    pub fn format_report(&self) -> String {
+        let start = self.tablebase.entry(&Board::default()).map_or(0, |entry| entry.value);
+        let mut out = String::new();
+        let _ = writeln!(out, "   games                {:>7}", self.games);
+        let _ = writeln!(out, "     X wins             {:>7}", self.x_wins);
+        let _ = writeln!(out, "     O wins             {:>7}", self.o_wins);
+        let _ = writeln!(out, "     draws              {:>7}", self.draws);
+        let _ = writeln!(out, "   positions            {:>7}", self.positions);
+        let _ = writeln!(out, "     finished           {:>7}", self.finished_positions);
+        let _ = writeln!(out, "   up to symmetry       {:>7}", self.canonical_positions);
+        let _ = writeln!(out, "   with perfect play    {:>7}", match start { 1 => "X wins", -1 => "O wins", _ => "draw" });
+        out
+    }
+}
+
+struct Walk {
+    seen: HashSet<u16>,
+    finished: usize,
+    /// Games from each position to the end, with X wins, O wins and draws.
+    games: HashMap<u16, [u64; 3]>,
+    values: HashMap<u16, i8>
+}
+
+impl Walk {
+    /// Game counts from `board`, and fills in `seen` and `values`.
+    fn visit(&mut self, board: &Board) -> [u64; 3] {
+        let index = board_index(board);
+        if let Some(&games) = self.games.get(&index) {
+            return games;
+        }
+        self.seen.insert(index);
+
+        let (games, value) = match board.outcome() {
+            Some(outcome) => {
+                self.finished += 1;
+                match outcome {
+                    Outcome::Win(0) => ([1, 0, 0], -1),
+                    Outcome::Win(_) => ([0, 1, 0], -1),
+                    Outcome::Draw => ([0, 0, 1], 0)
+                }
+            },
+            None => {
+                let mut games = [0; 3];
+                let mut value = -1;
+                for pos in board.moves() {
+                    let after = board.play(pos);
+                    let below = self.visit(&after);
+                    for (total, count) in games.iter_mut().zip(below) {
+                        *total += count;
+                    }
+                    value = value.max(-self.values[&board_index(&after)]);
+                }
+                (games, value)
+            }
+        };
+        self.games.insert(index, games);
+        self.values.insert(index, value);
+        games
+    }
+}
+
+/// Walks every game from the empty board and builds the tablebase.
+pub fn solve() -> Solution {
+    let mut walk = Walk { seen: HashSet::new(), finished: 0, games: HashMap::new(), values: HashMap::new() };
+    let [x_wins, o_wins, draws] = walk.visit(&Board::default());
+
+    let mut tablebase = Tablebase::default();
+    for &index in &walk.seen {
+        let board = board_from_index(index);
+        let canonical = board_index(&board.canonical().0);
+        if tablebase.entries.contains_key(&canonical) {
+            continue;
+        }
+        let board = board_from_index(canonical);
+        let value = walk.values[&canonical];
+        let moves = board.moves().into_iter()
+            .filter(|&pos| -walk.values[&board_index(&board.play(pos))] == value)
+            .fold(0, |moves, (x, y)| moves | 1 << (x * 3 + y));
+        tablebase.entries.insert(canonical, Entry { value, moves });
+    }
+
+    Solution {
+        games: x_wins + o_wins + draws,
+        x_wins,
+        o_wins,
+        draws,
+        positions: walk.seen.len(),
+        finished_positions: walk.finished,
+        canonical_positions: tablebase.len(),
+        tablebase
+    }
+}
+
+/// `tablebase.bin` next to the profiles.
+pub fn default_path() -> PathBuf {
+    profile::default_path().with_file_name("tablebase.bin")
+}
+
+/// `checksum` of the bytes of the tablebase `solve` builds.
+const CHECKSUM: u64 = 0x5063_4511_3e2f_deb7;
+
+/// 64-bit FNV-1a: enough to tell a tablebase file that reads but is not
+/// the one `solve` writes, which a few bad moves would otherwise pass for.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// The tablebase in the file at `path`, if it is exactly the one `solve`
+/// builds.
+fn load_checked(path: &Path) -> Option<Tablebase> {
+    let bytes = fs::read(path).ok()?;
+    if checksum(&bytes) != CHECKSUM {
+        return None;
+    }
+    Tablebase::from_bytes(&bytes).ok()
+}
+
+static SHARED: OnceLock<Tablebase> = OnceLock::new();
+
+/// The tablebase behind the `perfect` difficulty: the one `solve` wrote to
+/// `default_path()` if it matches, otherwise one solved on first use.
+/// Unit tests always solve, whatever is in the user's profile directory.
+pub fn tablebase() -> &'static Tablebase {
+    SHARED.get_or_init(|| {
+        let saved = if cfg!(test) { None } else { load_checked(&default_path()) };
+        saved.unwrap_or_else(|| solve().tablebase)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+    use std::str::FromStr;
+
+    use crate::board::{parse_position, Board, Square};
+    use crate::game::{self, Player, PlayerSet, PlayerType};
+    use crate::search::Position;
+
+    use super::{board_from_index, board_index, checksum, load_checked, solve, Tablebase, CHECKSUM};
+
+    #[test]
+    fn test_known_counts() {
+        let solution = solve();
+        assert_eq!(solution.games, 255_168);
+        assert_eq!((solution.x_wins, solution.o_wins, solution.draws), (131_184, 77_904, 46_080));
+        assert_eq!(solution.positions, 5_478);
+        assert_eq!(solution.finished_positions, 958);
+        assert_eq!(solution.canonical_positions, 765);
+        assert!(solution.format_report().contains("with perfect play       draw"));
+
+        let table = &solution.tablebase;
+        let start = table.entry(&Board::default()).unwrap();
+        assert_eq!(start.value, 0);
+        assert_eq!(table.best_moves(&Board::default()).unwrap().len(), 9);
+
+        // Only the centre holds the draw against a corner.
+        let corner = Board::from_str("X--\n---\n---").unwrap();
+        assert_eq!(table.best_moves(&corner), Some(vec![parse_position("B2").unwrap()]));
+        let turned = Board::from_str("---\n---\n--X").unwrap();
+        assert_eq!(table.best_moves(&turned), Some(vec![parse_position("B2").unwrap()]));
+
+        let win = Board::from_str("XX-\nOO-\n---").unwrap();
+        assert_eq!(table.entry(&win).unwrap().value, 1);
+        assert_eq!(table.best_moves(&win), Some(vec![parse_position("A3").unwrap()]));
+        assert_eq!(table.best_moves(&Board::from_str("XXX\nOO-\n---").unwrap()), Some(vec![]));
+        assert_eq!(table.entry(&Board::from_str("XXX\nOOO\n---").unwrap()), None);
+
+        let bytes = table.to_bytes();
+        assert_eq!(bytes.len(), 7 + 765 * 5);
+        assert_eq!(&Tablebase::from_bytes(&bytes).unwrap(), table);
+        assert!(Tablebase::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Tablebase::from_bytes(b"TTTB\x02\x00\x00").is_err());
+        assert_eq!(checksum(&bytes), CHECKSUM, "{:#x}", checksum(&bytes));
+    }
+
+    #[test]
+    fn test_load_checked() {
+        let path = env::temp_dir().join(format!("tic-tac-toe-tablebase-{}.bin", process::id()));
+        let table = solve().tablebase;
+        table.save(&path).unwrap();
+        assert_eq!(load_checked(&path), Some(table.clone()));
+
+        // A well-formed table with one value changed is turned away.
+        let mut bytes = table.to_bytes();
+        bytes[9] = if bytes[9] == 0 { 1 } else { 0 };
+        fs::write(&path, &bytes).unwrap();
+        assert!(Tablebase::from_bytes(&bytes).is_ok());
+        assert_eq!(load_checked(&path), None);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(load_checked(&path), None);
+    }
+
+    #[test]
+    fn test_agrees_with_minimax() {
+        let solution = solve();
+        let set = PlayerSet { x: Player::X(PlayerType::Human), o: Player::O(PlayerType::Human) };
+        let mut checked = 0;
+        for (&index, entry) in &solution.tablebase.entries {
+            let board = board_from_index(index);
+            if board.get_empty_squares().len() > 6 || board.outcome().is_some() {
+                continue;
+            }
+            let turn = if board.side_to_move() == Some(Square::X) { set.x } else { set.o };
+            assert_eq!(i32::from(entry.value), game::perfect_score(&turn, &set, &board), "{}", board.notation());
+            checked += 1;
+        }
+        assert!(checked > 600);
+    }
+
+    #[test]
+    fn test_board_index() {
+        let board = Board::from_str("X-O\n-X-\n--O").unwrap();
+        assert_eq!(board_from_index(board_index(&board)), board);
+        assert_eq!(board_index(&Board::default()), 0);
+    }
+}
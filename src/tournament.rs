@@ -0,0 +1,466 @@
+//! Round-robin tournaments between strategies, for `tic-tac-toe
+//! tournament`, and the records that let `replay` check any game.
+//!
+//! Every pair of participants plays the same number of games with each
+//! colour, as a `PlayerSet` per game.  Ratings are a maximum-likelihood
+//! Elo fit over all the results, centred on 0, with one extra draw
+//! between every pair that met so that a player who never drops a point
+//! still gets a finite rating.  The `±` figure is an approximate 95%
+//! interval from the curvature of the fit.
+//!
+//! A record is plain text:
+//!
+//! ```text
+//! tic-tac-toe-tournament 1
+//! participant perfect
+//! participant engine:./bot --fast
+//! game 1 1 2 1/2 A1 B2 A3 A2 C2 B3 B1 C1 C3
+//! game 2 2 1 0-1 B2 A1 C3 A3 A2 C2 B1 B3 C1
+//! ```
+//!
+//! Participants are numbered from 1 in the order listed.  Each game line
+//! gives the game number, the X and O participants, the result (`1-0`,
+//! `0-1` or `1/2`) and the moves from the empty board.
+
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::board::{parse_position, position_name, Board, Square};
+use crate::game::{self, Game, Player, PlayerSet};
+use crate::simulate::{self, Strategy};
+
+const FILE_HEADER: &str = "tic-tac-toe-tournament 1";
+
+/// Elo points per factor of ten in the odds of scoring.
+const ELO_SCALE: f64 = 400.0;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Participant {
+    /// The label it was entered under, unique in a tournament.
+    pub name: String,
+    pub strategy: Strategy
+}
+
+impl Participant {
+    /// A strategy label, or `engine:` and a command.  Plain `engine` has an
+    /// empty command for the caller to fill in.
+    pub fn from_label(label: &str) -> Option<Participant> {
+        let strategy = match label.split_once(':') {
+            Some(("engine", command)) if !command.trim().is_empty() => Strategy::Engine(command.to_string()),
+            _ => Strategy::from_label(label)?
+        };
+        Some(Participant { name: label.to_string(), strategy })
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameRecord {
+    /// Indexes into the participants.
+    pub x: usize,
+    pub o: usize,
+    pub winner: Option<Square>,
+    pub moves: Vec<(usize, usize)>
+}
+
+impl GameRecord {
+    /// X's score: 1, ½ or 0.
+    pub fn x_score(&self) -> f64 {
+        match self.winner {
+            Some(Square::X) => 1.0,
+            Some(_) => 0.0,
+            None => 0.5
+        }
+    }
+
+    /// `1-0`, `0-1` or `1/2`.
+    pub fn result_label(&self) -> &'static str {
+        match self.winner {
+            Some(Square::X) => "1-0",
+            Some(_) => "0-1",
+            None => "1/2"
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rating {
+    pub elo: f64,
+    /// Half the width of the 95% interval; infinite without games.
+    pub margin: f64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tournament {
+    pub participants: Vec<Participant>,
+    /// In game number order, from 1.
+    pub games: Vec<GameRecord>
+}
+
+impl Tournament {
+    /// Plays `games` games each way between every pair of participants,
+    /// over up to `threads` threads.  Game numbers don't depend on the
+    /// thread count.
+    pub fn run(participants: Vec<Participant>, games: usize, threads: usize) -> io::Result<Tournament> {
+        let mut pairings = Vec::new();
+        for first in 0..participants.len() {
+            for second in first + 1..participants.len() {
+                pairings.push((first, second));
+                pairings.push((second, first));
+            }
+        }
+
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(vec![Vec::new(); pairings.len()]);
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.clamp(1, pairings.len().max(1)))
+                .map(|_| scope.spawn(|| -> io::Result<()> {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&(x, o)) = pairings.get(index) else { break; };
+                        let records = play_pairing(&participants, x, o, games)?;
+                        results.lock().unwrap_or_else(|poisoned| poisoned.into_inner())[index] = records;
+                    }
+                    Ok(())
+                }))
+                .collect();
+            workers.into_iter()
+                .try_for_each(|worker| worker.join().unwrap_or_else(|_| Err(io::Error::other("a tournament thread panicked"))))
+        })?;
+
+        let games = results.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()).into_iter().flatten().collect();
+        Ok(Tournament { participants, games })
+    }
+
+    /// Each participant's rating, in participant order.
+    pub fn ratings(&self) -> Vec<Rating> {
+        let count = self.participants.len();
+        // games[i][j] and score[i][j]: i's games and points against j.
+        let mut games = vec![vec![0.0; count]; count];
+        let mut score = vec![vec![0.0; count]; count];
+        for record in &self.games {
+            games[record.x][record.o] += 1.0;
+            games[record.o][record.x] += 1.0;
+            score[record.x][record.o] += record.x_score();
+            score[record.o][record.x] += 1.0 - record.x_score();
+        }
+        for i in 0..count {
+            for j in 0..count {
+                if games[i][j] > 0.0 {
+                    games[i][j] += 1.0;
+                    score[i][j] += 0.5;
+                }
+            }
+        }
+
+        let slope = 10f64.ln() / ELO_SCALE;
+        let expected = |difference: f64| 1.0 / (1.0 + 10f64.powf(-difference / ELO_SCALE));
+        // The slope of i's log-likelihood and how sharply it curves.
+        let derivatives = |elo: &[f64], i: usize| -> (f64, f64) {
+            (0..count).filter(|&j| games[i][j] > 0.0).fold((0.0, 0.0), |(gradient, information), j| {
+                let e = expected(elo[i] - elo[j]);
+                (gradient + score[i][j] - games[i][j] * e, information + games[i][j] * e * (1.0 - e))
+            })
+        };
+
+        // One Newton step per player at a time, until nothing moves.
+        let mut elo = vec![0.0; count];
+        for _ in 0..10_000 {
+            let mut largest = 0.0f64;
+            for i in 0..count {
+                let (gradient, information) = derivatives(&elo, i);
+                if information > 0.0 {
+                    let step = (gradient / (slope * information)).clamp(-ELO_SCALE, ELO_SCALE);
+                    elo[i] += step;
+                    largest = largest.max(step.abs());
+                }
+            }
+            let mean = elo.iter().sum::<f64>() / count.max(1) as f64;
+            elo.iter_mut().for_each(|rating| *rating -= mean);
+            if largest < 1e-6 {
+                break;
+            }
+        }
+
+        (0..count)
+            .map(|i| {
+                let (_, information) = derivatives(&elo, i);
+                let margin = if information > 0.0 { 1.96 / (slope * information.sqrt()) } else { f64::INFINITY };
+                Rating { elo: elo[i], margin }
+            })
+            .collect()
+    }
+
+    /// Participants by rating, with each one's score against every other.
+    pub fn format_crosstable(&self) -> String {
+        let ratings = self.ratings();
+        let mut order: Vec<usize> = (0..self.participants.len()).collect();
+        order.sort_by(|&a, &b| ratings[b].elo.total_cmp(&ratings[a].elo));
+
+        let mut points = vec![vec![0.0; self.participants.len()]; self.participants.len()];
+        let mut played = vec![0; self.participants.len()];
+        for record in &self.games {
+            points[record.x][record.o] += record.x_score();
+            points[record.o][record.x] += 1.0 - record.x_score();
+            played[record.x] += 1;
+            played[record.o] += 1;
+        }
+
+        let width = self.participants.iter().map(|p| p.name.chars().count()).max().unwrap_or(0).max(11);
+        let mut out = format!("   # {:<width$}    Elo     ±   score  games", "participant");
+        for rank in 1..=order.len() {
+            let _ = write!(out, " {:>6}", rank);
+        }
+        out.push('\n');
+
+        for (rank, &i) in order.iter().enumerate() {
+            let margin = if ratings[i].margin.is_finite() { format!("{:.0}", ratings[i].margin) } else { "-".to_string() };
+            let total: f64 = points[i].iter().sum();
+            let _ = write!(
+                out, "{:>4} {:<width$} {:>+6.0} {:>5} {:>7.1} {:>6}",
+                rank + 1, self.participants[i].name, ratings[i].elo, margin, total, played[i]);
+            for &j in &order {
+                if i == j {
+                    let _ = write!(out, " {:>6}", "-");
+                } else {
+                    let _ = write!(out, " {:>6.1}", points[i][j]);
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Tournament> {
+        let text = fs::read_to_string(path)?;
+        Tournament::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    /// The record file described in the module docs.
+    pub fn serialize(&self) -> String {
+        let mut out = format!("{}\n", FILE_HEADER);
+        for participant in &self.participants {
+            let _ = writeln!(out, "participant {}", participant.name);
+        }
+        for (number, record) in self.games.iter().enumerate() {
+            let _ = write!(out, "game {} {} {} {}", number + 1, record.x + 1, record.o + 1, record.result_label());
+            for &pos in &record.moves {
+                let _ = write!(out, " {}", position_name(pos));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Reads a record.  Moves are only checked by `replay`, so a record
+    /// with a disputed game still loads.
+    pub fn parse(text: &str) -> Result<Tournament, RecordError> {
+        let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line.trim())).filter(|(_, line)| !line.is_empty());
+        let error = |line: usize, message: String| RecordError { line, message };
+
+        if lines.next().map(|(_, line)| line) != Some(FILE_HEADER) {
+            return Err(error(1, "unknown file format or version".to_string()));
+        }
+
+        let mut tournament = Tournament { participants: Vec::new(), games: Vec::new() };
+        for (number, line) in lines {
+            if let Some(label) = line.strip_prefix("participant ") {
+                if !tournament.games.is_empty() {
+                    return Err(error(number, "participants come before games".to_string()));
+                }
+                let participant = Participant::from_label(label.trim())
+                    .ok_or_else(|| error(number, format!("unknown participant '{}'", label.trim())))?;
+                tournament.participants.push(participant);
+            } else if let Some(rest) = line.strip_prefix("game ") {
+                let record = parse_game(rest, tournament.games.len() + 1, tournament.participants.len())
+                    .map_err(|message| error(number, message))?;
+                tournament.games.push(record);
+            } else {
+                return Err(error(number, format!("unexpected line '{}'", line)));
+            }
+        }
+        Ok(tournament)
+    }
+
+    /// Plays game `number` (from 1) again from its moves, returning each
+    /// position and the game as the moves really went.
+    pub fn replay(&self, number: usize) -> Result<(Vec<Board>, GameRecord), String> {
+        let recorded = number.checked_sub(1).and_then(|index| self.games.get(index))
+            .ok_or_else(|| format!("there is no game {}", number))?;
+        let set = PlayerSet {
+            x: Player::X(self.participants[recorded.x].strategy.player_type()),
+            o: Player::O(self.participants[recorded.o].strategy.player_type())
+        };
+
+        let mut current = Game::InPlay { set, turn: set.x, board: Board::default() };
+        let mut positions = Vec::new();
+        for &pos in &recorded.moves {
+            let Game::InPlay { set, turn, board } = current else {
+                return Err(format!("moves continue after the game ended, at {}", position_name(pos)));
+            };
+            current = game::make_move(&pos, &board, &turn, &set)
+                .map_err(|_| format!("{} is played twice", position_name(pos)))?;
+            positions.extend(current.board().copied());
+        }
+        if let Game::InPlay { .. } = current {
+            return Err("the moves stop before the game is over".to_string());
+        }
+        Ok((positions, record(recorded.x, recorded.o, &current, recorded.moves.clone())))
+    }
+}
+
+fn parse_game(text: &str, expected_number: usize, participants: usize) -> Result<GameRecord, String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let [number, x, o, result, moves @ ..] = words.as_slice() else {
+        return Err("expected 'game <number> <x> <o> <result> <moves>'".to_string());
+    };
+    if number.parse() != Ok(expected_number) {
+        return Err(format!("expected game {}", expected_number));
+    }
+    let participant = |word: &str| match word.parse::<usize>() {
+        Ok(index) if (1..=participants).contains(&index) => Ok(index - 1),
+        _ => Err(format!("'{}' is not a participant", word))
+    };
+    let winner = match *result {
+        "1-0" => Some(Square::X),
+        "0-1" => Some(Square::O),
+        "1/2" => None,
+        _ => { return Err(format!("'{}' is not 1-0, 0-1 or 1/2", result)); }
+    };
+    let moves = moves.iter()
+        .map(|name| parse_position(name).ok_or_else(|| format!("bad move '{}'", name)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(GameRecord { x: participant(x)?, o: participant(o)?, winner, moves })
+}
+
+fn record(x: usize, o: usize, game: &Game, moves: Vec<(usize, usize)>) -> GameRecord {
+    let winner = match game {
+        Game::Win(winner, _) => Some(winner.associated_square()),
+        _ => None
+    };
+    GameRecord { x, o, winner, moves }
+}
+
+/// `games` games with participant `x` as X and `o` as O.
+fn play_pairing(participants: &[Participant], x: usize, o: usize, games: usize) -> io::Result<Vec<GameRecord>> {
+    let strategies = [&participants[x].strategy, &participants[o].strategy];
+    let mut engines = [strategies[0].start()?, strategies[1].start()?];
+    (0..games)
+        .map(|_| simulate::play_game(strategies, &mut engines).map(|(game, moves)| record(x, o, &game, moves)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Square;
+    use crate::game::Difficulty;
+    use crate::simulate::Strategy;
+
+    use super::{GameRecord, Participant, Tournament};
+
+    fn participants(labels: &[&str]) -> Vec<Participant> {
+        labels.iter().map(|label| Participant::from_label(label).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let tournament = Tournament::run(participants(&["perfect", "random", "easy"]), 6, 3).unwrap();
+        assert_eq!(tournament.games.len(), 3 * 2 * 6);
+        for (first, second) in [(0, 1), (1, 0), (0, 2), (2, 0), (1, 2), (2, 1)] {
+            assert_eq!(tournament.games.iter().filter(|g| (g.x, g.o) == (first, second)).count(), 6);
+        }
+        // Perfect play never loses.
+        for game in tournament.games.iter().filter(|game| game.x == 0 || game.o == 0) {
+            let perfect = if game.x == 0 { Square::X } else { Square::O };
+            assert!(game.winner.is_none() || game.winner == Some(perfect));
+        }
+
+        let ratings = tournament.ratings();
+        assert!(ratings[0].elo > ratings[2].elo && ratings[2].elo > ratings[1].elo);
+        assert!(ratings.iter().map(|r| r.elo).sum::<f64>().abs() < 1e-6);
+        assert!(ratings.iter().all(|r| r.margin.is_finite() && r.margin > 0.0));
+
+        let table = tournament.format_crosstable();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("   1 perfect "));
+        assert!(lines[3].starts_with("   3 random "));
+    }
+
+    #[test]
+    fn test_ratings_for_even_and_lopsided_results() {
+        let game = |x, o, winner| GameRecord { x, o, winner, moves: Vec::new() };
+        let even = Tournament { participants: participants(&["easy", "random"]), games: vec![game(0, 1, Some(Square::X)), game(1, 0, Some(Square::X))] };
+        let ratings = even.ratings();
+        assert!(ratings[0].elo.abs() < 1e-6 && ratings[1].elo.abs() < 1e-6);
+
+        let lopsided = Tournament { participants: participants(&["perfect", "random"]), games: vec![game(0, 1, Some(Square::X)); 20] };
+        let ratings = lopsided.ratings();
+        // 20.5 points of 21 is odds of 41 to 1, or about 645 Elo.
+        assert!((ratings[0].elo - ratings[1].elo - 400.0 * 41f64.log10()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_record_round_trip_and_replay() {
+        let tournament = Tournament::run(participants(&["perfect", "easy"]), 2, 1).unwrap();
+        let text = tournament.serialize();
+        assert!(text.starts_with("tic-tac-toe-tournament 1\nparticipant perfect\nparticipant easy\ngame 1 1 2 "));
+        let parsed = Tournament::parse(&text).unwrap();
+        assert_eq!(parsed, tournament);
+
+        for number in 1..=4 {
+            let (positions, replayed) = parsed.replay(number).unwrap();
+            assert_eq!(positions.len(), replayed.moves.len());
+            assert_eq!(replayed, parsed.games[number - 1]);
+        }
+        assert!(parsed.replay(0).is_err() && parsed.replay(5).is_err());
+
+        let engine = Participant::from_label("engine:./bot --fast").unwrap();
+        assert_eq!(engine.strategy, Strategy::Engine("./bot --fast".to_string()));
+        assert_eq!(Participant::from_label("perfect").unwrap().strategy, Strategy::Builtin(Difficulty::Perfect));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let header = "tic-tac-toe-tournament 1\nparticipant easy\nparticipant random\n";
+        let disputed = format!("{}game 1 1 2 0-1 A1 A1\n", header);
+        let tournament = Tournament::parse(&disputed).unwrap();
+        assert_eq!(tournament.replay(1), Err("A1 is played twice".to_string()));
+        let unfinished = Tournament::parse(&format!("{}game 1 1 2 1-0 A1 B1\n", header)).unwrap();
+        assert!(unfinished.replay(1).is_err());
+        let misrecorded = Tournament::parse(&format!("{}game 1 1 2 0-1 A1 B1 A2 B2 A3\n", header)).unwrap();
+        assert_eq!(misrecorded.replay(1).unwrap().1.winner, Some(Square::X));
+
+        for bad in [
+            "tic-tac-toe-tournament 2\n".to_string(),
+            format!("{}game 2 1 2 1-0 A1\n", header),
+            format!("{}game 1 1 3 1-0 A1\n", header),
+            format!("{}game 1 1 2 2-0 A1\n", header),
+            format!("{}game 1 1 2 1-0 D4\n", header),
+            format!("{}participant genius\n", header),
+            format!("{}move A1\n", header)
+        ] {
+            assert!(Tournament::parse(&bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use crate::board::{position_name, XPos, YPos};
+use crate::engine::{self, ExternalEngine};
+use crate::game::{self, Game, PlayerType};
+use crate::render::{Renderer, Theme};
+use crate::save;
+use crate::session::Session;
+
+/// Top-left corner of the board frame on screen.
+const BOARD_LEFT: u16 = 2;
+const BOARD_TOP: u16 = 2;
+const HISTORY_LEFT: u16 = 22;
+
+/// What the player asked for with a key press or click.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Nothing,
+    Place,
+    Quit,
+    /// Ctrl-C: save the game in progress, then quit.
+    Interrupt
+}
+
+/// Cursor and message state for the full-screen mode.  Kept apart from the
+/// terminal so key and mouse handling can be tested.
+pub struct Tui {
+    pub cursor: (usize, usize),
+    pub message: Option<String>,
+    /// The theme the board is drawn in, which decides where its cells are.
+    pub theme: Theme
+}
+
+impl Tui {
+    pub fn new(theme: Theme) -> Tui {
+        Tui { cursor: (XPos::B, YPos::_2), message: None, theme }
+    }
+
+    pub fn handle(&mut self, event: &Event) -> Action {
+        match event {
+            Event::Key(KeyEvent { code, modifiers, kind: KeyEventKind::Press, .. }) => {
+                let (x, y) = self.cursor;
+                match code {
+                    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => { return Action::Interrupt; },
+                    KeyCode::Up | KeyCode::Char('k') => { self.cursor = (x.saturating_sub(1), y); },
+                    KeyCode::Down | KeyCode::Char('j') => { self.cursor = ((x + 1).min(XPos::C), y); },
+                    KeyCode::Left | KeyCode::Char('h') => { self.cursor = (x, y.saturating_sub(1)); },
+                    KeyCode::Right | KeyCode::Char('l') => { self.cursor = (x, (y + 1).min(YPos::_3)); },
+                    KeyCode::Enter | KeyCode::Char(' ') => { return Action::Place; },
+                    KeyCode::Char('q') | KeyCode::Esc => { return Action::Quit; },
+                    _ => { }
+                }
+                Action::Nothing
+            },
+            Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(pos) = cell_at(self.theme, mouse.column, mouse.row) {
+                    self.cursor = pos;
+                }
+                Action::Nothing
+            },
+            _ => Action::Nothing
+        }
+    }
+}
+
+/// Screen column and row of the piece in a cell of a board drawn in
+/// `theme`: the middle of a grid cell, or an emoji's left half.
+pub fn cell_origin(theme: Theme, pos: (usize, usize)) -> (u16, u16) {
+    let (x, y) = (pos.0 as u16, pos.1 as u16);
+    match theme {
+        Theme::Ascii => (BOARD_LEFT + 2 + 4 * y, BOARD_TOP + 2 * x),
+        Theme::Unicode => (BOARD_LEFT + 2 + 4 * y, BOARD_TOP + 1 + 2 * x),
+        Theme::Emoji => (BOARD_LEFT + 2 * y, BOARD_TOP - 1 + x)
+    }
+}
+
+/// The cell under a screen position, anywhere inside its three columns,
+/// or the two an emoji takes up.
+pub fn cell_at(theme: Theme, column: u16, row: u16) -> Option<(usize, usize)> {
+    (XPos::A ..= XPos::C)
+        .flat_map(|x| (YPos::_1 ..= YPos::_3).map(move |y| (x, y)))
+        .find(|pos| {
+            let (origin, cell_row) = cell_origin(theme, *pos);
+            let (first, last) = if theme == Theme::Emoji { (origin, origin + 1) } else { (origin - 1, origin + 1) };
+            row == cell_row && (first..=last).contains(&column)
+        })
+}
+
+/// Raw mode and the alternate screen, given back when dropped so the
+/// terminal is restored even if the game panics.
+struct Screen;
+
+impl Screen {
+    fn enter(stdout: &mut io::Stdout) -> io::Result<Screen> {
+        terminal::enable_raw_mode()?;
+        let screen = Screen;
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture, Hide)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, DisableMouseCapture, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Plays `session` to the end in the alternate screen.  Returns early,
+/// with the game still in play, if the players quit.
+pub fn run(session: &mut Session, renderer: &Renderer, engine: Option<&mut ExternalEngine>) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    let screen = Screen::enter(&mut stdout)?;
+    let result = event_loop(session, renderer, engine, &mut stdout);
+    drop(screen);
+
+    if let Ok(Some(path)) = &result {
+        println!("Game saved. Resume with --resume {}", path.display());
+    }
+    result.map(|_| ())
+}
+
+fn event_loop(
+    session: &mut Session,
+    renderer: &Renderer,
+    mut engine: Option<&mut ExternalEngine>,
+    out: &mut impl Write) -> io::Result<Option<std::path::PathBuf>> {
+    let mut tui = Tui::new(renderer.theme);
+
+    loop {
+        draw(out, session, &tui, renderer)?;
+
+        let Game::InPlay { set, turn, board } = session.game else {
+            // Game over: leave the result up until a key is pressed.
+            if let Event::Key(_) = event::read()? {
+                return Ok(None);
+            }
+            continue;
+        };
+
+        if turn.player_type() != &PlayerType::Human {
+            // A short pause so the computer's move can be seen arriving.
+            std::thread::sleep(Duration::from_millis(250));
+            let updated_game = engine::play_turn(engine.as_deref_mut(), &turn, &set, &board)?;
+            session.advance(updated_game);
+            continue;
+        }
+
+        match tui.handle(&event::read()?) {
+            Action::Nothing => { },
+            Action::Quit => { return Ok(None); },
+            Action::Interrupt => {
+                let Some(saved) = session.snapshot() else { return Ok(None); };
+                let path = save::autosave_path();
+                saved.save(&path)?;
+                return Ok(Some(path));
+            },
+            Action::Place => {
+                match game::make_move(&tui.cursor, &board, &turn, &set) {
+                    Ok(updated_game) => {
+                        session.advance(updated_game);
+                        tui.message = None;
+                    },
+                    Err(_) => { tui.message = Some(format!("{} is taken", position_name(tui.cursor))); }
+                }
+            }
+        }
+    }
+}
+
+fn draw(out: &mut impl Write, session: &Session, tui: &Tui, renderer: &Renderer) -> io::Result<()> {
+    queue!(out, Clear(ClearType::All), MoveTo(0, 0), Print("Tic Tac Toe"))?;
+
+    let Some(board) = session.game.board() else { return out.flush(); };
+    let mut frame = Vec::new();
+    renderer.write_board(&mut frame, board, session.last_move())?;
+    for (offset, line) in String::from_utf8_lossy(&frame).lines().enumerate() {
+        queue!(out, MoveTo(BOARD_LEFT - 2, BOARD_TOP - 1 + offset as u16), Print(line))?;
+    }
+
+    if let Game::InPlay { .. } = session.game {
+        let (column, row) = cell_origin(tui.theme, tui.cursor);
+        let (x, y) = tui.cursor;
+        let piece = renderer.square(board.squares[x][y]);
+        // Emoji fill their cell; grid pieces are lit with the space either side.
+        let (column, cell) = if tui.theme == Theme::Emoji { (column, piece.to_string()) } else { (column - 1, format!(" {} ", piece)) };
+        queue!(
+            out,
+            MoveTo(column, row),
+            SetAttribute(Attribute::Reverse),
+            Print(cell),
+            SetAttribute(Attribute::Reset)
+        )?;
+    }
+
+    queue!(out, MoveTo(HISTORY_LEFT, BOARD_TOP - 1), Print("Moves"))?;
+    for (index, pos) in session.history.iter().enumerate() {
+        let mover = if index % 2 == 0 { "X" } else { "O" };
+        let line = format!("{:>2}. {} {}", index + 1, mover, position_name(*pos));
+        queue!(out, MoveTo(HISTORY_LEFT, BOARD_TOP + index as u16), Print(line))?;
+    }
+
+    let status = match &session.game {
+        Game::InPlay { turn, .. } => format!("{} to move", renderer.player(turn)),
+        Game::Win(winner, _) => format!("{} wins! Press any key.", renderer.player(winner)),
+        Game::Tie(_) => "Game is tied. Press any key.".to_string(),
+        Game::Uninitiated => String::new()
+    };
+    let help = "arrows/hjkl move, click selects, Enter places, q quits";
+    let status_row = BOARD_TOP + 9;
+    queue!(out, MoveTo(0, status_row), SetAttribute(Attribute::Reverse), Print(format!(" {} ", status)), SetAttribute(Attribute::Reset))?;
+    if let Some(message) = &tui.message {
+        queue!(out, Print(format!("  {}", message)))?;
+    }
+    queue!(out, MoveTo(0, status_row + 1), Print(help))?;
+
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+    use crate::board::Board;
+    use crate::render::{Renderer, Theme};
+
+    use super::{cell_at, cell_origin, Action, Tui};
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn click(column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE
+        })
+    }
+
+    #[test]
+    fn test_cursor_keys_stay_on_board() {
+        let mut tui = Tui::new(Theme::Unicode);
+        assert_eq!(tui.cursor, (1, 1));
+
+        for code in [KeyCode::Up, KeyCode::Char('k'), KeyCode::Left, KeyCode::Char('h')] {
+            assert_eq!(tui.handle(&key(code)), Action::Nothing);
+        }
+        assert_eq!(tui.cursor, (0, 0));
+
+        for code in [KeyCode::Down, KeyCode::Char('j'), KeyCode::Down, KeyCode::Right, KeyCode::Char('l')] {
+            tui.handle(&key(code));
+        }
+        assert_eq!(tui.cursor, (2, 2));
+    }
+
+    #[test]
+    fn test_actions() {
+        let mut tui = Tui::new(Theme::Unicode);
+        assert_eq!(tui.handle(&key(KeyCode::Enter)), Action::Place);
+        assert_eq!(tui.handle(&key(KeyCode::Char('q'))), Action::Quit);
+        let ctrl_c = Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert_eq!(tui.handle(&ctrl_c), Action::Interrupt);
+    }
+
+    #[test]
+    fn test_mouse_selects_cell() {
+        for theme in Theme::ALL {
+            for x in 0..3 {
+                for y in 0..3 {
+                    let (column, row) = cell_origin(theme, (x, y));
+                    assert_eq!(cell_at(theme, column, row), Some((x, y)));
+                    assert_eq!(cell_at(theme, column + 1, row), Some((x, y)));
+                }
+            }
+        }
+        for theme in [Theme::Ascii, Theme::Unicode] {
+            let (column, row) = cell_origin(theme, (0, 0));
+            assert_eq!(cell_at(theme, column - 1, row), Some((0, 0)));
+            assert_eq!(cell_at(theme, column, row + 1), None);
+            assert_eq!(cell_at(theme, column - 2, row), None);
+        }
+        // Emoji rows follow one another with nothing between them.
+        let (column, row) = cell_origin(Theme::Emoji, (0, 1));
+        assert_eq!(cell_at(Theme::Emoji, column - 1, row), Some((0, 0)));
+        assert_eq!(cell_at(Theme::Emoji, column, row + 1), Some((1, 1)));
+
+        let mut tui = Tui::new(Theme::Unicode);
+        let (column, row) = cell_origin(Theme::Unicode, (2, 0));
+        assert_eq!(tui.handle(&click(column, row)), Action::Nothing);
+        assert_eq!(tui.cursor, (2, 0));
+    }
+
+    #[test]
+    fn test_cells_match_rendered_board() {
+        // The grid themes' pieces land where `cell_origin` says, the board
+        // drawn from the screen's top-left corner as `draw` does.  Their
+        // characters are all one column wide, so columns are characters.
+        let board = Board::from_str("XOX\nO-O\nXO-").unwrap();
+        for theme in [Theme::Ascii, Theme::Unicode] {
+            let mut frame = Vec::new();
+            Renderer::new(theme).write_board(&mut frame, &board, None).unwrap();
+            let lines: Vec<Vec<char>> = String::from_utf8(frame).unwrap().lines().map(|line| line.chars().collect()).collect();
+            for (x, y) in [(0, 0), (0, 1), (1, 2), (2, 0)] {
+                let (column, row) = cell_origin(theme, (x, y));
+                let piece = Renderer::new(theme).square(board.squares[x][y]);
+                assert_eq!(lines[row as usize - 1][column as usize].to_string(), piece, "{:?} {:?}", theme, (x, y));
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! The browser page served by `tic-tac-toe serve`, next to the API.
+//!
+//! The page, script and stylesheet are compiled into the binary, so the
+//! server needs no files on disk and the page loads nothing from elsewhere.
+
+use crate::api::Api;
+use crate::http::{Handler, Request, Response};
+
+const INDEX_HTML: &str = include_str!("web/index.html");
+const APP_JS: &str = include_str!("web/app.js");
+const STYLE_CSS: &str = include_str!("web/style.css");
+
+/// Serves the page at `/` and passes everything else to the API.
+#[derive(Default)]
+pub struct Web {
+    api: Api
+}
+
+impl Handler for Web {
+    fn handle(&self, request: &Request) -> Response {
+        let asset = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/" | "/index.html") => Some(("text/html; charset=utf-8", INDEX_HTML)),
+            ("GET", "/app.js") => Some(("text/javascript; charset=utf-8", APP_JS)),
+            ("GET", "/style.css") => Some(("text/css; charset=utf-8", STYLE_CSS)),
+            _ => None
+        };
+        match asset {
+            Some((content_type, body)) => Response::new(200, content_type, body.to_string()),
+            None => self.api.handle(request)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Difficulty, PlayerType};
+    use crate::http::{Handler, Request};
+
+    use super::{Web, APP_JS, INDEX_HTML, STYLE_CSS};
+
+    #[test]
+    fn test_serves_assets_and_api() {
+        let web = Web::default();
+        let page = web.handle(&Request::new("GET", "/", ""));
+        assert_eq!(page.status, 200);
+        assert_eq!(page.content_type, "text/html; charset=utf-8");
+        assert!(page.body.contains("<script src=\"/app.js\">"));
+
+        assert_eq!(web.handle(&Request::new("GET", "/app.js", "")).content_type, "text/javascript; charset=utf-8");
+        assert_eq!(web.handle(&Request::new("GET", "/style.css", "")).content_type, "text/css; charset=utf-8");
+
+        let created = web.handle(&Request::new("POST", "/games", ""));
+        assert_eq!(created.status, 201);
+        assert_eq!(web.handle(&Request::new("GET", "/favicon.ico", "")).status, 404);
+    }
+
+    #[test]
+    fn test_assets_are_self_contained() {
+        for asset in [INDEX_HTML, APP_JS, STYLE_CSS] {
+            assert!(!asset.contains("http://") && !asset.contains("https://") && !asset.contains("src=\"//"));
+        }
+    }
+
+    #[test]
+    fn test_page_offers_every_player_type() {
+        // Every type but the engine, which the API refuses.
+        let types = Difficulty::ALL.into_iter().map(PlayerType::Computer).chain([PlayerType::Human]);
+        for label in types.map(PlayerType::label) {
+            assert!(INDEX_HTML.contains(&format!("value=\"{}\"", label)), "{}", label);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// Plays through the JSON API served alongside this page.  The game ID lives
+// in the address (#game=3) so a second browser can join the same game.
+
+const ROWS = ["A", "B", "C"];
+const board = document.getElementById("board");
+const status = document.getElementById("status");
+const share = document.getElementById("share");
+let game = null;
+let busy = false;
+// The last refused move or failed request, shown until the game moves on.
+let problem = null;
+
+for (const row of ROWS) {
+  for (let column = 1; column <= 3; column++) {
+    const cell = document.createElement("button");
+    cell.dataset.square = row + column;
+    cell.setAttribute("aria-label", row + column);
+    cell.disabled = true;
+    cell.addEventListener("click", () => play(cell.dataset.square));
+    board.appendChild(cell);
+  }
+}
+
+async function api(method, path, body) {
+  const response = await fetch(path, {
+    method,
+    headers: { "Content-Type": "application/json" },
+    body: body === undefined ? undefined : JSON.stringify(body)
+  });
+  const json = await response.json();
+  if (!response.ok) {
+    throw new Error(json.error ? json.error.message : response.statusText);
+  }
+  return json;
+}
+
+function players() {
+  return game ? game.players : {};
+}
+
+function isHuman(mark) {
+  return players()[mark.toLowerCase()] === "human";
+}
+
+function render() {
+  const line = game.winning_line || [];
+  for (const cell of board.children) {
+    const square = cell.dataset.square;
+    const mark = game.board[ROWS.indexOf(square[0])][Number(square[1]) - 1];
+    cell.textContent = mark === "-" ? "" : mark;
+    cell.className = mark === "-" ? "" : mark.toLowerCase();
+    cell.classList.toggle("last", square === game.last_move);
+    cell.classList.toggle("win", line.includes(square));
+    cell.disabled = busy || mark !== "-" || game.status !== "in_play" || !isHuman(game.turn);
+  }
+
+  if (problem) {
+    status.textContent = problem;
+  } else if (game.status === "won") {
+    status.textContent = game.winner + " wins!";
+  } else if (game.status === "tied") {
+    status.textContent = "Game is tied";
+  } else if (isHuman(game.turn)) {
+    status.textContent = game.turn + " to move";
+  } else {
+    status.textContent = game.turn + " (computer) is thinking...";
+  }
+  share.hidden = false;
+}
+
+function fail(error) {
+  problem = error.message;
+  if (game) {
+    render();
+  } else {
+    status.textContent = problem;
+  }
+}
+
+async function update(next) {
+  game = next;
+  problem = null;
+  location.hash = "game=" + game.id;
+  render();
+  if (game.status === "in_play" && !isHuman(game.turn)) {
+    // A short pause so the computer's move can be seen arriving.
+    await new Promise(resolve => setTimeout(resolve, 300));
+    await update(await api("POST", "/games/" + game.id + "/computer-move"));
+  }
+}
+
+async function play(square) {
+  busy = true;
+  render();
+  try {
+    await update(await api("POST", "/games/" + game.id + "/moves", { square }));
+  } catch (error) {
+    fail(error);
+  } finally {
+    busy = false;
+    render();
+  }
+}
+
+document.getElementById("new-game").addEventListener("submit", async event => {
+  event.preventDefault();
+  const form = new FormData(event.target);
+  try {
+    await update(await api("POST", "/games", { x: form.get("x"), o: form.get("o") }));
+  } catch (error) {
+    fail(error);
+  }
+});
+
+// Keep the board live while someone else, in another browser, moves.
+setInterval(async () => {
+  if (!game || busy || game.status !== "in_play" || !isHuman(game.turn)) return;
+  try {
+    const latest = await api("GET", "/games/" + game.id);
+    if (latest.moves.length !== game.moves.length) {
+      game = latest;
+      problem = null;
+      render();
+    }
+  } catch (error) {
+    fail(error);
+  }
+}, 1000);
+
+const joined = /game=(\d+)/.exec(location.hash);
+if (joined) {
+  api("GET", "/games/" + joined[1]).then(update, fail);
+}
@@ -0,0 +1,78 @@
+/* Plays a few games through include/tic_tac_toe.h; run by tests/ffi.rs. */
+#include <stdio.h>
+#include <string.h>
+
+#include "tic_tac_toe.h"
+
+#define CHECK(call, expected) do { \
+        int code = (call); \
+        if (code != (expected)) { \
+            fprintf(stderr, "%s:%d: %s returned %d (%s), expected %d\n", \
+                    __FILE__, __LINE__, #call, code, ttt_error_message(code), (expected)); \
+            return 1; \
+        } \
+    } while (0)
+
+static int human_game(void) {
+    ttt_game *game = NULL;
+    char board[TTT_BOARD_SIZE];
+    char mark = 0;
+    int status = -1;
+
+    CHECK(ttt_game_new("human", NULL, &game), TTT_OK);
+    CHECK(ttt_make_move(game, "B2"), TTT_OK);
+    CHECK(ttt_make_move(game, "B2"), TTT_ERR_SQUARE_TAKEN);
+    CHECK(ttt_make_move(game, "Z9"), TTT_ERR_BAD_SQUARE);
+    CHECK(ttt_game_turn(game, &mark), TTT_OK);
+    CHECK(mark == 'O', 1);
+    CHECK(ttt_make_move(game, "A1"), TTT_OK);
+    CHECK(ttt_make_move(game, "A3"), TTT_OK);
+    CHECK(ttt_make_move(game, "C3"), TTT_OK);
+    CHECK(ttt_make_move(game, "C1"), TTT_OK);
+
+    CHECK(ttt_game_board(game, board, sizeof board), TTT_OK);
+    CHECK(strcmp(board, "O-X/-X-/X-O"), 0);
+    CHECK(ttt_game_status(game, &status), TTT_OK);
+    CHECK(status, TTT_X_WON);
+    CHECK(ttt_make_move(game, "A2"), TTT_ERR_GAME_OVER);
+    ttt_game_free(game);
+    return 0;
+}
+
+static int computer_game(void) {
+    ttt_game *game = NULL;
+    int status = TTT_IN_PLAY;
+
+    CHECK(ttt_game_new("computer:perfect", "computer:perfect", &game), TTT_OK);
+    CHECK(ttt_make_move(game, "A1"), TTT_ERR_COMPUTER_TURN);
+    while (status == TTT_IN_PLAY) {
+        CHECK(ttt_computer_move(game), TTT_OK);
+        CHECK(ttt_game_status(game, &status), TTT_OK);
+    }
+    CHECK(status, TTT_TIED);
+    ttt_game_free(game);
+    return 0;
+}
+
+static int bad_arguments(void) {
+    ttt_game *game = NULL;
+    char small[4];
+
+    CHECK(ttt_game_new("wizard", NULL, &game), TTT_ERR_BAD_PLAYER);
+    CHECK(game == NULL, 1);
+    CHECK(ttt_computer_move(NULL), TTT_ERR_NULL);
+    CHECK(ttt_game_new(NULL, NULL, &game), TTT_OK);
+    CHECK(ttt_game_board(game, small, sizeof small), TTT_ERR_BUFFER_TOO_SMALL);
+    CHECK(ttt_computer_move(game), TTT_ERR_HUMAN_TURN);
+    ttt_game_free(game);
+    ttt_game_free(NULL);
+    return 0;
+}
+
+int main(void) {
+    if (human_game() || computer_game() || bad_arguments()) {
+        return 1;
+    }
+    puts("ok");
+    return 0;
+}
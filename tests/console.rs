@@ -0,0 +1,172 @@
+use std::env;
+use std::fs;
+use std::io;
+
+use tic_tac_toe::console::{Console, Script};
+use tic_tac_toe::game::{Difficulty, Game, Player, PlayerType};
+use tic_tac_toe::render::{Renderer, Theme};
+use tic_tac_toe::session::Session;
+
+const MENU: &str = "Tic Tac Toe.  Enter an option: (X goes first)\n\
+                    1. X: Human, O: Computer\n\
+                    2: X: Human, O: Human\n\
+                    3: X: Computer, O: Human\n";
+
+const DIFFICULTY_MENU: &str = "Computer difficulty:\n1. random\n2. easy\n3. perfect\n4. mcts\n5. learner\n6. rules\n";
+
+fn run_script(lines: &[&str]) -> (io::Result<Session>, String) {
+    let mut input = Script::new(lines);
+    let mut output = Vec::new();
+    let result = Console::new(&mut input, &mut output, Renderer::new(Theme::Ascii)).run(None);
+    (result, String::from_utf8(output).unwrap())
+}
+
+/// The ASCII board for three rows of `X`, `O` and `-`.
+fn board(rows: [&str; 3]) -> String {
+    let mut out = String::from("    1   2   3\n");
+    for (index, (letter, row)) in ["A", "B", "C"].iter().zip(rows).enumerate() {
+        let cells: Vec<String> = row.chars()
+            .map(|c| format!(" {} ", if c == '-' { ' ' } else { c }))
+            .collect();
+        out.push_str(format!("{}  {}", letter, cells.join("|")).trim_end());
+        out.push('\n');
+        if index < 2 {
+            out.push_str("   ---+---+---\n");
+        }
+    }
+    out
+}
+
+fn prompt(player: &str) -> String {
+    format!("{}, please enter move A1 thru C3 (or save <file>, load <file>):\n", player)
+}
+
+fn name_prompt(player: &str) -> String {
+    format!("{}, enter your profile name (or press enter to play as a guest):\n", player)
+}
+
+#[test]
+fn human_vs_human_transcript() {
+    let (result, transcript) = run_script(&["x", "2", "ann", "", "Z1", "A9", "B2", "b2", "A1", "A2", "C1", "C2"]);
+    let session = result.unwrap();
+
+    let empty = board(["---", "---", "---"]);
+    let expected = [
+        MENU.to_string(),
+        "Please enter a number\n".to_string(),
+        MENU.to_string(),
+        name_prompt("X"),
+        name_prompt("O"),
+        empty.clone(),
+        prompt("X"),
+        "Letter. Please enter Letter (A-C) & Number (1-3) format. ie: A1, C2, etc\n".to_string(),
+        empty.clone(),
+        prompt("X"),
+        "Number. Please enter Letter (A-C) & Number (1-3) format. ie: A1, C2, etc\n".to_string(),
+        empty,
+        prompt("X"),
+        board(["---", "-X-", "---"]),
+        prompt("O"),
+        "Can't move there. Please choose another move. PlacementError\n".to_string(),
+        board(["---", "-X-", "---"]),
+        prompt("O"),
+        board(["O--", "-X-", "---"]),
+        prompt("X"),
+        board(["OX-", "-X-", "---"]),
+        prompt("O"),
+        board(["OX-", "-X-", "O--"]),
+        prompt("X"),
+        "X Wins!\n".to_string(),
+        board(["OX-", "-X-", "OX-"])
+    ].concat();
+
+    assert_eq!(transcript, expected);
+    assert_eq!(session.names.x.as_deref(), Some("ann"));
+    assert_eq!(session.names.o, None);
+    assert_eq!(session.history, vec![(1, 1), (0, 0), (0, 1), (2, 0), (2, 1)]);
+    assert!(matches!(session.game, Game::Win(Player::X(PlayerType::Human), _)));
+}
+
+#[test]
+fn perfect_computer_never_loses() {
+    let (result, transcript) = run_script(&["1", "7", "3", "", "A1", "C3", "A3", "B1", "C2", "A2", "B3"]);
+    let session = result.unwrap();
+
+    let opening = [MENU, DIFFICULTY_MENU, "invalid input, try again\n", DIFFICULTY_MENU].concat();
+    assert!(transcript.starts_with(&opening));
+    assert_eq!(session.set.o, Player::O(PlayerType::Computer(Difficulty::Perfect)));
+    match session.game {
+        Game::Tie(_) => assert!(transcript.contains("Game is tied\n")),
+        Game::Win(winner, _) => assert_eq!(winner, session.set.o),
+        ref game => panic!("game did not finish: {:?}", game)
+    }
+}
+
+#[test]
+fn computer_moves_first_when_x() {
+    let (result, transcript) = run_script(&["3", "3", "bo"]);
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+    let expected = [
+        MENU.to_string(),
+        DIFFICULTY_MENU.to_string(),
+        name_prompt("O"),
+        board(["X--", "---", "---"]),
+        prompt("O")
+    ].concat();
+    assert_eq!(transcript, expected);
+}
+
+#[test]
+fn save_and_load_at_the_prompt() {
+    let path = env::temp_dir().join(format!("tic-tac-toe-console-{}.txt", std::process::id()));
+    let path_str = path.to_str().unwrap();
+    let save = format!("save {}", path_str);
+    let load = format!("load {}", path_str);
+
+    let (result, transcript) = run_script(&["2", "", "", "A1", &save, "B2", &load, "B2", "A2", "C3", "A3"]);
+    let saved = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let session = result.unwrap();
+
+    assert!(transcript.contains(&format!("Game saved to {}\n", path_str)));
+    assert!(transcript.contains(&format!("Loaded {}\n", path_str)));
+    assert!(saved.contains("moves A1\n"));
+    // B2 was played before the load, then again after it.
+    assert_eq!(session.history, vec![(0, 0), (1, 1), (0, 1), (2, 2), (0, 2)]);
+    assert!(matches!(session.game, Game::Win(Player::X(_), _)));
+}
+
+#[test]
+fn rules_computer_explains_its_moves() {
+    let (result, transcript) = run_script(&["1", "6", "", "A1", "A2"]);
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+    let expected = [
+        prompt("X"),
+        "O is taking the centre at B2\n".to_string(),
+        board(["X--", "-O-", "---"]),
+        prompt("X"),
+        "O is blocking your line at A3\n".to_string(),
+        board(["XXO", "-O-", "---"]),
+        prompt("X")
+    ].concat();
+    assert!(transcript.ends_with(&expected), "{}", transcript);
+}
+
+#[test]
+fn coach_reviews_human_moves() {
+    let mut input = Script::new(&["2", "", "", "A1", "B2", "C3", "A3"]);
+    let mut output = Vec::new();
+    let result = Console::new(&mut input, &mut output, Renderer::new(Theme::Ascii)).with_coach().run(None);
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+    let transcript = String::from_utf8(output).unwrap();
+    let coaching: Vec<&str> = transcript.lines().filter(|line| line.starts_with("Coach: ")).collect();
+    assert_eq!(coaching, [
+        "Coach: A1 keeps the draw.",
+        "Coach: B2 keeps the draw.",
+        "Coach: C3 keeps the draw.",
+        "Coach: A3 turns the draw into a loss: this lets X fork at C1."
+    ]);
+}
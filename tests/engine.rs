@@ -0,0 +1,99 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use tic_tac_toe::board::Board;
+use tic_tac_toe::console::{Console, Script};
+use tic_tac_toe::engine::{ExternalEngine, Limits};
+use tic_tac_toe::game::{Game, Player, PlayerType};
+use tic_tac_toe::render::{Renderer, Theme};
+
+const BINARY: &str = env!("CARGO_BIN_EXE_tic-tac-toe");
+
+fn engine_command() -> String {
+    format!("{} engine", BINARY)
+}
+
+#[test]
+fn engine_mode_speaks_the_protocol_on_stdio() {
+    let mut child = Command::new(BINARY)
+        .arg("engine")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let commands = "tttp\nsetoption name difficulty value perfect\nnewgame\n\
+                    position startpos moves A1 B1 A2\ngo movetime 50\nquit\n";
+    child.stdin.take().unwrap().write_all(commands.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let replies = String::from_utf8(output.stdout).unwrap();
+    // O has to block A3, but X then forks with B2.
+    assert!(replies.ends_with("tttpok\ninfo depth 4 nodes 72 score -1\nbestmove A3 score -1\n"), "{}", replies);
+}
+
+#[test]
+fn engine_mode_takes_stop_during_a_search() {
+    let mut child = Command::new(BINARY)
+        .arg("engine")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"position startpos\ngo\nstop\nisready\nquit\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let replies = String::from_utf8(output.stdout).unwrap();
+    // Whether the search finishes before `stop` lands is a race, so the
+    // score may or may not be there.
+    let lines: Vec<&str> = replies.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", replies);
+    assert!(lines[0].starts_with("info depth "), "{}", replies);
+    assert!(lines[1] == "bestmove A1" || lines[1] == "bestmove A1 score 0", "{}", replies);
+    assert_eq!(lines[2], "readyok");
+}
+
+#[test]
+fn external_engine_reports_moves() {
+    let mut engine = ExternalEngine::spawn(&engine_command()).unwrap();
+    assert!(engine.name.starts_with("tic-tac-toe "));
+    engine.new_game().unwrap();
+    engine.limits = Limits { depth: Some(9), ..Limits::default() };
+
+    let board = Board::from_str("OO-\nXX-\nX--").unwrap();
+    let best = engine.best_move(&board).unwrap();
+    assert_eq!(best.pos, (0, 2));
+    assert_eq!(best.score, Some(1));
+
+    engine.set_option("difficulty", "random").unwrap();
+    let board = Board::from_str("XOX\nXOO\nOX-").unwrap();
+    assert_eq!(engine.best_move(&board).unwrap().pos, (2, 2));
+}
+
+#[test]
+fn external_engine_gives_up_on_a_silent_program() {
+    let started = Instant::now();
+    let error = ExternalEngine::spawn_with_timeout("sleep 30", Duration::from_millis(200)).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn console_seats_the_engine_as_the_computer() {
+    let engine = ExternalEngine::spawn(&engine_command()).unwrap();
+    // Every square in turn, so each prompt finds a free one eventually.
+    let mut input = Script::new(&["1", "", "B2", "A1", "A2", "A3", "B1", "B3", "C1", "C2", "C3"]);
+    let mut output = Vec::new();
+    let session = Console::new(&mut input, &mut output, Renderer::new(Theme::Ascii))
+        .with_engine(engine)
+        .run(None)
+        .unwrap();
+
+    let transcript = String::from_utf8(output).unwrap();
+    assert!(!transcript.contains("Computer difficulty:"));
+    assert_eq!(session.set.o, Player::O(PlayerType::Engine));
+    assert!(matches!(session.game, Game::Tie(_) | Game::Win(Player::O(_), _)), "{:?}", session.game);
+}
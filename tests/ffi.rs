@@ -0,0 +1,62 @@
+// With the `python` feature the static library also needs libpython.
+#![cfg(all(target_os = "linux", not(feature = "python")))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const ROOT: &str = env!("CARGO_MANIFEST_DIR");
+
+/// `target/debug`, or wherever cargo put the binary.  Libraries built for
+/// tests stay in its `deps`.
+fn build_dir() -> PathBuf {
+    Path::new(env!("CARGO_BIN_EXE_tic-tac-toe")).parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn c_program_runs_against_the_static_library() {
+    let build = build_dir();
+    let program = build.join("ffi_test");
+    let compiled = Command::new("cc")
+        .arg(Path::new(ROOT).join("tests/c/ffi_test.c"))
+        .arg("-I").arg(Path::new(ROOT).join("include"))
+        .args(["-Wall", "-Werror"])
+        .arg(build.join("deps/libtic_tac_toe.a"))
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&program)
+        .status()
+        .unwrap();
+    assert!(compiled.success());
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
+
+/// The checked-in header must be what build.rs generates from src/ffi.rs.
+/// cbindgen only runs with the `header` feature, so the exports are also
+/// checked by name for builds without it.
+#[test]
+fn header_is_generated_from_source() {
+    let header = fs::read_to_string(Path::new(ROOT).join("include/tic_tac_toe.h")).unwrap();
+    let source = fs::read_to_string(Path::new(ROOT).join("src/ffi.rs")).unwrap();
+    assert!(header.contains("Generated from src/ffi.rs by build.rs"));
+
+    #[cfg(feature = "header")]
+    {
+        let generated = Path::new(env!("OUT_DIR")).join("tic_tac_toe.h");
+        assert!(header == fs::read_to_string(&generated).unwrap(), "include/tic_tac_toe.h is out of date; copy {} over it", generated.display());
+    }
+
+    for line in source.lines() {
+        if let Some(rest) = line.split("extern \"C\" fn ").nth(1) {
+            let name = rest.split('(').next().unwrap();
+            assert!(header.contains(&format!(" {}(", name)) || header.contains(&format!("*{}(", name)), "{} is not in the header", name);
+        }
+        if let Some(rest) = line.strip_prefix("pub const ") {
+            let (name, value) = rest.split_once(':').unwrap();
+            let value = value.split('=').nth(1).unwrap().trim().trim_end_matches(';');
+            assert!(header.contains(&format!("#define {} {}\n", name, value)), "{} = {} is not in the header", name, value);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use tic_tac_toe::api::Api;
+use tic_tac_toe::http;
+use tic_tac_toe::json::Json;
+
+fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || http::serve(listener, Arc::new(Api::default())));
+    addr
+}
+
+/// Sends one request and returns the status and the raw response.
+fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method, path, body.len(), body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    (status, response)
+}
+
+fn json_body(response: &str) -> Json {
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    Json::parse(body).unwrap()
+}
+
+#[test]
+fn play_against_the_computer_over_http() {
+    let addr = start_server();
+
+    let (status, response) = request(addr, "POST", "/games", r#"{"x": "human", "o": "computer:perfect"}"#);
+    assert_eq!(status, 201);
+    assert!(response.contains("Content-Type: application/json\r\n"));
+    assert!(response.contains("Access-Control-Allow-Origin: *\r\n"));
+    let id = match json_body(&response).get("id") {
+        Some(Json::Number(id)) => *id as u64,
+        other => panic!("no id: {:?}", other)
+    };
+
+    let moves = format!("/games/{}/moves", id);
+    let computer = format!("/games/{}/computer-move", id);
+    let mut game = json_body(&response);
+    // Take the first open square each turn; the computer must not lose.
+    while game.get("status").and_then(Json::as_str) == Some("in_play") {
+        let (status, response) = if game.get("turn").and_then(Json::as_str) == Some("X") {
+            let Some(Json::Array(rows)) = game.get("board") else { panic!("no board") };
+            let square = rows.iter().enumerate()
+                .flat_map(|(x, row)| row.as_str().unwrap().chars().enumerate()
+                    .filter(|(_, c)| *c == '-')
+                    .map(move |(y, _)| format!("{}{}", ["A", "B", "C"][x], y + 1)))
+                .next()
+                .unwrap();
+            request(addr, "POST", &moves, &format!("{{\"square\": \"{}\"}}", square))
+        } else {
+            request(addr, "POST", &computer, "")
+        };
+        assert_eq!(status, 200, "{}", response);
+        game = json_body(&response);
+    }
+    assert_ne!(game.get("winner").and_then(Json::as_str), Some("X"));
+
+    let (status, response) = request(addr, "POST", &moves, r#"{"square": "A1"}"#);
+    assert_eq!(status, 409);
+    assert_eq!(json_body(&response).get("error").and_then(|e| e.get("code")).and_then(Json::as_str), Some("game_over"));
+}
+
+#[test]
+fn games_are_kept_apart() {
+    let addr = start_server();
+    request(addr, "POST", "/games", "");
+    request(addr, "POST", "/games", "");
+
+    let (status, _) = request(addr, "POST", "/games/2/moves", r#"{"square": "C3"}"#);
+    assert_eq!(status, 200);
+    let (_, first) = request(addr, "GET", "/games/1", "");
+    let (_, second) = request(addr, "GET", "/games/2", "");
+    assert_eq!(json_body(&first).get("moves").unwrap().to_string(), "[]");
+    assert_eq!(json_body(&second).get("moves").unwrap().to_string(), r#"["C3"]"#);
+
+    let (status, response) = request(addr, "POST", "/games/2/moves", r#"{"square": "C3"}"#);
+    assert_eq!(status, 409);
+    assert!(response.ends_with(r#"{"error":{"code":"square_taken","message":"C3 is already taken"}}"#));
+
+    let (status, _) = request(addr, "OPTIONS", "/games", "");
+    assert_eq!(status, 204);
+}
+
+#[test]
+fn hostile_requests_are_refused_without_harm() {
+    let addr = start_server();
+
+    let nested = "[".repeat(60_000);
+    let (status, response) = request(addr, "POST", "/games", &nested);
+    assert_eq!(status, 400);
+    assert!(response.contains("nested too deeply"), "{}", response);
+
+    // The server answers before reading the rest, so the connection may
+    // be reset under us; all that matters is that the server lives on.
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let _ = write!(stream, "GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(32 * 1024));
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    // A reset can also cut the answer short.
+    let refused = "HTTP/1.1 400 ";
+    assert!(refused.starts_with(&response[..response.len().min(refused.len())]), "{}", response);
+
+    let (status, _) = request(addr, "POST", "/games", "");
+    assert_eq!(status, 201);
+}
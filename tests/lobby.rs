@@ -0,0 +1,199 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use tic_tac_toe::lobby;
+
+fn start_lobby() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || lobby::serve(listener));
+    addr
+}
+
+/// One person in the lobby, typing commands and checking replies.
+struct Peer {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream
+}
+
+impl Peer {
+    fn connect(addr: SocketAddr, name: &str) -> Peer {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut peer = Peer { reader: BufReader::new(stream.try_clone().unwrap()), stream };
+        peer.expect("LOBBY tic-tac-toe 1");
+        peer.send(&format!("NAME {}", name));
+        peer.expect(&format!("WELCOME {}", name));
+        peer
+    }
+
+    fn send(&mut self, line: &str) {
+        writeln!(self.stream, "{}", line).unwrap();
+    }
+
+    fn line(&mut self) -> String {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        line.trim_end().to_string()
+    }
+
+    fn expect(&mut self, expected: &str) {
+        assert_eq!(self.line(), expected);
+    }
+}
+
+#[test]
+fn matchmaking_spectators_and_chat() {
+    let addr = start_lobby();
+    let mut ann = Peer::connect(addr, "ann");
+    let mut bob = Peer::connect(addr, "bob");
+    let mut cat = Peer::connect(addr, "cat");
+
+    ann.send("PLAY");
+    ann.expect("WAITING 1");
+    cat.send("LIST");
+    cat.expect("GAME 1 open ann - 0");
+    cat.expect("END");
+
+    bob.send("PLAY");
+    bob.expect("START 1 O ann");
+    bob.expect("STATE 1 TURN X ---/---/---");
+    ann.expect("START 1 X bob");
+    ann.expect("STATE 1 TURN X ---/---/---");
+
+    cat.send("WATCH 1");
+    cat.expect("WATCHING 1 ann bob");
+    cat.expect("STATE 1 TURN X ---/---/---");
+    cat.send("LIST");
+    cat.expect("GAME 1 playing ann bob 1");
+    cat.expect("END");
+
+    ann.send("SAY good luck, bob!");
+    for peer in [&mut ann, &mut bob, &mut cat] {
+        peer.expect("CHAT 1 ann good luck, bob!");
+    }
+
+    bob.send("MOVE A1");
+    bob.expect("ERROR it is not your turn");
+    cat.send("MOVE A1");
+    cat.expect("ERROR spectators can't move");
+
+    let moves = [
+        ("ann", "B2", "TURN O ---/-X-/---"),
+        ("bob", "A1", "TURN X O--/-X-/---"),
+        ("ann", "C1", "TURN O O--/-X-/X--"),
+        ("bob", "A3", "TURN X O-O/-X-/X--"),
+        ("ann", "A2", "TURN O OXO/-X-/X--"),
+        ("bob", "C2", "TURN X OXO/-X-/XO-"),
+        ("ann", "C3", "TURN O OXO/-X-/XOX"),
+        ("bob", "B1", "TURN X OXO/OX-/XOX"),
+        ("ann", "B3", "TIE OXO/OXX/XOX")
+    ];
+    for (mover, square, state) in moves {
+        let peer = if mover == "ann" { &mut ann } else { &mut bob };
+        peer.send(&format!("MOVE {}", square));
+        for peer in [&mut ann, &mut bob, &mut cat] {
+            peer.expect(&format!("STATE 1 {}", state));
+        }
+    }
+
+    ann.send("MOVE A1");
+    ann.expect("ERROR the game is over");
+    cat.send("LIST");
+    cat.expect("END");
+}
+
+#[test]
+fn players_can_be_paired_with_bots() {
+    let addr = start_lobby();
+    let mut dan = Peer::connect(addr, "dan");
+
+    dan.send("PLAY bot wizard");
+    dan.expect("ERROR unknown difficulty 'wizard'");
+    dan.send("PLAY bot perfect");
+    dan.expect("START 1 X bot:perfect");
+
+    // Play the first open square until the game ends; the bot answers each
+    // move straight away.
+    loop {
+        let state = dan.line();
+        let words: Vec<&str> = state.split_whitespace().collect();
+        match words.as_slice() {
+            ["STATE", "1", "TURN", "X", board] => {
+                let index = board.replace('/', "").find('-').unwrap();
+                dan.send(&format!("MOVE {}{}", ["A", "B", "C"][index / 3], index % 3 + 1));
+            },
+            ["STATE", "1", "TURN", "O", _] => { },
+            ["STATE", "1", "WIN", winner, _] => {
+                assert_eq!(*winner, "O");
+                break;
+            },
+            ["STATE", "1", "TIE", _] => break,
+            _ => panic!("unexpected {}", state)
+        }
+    }
+}
+
+#[test]
+fn leaving_abandons_a_running_game() {
+    let addr = start_lobby();
+    let mut eve = Peer::connect(addr, "eve");
+    let mut fay = Peer::connect(addr, "fay");
+
+    eve.send("CREATE");
+    eve.expect("WAITING 1");
+    fay.send("JOIN 1");
+    fay.expect("START 1 O eve");
+    fay.expect("STATE 1 TURN X ---/---/---");
+    eve.expect("START 1 X fay");
+    eve.expect("STATE 1 TURN X ---/---/---");
+
+    fay.send("QUIT");
+    eve.expect("OVER 1 abandoned fay");
+    eve.send("MOVE A1");
+    eve.expect("ERROR the game is over");
+
+    // The name is free again once its connection has gone.
+    let _fay = Peer::connect(addr, "fay");
+    eve.send("JOIN 1");
+    eve.expect("ERROR table 1 is not open");
+}
+
+#[test]
+fn a_spectator_leaving_keeps_an_open_table() {
+    let addr = start_lobby();
+    let mut ann = Peer::connect(addr, "ann");
+    let mut bob = Peer::connect(addr, "bob");
+    let mut cat = Peer::connect(addr, "cat");
+
+    ann.send("CREATE");
+    ann.expect("WAITING 1");
+    bob.send("WATCH 1");
+    bob.expect("WATCHING 1 ann -");
+    bob.expect("STATE 1 TURN X ---/---/---");
+    bob.send("LEAVE");
+    bob.expect("OK");
+
+    cat.send("JOIN 1");
+    cat.expect("START 1 O ann");
+    ann.expect("START 1 X cat");
+}
+
+#[test]
+fn overlong_lines_close_the_connection() {
+    let addr = start_lobby();
+    let mut gus = Peer::connect(addr, "gus");
+    gus.send(&format!("SAY {}", "a".repeat(4096)));
+    // Closed with the rest unread, which may reach us as a reset.
+    let mut rest = String::new();
+    let _ = gus.reader.read_line(&mut rest);
+    assert_eq!(rest, "");
+
+    // Everyone else is unaffected.
+    let mut hal = Peer::connect(addr, "hal");
+    hal.send("LIST");
+    hal.expect("END");
+}
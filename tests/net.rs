@@ -0,0 +1,201 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use tic_tac_toe::board::Square;
+use tic_tac_toe::console::Script;
+use tic_tac_toe::game::{Game, Player};
+use tic_tac_toe::net::{Client, Server};
+use tic_tac_toe::render::{Renderer, Theme};
+
+fn start_server() -> (SocketAddr, thread::JoinHandle<Game>) {
+    let server = Server::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    (addr, thread::spawn(move || server.run().unwrap()))
+}
+
+/// A raw protocol connection, speaking and checking lines by hand.
+struct Peer {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream
+}
+
+impl Peer {
+    fn connect(addr: SocketAddr, hello: &str) -> Peer {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut peer = Peer { reader: BufReader::new(stream.try_clone().unwrap()), stream };
+        peer.send(hello);
+        peer
+    }
+
+    fn send(&mut self, line: &str) {
+        writeln!(self.stream, "{}", line).unwrap();
+    }
+
+    fn expect(&mut self, expected: &str) {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        assert_eq!(line.trim_end(), expected);
+    }
+
+    fn expect_closed(&mut self) {
+        let mut line = String::new();
+        assert_eq!(self.reader.read_line(&mut line).unwrap(), 0, "unexpected line {:?}", line);
+    }
+}
+
+#[test]
+fn handshake_rejects_other_versions() {
+    let (addr, _) = start_server();
+
+    let mut old = Peer::connect(addr, "HELLO tic-tac-toe 0");
+    old.expect("ERROR version 0 is not supported, this host speaks 1");
+    old.expect_closed();
+
+    let mut stranger = Peer::connect(addr, "GET / HTTP/1.1");
+    stranger.expect("ERROR expected HELLO");
+    stranger.expect_closed();
+}
+
+#[test]
+fn host_checks_every_move() {
+    let (addr, server) = start_server();
+
+    let mut x = Peer::connect(addr, "HELLO tic-tac-toe 1");
+    x.expect("WELCOME 1 X");
+    x.expect("WAITING O");
+    x.send("MOVE A1");
+    x.expect("ERROR waiting for O to connect");
+
+    let mut o = Peer::connect(addr, "HELLO tic-tac-toe 1");
+    o.expect("WELCOME 1 O");
+    o.expect("STATE TURN X ---/---/---");
+    x.expect("STATE TURN X ---/---/---");
+
+    let mut third = Peer::connect(addr, "HELLO tic-tac-toe 1");
+    third.expect("ERROR game is full");
+    third.expect_closed();
+
+    o.send("MOVE A1");
+    o.expect("ERROR it is not your turn");
+    x.send("MOVE A1");
+    x.expect("STATE TURN O X--/---/---");
+    o.expect("STATE TURN O X--/---/---");
+    o.send("MOVE A1");
+    o.expect("ERROR A1 is taken");
+    o.send("MOVE D7");
+    o.expect("ERROR 'D7' is not a square");
+
+    o.send("MOVE B1");
+    x.expect("STATE TURN X X--/O--/---");
+    o.expect("STATE TURN X X--/O--/---");
+    x.send("MOVE A2");
+    x.expect("STATE TURN O XX-/O--/---");
+    o.expect("STATE TURN O XX-/O--/---");
+    o.send("MOVE B2");
+    x.expect("STATE TURN X XX-/OO-/---");
+    o.expect("STATE TURN X XX-/OO-/---");
+    x.send("MOVE A3");
+    x.expect("STATE WIN X XXX/OO-/---");
+    o.expect("STATE WIN X XXX/OO-/---");
+    x.send("MOVE C3");
+    x.expect("ERROR the game is over");
+
+    let game = server.join().unwrap();
+    assert!(matches!(game, Game::Win(Player::X(_), _)));
+}
+
+#[test]
+fn dropped_player_can_reconnect() {
+    let (addr, _) = start_server();
+
+    let mut x = Peer::connect(addr, "HELLO tic-tac-toe 1");
+    x.expect("WELCOME 1 X");
+    x.expect("WAITING O");
+    let mut o = Peer::connect(addr, "HELLO tic-tac-toe 1");
+    o.expect("WELCOME 1 O");
+    o.expect("STATE TURN X ---/---/---");
+    x.expect("STATE TURN X ---/---/---");
+    x.send("MOVE B2");
+    x.expect("STATE TURN O ---/-X-/---");
+    o.expect("STATE TURN O ---/-X-/---");
+
+    drop(o);
+    x.expect("WAITING O");
+
+    let mut impostor = Peer::connect(addr, "HELLO tic-tac-toe 1 X");
+    impostor.expect("ERROR seat X is taken");
+
+    let mut o = Peer::connect(addr, "HELLO tic-tac-toe 1 O");
+    o.expect("WELCOME 1 O");
+    o.expect("STATE TURN O ---/-X-/---");
+    x.expect("STATE TURN O ---/-X-/---");
+    o.send("MOVE A1");
+    x.expect("STATE TURN X O--/-X-/---");
+}
+
+#[test]
+fn client_plays_from_script() {
+    let (addr, server) = start_server();
+
+    let mut o = Peer::connect(addr, "HELLO tic-tac-toe 1 O");
+    o.expect("WELCOME 1 O");
+    o.expect("WAITING X");
+
+    let x = thread::spawn(move || {
+        let mut input = Script::new(&["B2", "Z9", "A3", "A1", "C3"]);
+        let mut output = Vec::new();
+        let mut client = Client::new(&addr.to_string(), Some(Square::X));
+        let game = client.run(&mut input, &mut output, &Renderer::new(Theme::Ascii)).unwrap();
+        (game, String::from_utf8(output).unwrap())
+    });
+
+    o.expect("STATE TURN X ---/---/---");
+    o.expect("STATE TURN O ---/-X-/---");
+    o.send("MOVE A3");
+    o.expect("STATE TURN X --O/-X-/---");
+    o.expect("STATE TURN O X-O/-X-/---");
+    o.send("MOVE B3");
+    o.expect("STATE TURN X X-O/-XO/---");
+    o.expect("STATE WIN X X-O/-XO/--X");
+
+    let (game, transcript) = x.join().unwrap();
+    assert!(matches!(game, Game::Win(Player::X(_), _)));
+    assert!(matches!(server.join().unwrap(), Game::Win(Player::X(_), _)));
+    assert!(transcript.starts_with(&format!("Connected to {} as X\n", addr)));
+    assert!(transcript.contains("Please enter Letter (A-C) & Number (1-3) format. ie: A1, C2, etc\n"));
+    assert!(transcript.contains("A3 is taken, please choose another square.\n"));
+    assert!(transcript.contains("Waiting for O to move...\n"));
+    assert!(transcript.contains("X Wins!\n"));
+}
+
+#[test]
+fn client_chooses_again_when_the_host_refuses_a_move() {
+    // A host that turns the first move down whatever it is.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let host = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut host = Peer { reader: BufReader::new(stream.try_clone().unwrap()), stream };
+        host.expect("HELLO tic-tac-toe 1 X");
+        host.send("WELCOME 1 X");
+        host.send("STATE TURN X ---/---/---");
+        host.expect("MOVE B2");
+        host.send("ERROR B2 is taken");
+        host.expect("MOVE A1");
+        host.send("STATE TIE XOX/XOO/OXX");
+        host.expect("BYE");
+    });
+
+    let mut input = Script::new(&["B2", "A1"]);
+    let mut output = Vec::new();
+    let mut client = Client::new(&addr.to_string(), Some(Square::X));
+    let game = client.run(&mut input, &mut output, &Renderer::new(Theme::Ascii)).unwrap();
+    host.join().unwrap();
+    assert!(matches!(game, Game::Tie(_)));
+    assert!(String::from_utf8(output).unwrap().contains("Host says: B2 is taken\nX, please enter move A1 thru C3:\n"));
+}
@@ -0,0 +1,24 @@
+#![cfg(all(feature = "python", target_os = "linux"))]
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const ROOT: &str = env!("CARGO_MANIFEST_DIR");
+
+#[test]
+fn python_tests_pass_against_the_extension_module() {
+    // Libraries built for tests sit in `deps` next to the binary.
+    let build = Path::new(env!("CARGO_BIN_EXE_tic-tac-toe")).parent().unwrap();
+    let module_dir = build.join("python");
+    fs::create_dir_all(&module_dir).unwrap();
+    fs::copy(build.join("deps/libtic_tac_toe.so"), module_dir.join("tic_tac_toe.so")).unwrap();
+
+    let output = Command::new("python3")
+        .args(["-m", "unittest", "discover", "-s"])
+        .arg(Path::new(ROOT).join("python/tests"))
+        .env("PYTHONPATH", &module_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
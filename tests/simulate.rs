@@ -0,0 +1,46 @@
+use std::process::Command;
+
+const BINARY: &str = env!("CARGO_BIN_EXE_tic-tac-toe");
+
+fn simulate(args: &[&str]) -> Vec<Vec<String>> {
+    let output = Command::new(BINARY).arg("simulate").args(args).arg("--csv").output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+        .lines()
+        .map(|line| line.split(',').map(str::to_string).collect())
+        .collect()
+}
+
+fn column(rows: &[Vec<String>], name: &str) -> String {
+    let index = rows[0].iter().position(|header| header == name).unwrap();
+    rows[1][index].clone()
+}
+
+#[test]
+fn simulate_prints_csv() {
+    let rows = simulate(&["easy", "perfect", "--games", "60", "--threads", "3"]);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(column(&rows, "games"), "60");
+    assert_eq!(column(&rows, "x_wins"), "0");
+    let firsts: usize = rows[0].iter().zip(&rows[1])
+        .filter(|(header, _)| header.starts_with("first_"))
+        .map(|(_, count)| count.parse::<usize>().unwrap())
+        .sum();
+    assert_eq!(firsts, 60);
+}
+
+#[test]
+fn simulate_plays_an_external_engine() {
+    let engine = format!("{} engine", BINARY);
+    let rows = simulate(&["engine", "perfect", "--games", "4", "--threads", "2", "--engine", &engine]);
+    assert_eq!(column(&rows, "x"), "engine");
+    assert_eq!(column(&rows, "draws"), "4");
+    assert_eq!(column(&rows, "average_length"), "9.0000");
+}
+
+#[test]
+fn simulate_needs_an_engine_command_for_engine() {
+    let output = Command::new(BINARY).args(["simulate", "engine", "easy"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--engine"));
+}
@@ -0,0 +1,40 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+
+const BINARY: &str = env!("CARGO_BIN_EXE_tic-tac-toe");
+
+#[test]
+fn tournament_records_games_that_replay() {
+    let record = env::temp_dir().join(format!("tic-tac-toe-tournament-{}.txt", std::process::id()));
+    let output = Command::new(BINARY)
+        .args(["tournament", "perfect", "random", "easy", "--games", "3", "--threads", "2", "--record"])
+        .arg(&record)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.lines().nth(1).unwrap().starts_with("   1 perfect "), "{}", table);
+
+    let text = fs::read_to_string(&record).unwrap();
+    assert_eq!(text.lines().filter(|line| line.starts_with("game ")).count(), 3 * 2 * 3);
+
+    let replay = |number: &str| Command::new(BINARY)
+        .args(["replay"]).arg(&record).args([number, "--theme", "ascii", "--color", "never"])
+        .output()
+        .unwrap();
+    let output = replay("1");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("as recorded."));
+
+    // Claim the first game went the other way.
+    let first = text.lines().find(|line| line.starts_with("game 1 ")).unwrap();
+    let flipped = if first.contains(" 1-0 ") { first.replace(" 1-0 ", " 0-1 ") } else { first.replacen(" 1/2 ", " 0-1 ", 1).replacen(" 0-1 ", " 1-0 ", 1) };
+    fs::write(&record, text.replace(first, &flipped)).unwrap();
+    let output = replay("1");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("but the record says"));
+
+    assert_eq!(replay("99").status.code(), Some(1));
+    fs::remove_file(&record).unwrap();
+}